@@ -0,0 +1,79 @@
+//! Counts the allocations of `Regex::is_match` over many lines, asserting there are
+//! none for patterns without backreferences, whether they contain groups or not.
+//! Repeated groups are covered both when their iterations are counted in a loop and
+//! when they are matched recursively on a stack that may be grown.
+//!
+//! Run with `cargo bench --bench allocations`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use codecrafters_grep::Regex;
+
+/// Counts every allocation before passing it on to the system allocator.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Returns lines of prose, every tenth one containing "needle".
+fn lines(count: usize) -> Vec<String> {
+    (0..count)
+        .map(|index| match index % 10 {
+            0 => format!("a needle in line {} of the haystack", index),
+            _ => format!("the quick brown fox jumps over line {}", index),
+        })
+        .collect()
+}
+
+/// Returns the number of matching lines and the number of allocations while matching.
+fn count_allocations(regex: &Regex, lines: &[String]) -> (usize, usize) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let matches = lines.iter().filter(|line| regex.is_match(black_box(line))).count();
+
+    (matches, ALLOCATIONS.load(Ordering::Relaxed) - before)
+}
+
+fn main() {
+    let lines = lines(100_000);
+    let patterns = [
+        "needle",
+        "ne+dle \\w+",
+        "[a-z]+dle",
+        "e{2}dle in line \\d{1,3}",
+        "[a-z]{3,} [0-9]?",
+        "n(e)(e)dle",
+        "(needle|pin) in",
+        "(ck)+",
+        "(ne)+edle",
+        "(\\w+ )+needle",
+        "(n)eedle.*\\1",
+    ];
+
+    println!("{:<20} {:>10} {:>12}", "pattern", "matches", "allocations");
+    for pattern in patterns {
+        let regex = Regex::compile(pattern);
+        let (matches, allocations) = count_allocations(&regex, &lines);
+
+        println!("{:<20} {:>10} {:>12}", pattern, matches, allocations);
+
+        // Only backreferences need the captured texts, which are recorded in a map.
+        if !pattern.contains("\\1") {
+            assert_eq!(0, allocations, "Matching '{}' allocated", pattern);
+        }
+    }
+}
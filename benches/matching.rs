@@ -0,0 +1,138 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use codecrafters_grep::{match_pattern, Engine, Regex};
+
+/// Returns a text of roughly the given byte length made of repeated prose.
+fn haystack(len: usize) -> String {
+    let prose = "the quick brown fox jumps over the lazy dog 42 times, ";
+    prose.repeat(len / prose.len() + 1)[..len].to_string()
+}
+
+/// Returns the pattern `a?{n}a{n}`, which backtracks through all ways of splitting
+/// the text between the optional and the required items before matching.
+fn pathological_pattern(n: usize) -> String {
+    format!("{}{}", "a?".repeat(n), "a".repeat(n))
+}
+
+fn bench_literal(c: &mut Criterion) {
+    let text = haystack(10_000) + "needle";
+
+    c.bench_function("literal search", |b| {
+        b.iter(|| match_pattern(black_box(&text), black_box("needle")))
+    });
+}
+
+fn bench_literal_run(c: &mut Criterion) {
+    let text = haystack(10_000);
+    let regex = Regex::compile("the quick brown fox jumps over the lazy cat");
+
+    c.bench_function("long literal miss", |b| {
+        b.iter(|| regex.is_match(black_box(&text)))
+    });
+}
+
+fn bench_word_run(c: &mut Criterion) {
+    let text = haystack(10_000);
+    let regex = Regex::compile("\\w+");
+
+    c.bench_function("word run find_iter", |b| {
+        b.iter(|| regex.find_iter(black_box(&text)).count())
+    });
+}
+
+fn bench_pathological(c: &mut Criterion) {
+    let regex = Regex::compile(&pathological_pattern(12));
+    let text = "a".repeat(12);
+
+    c.bench_function("catastrophic backtracking", |b| {
+        b.iter(|| regex.is_match(black_box(&text)))
+    });
+}
+
+fn bench_pathological_nfa(c: &mut Criterion) {
+    let regex = Regex::compile(&pathological_pattern(12))
+        .with_engine(Engine::Nfa)
+        .unwrap();
+    let text = "a".repeat(12);
+
+    c.bench_function("catastrophic backtracking nfa", |b| {
+        b.iter(|| regex.is_match(black_box(&text)))
+    });
+}
+
+fn bench_alternation(c: &mut Criterion) {
+    let text = haystack(2_000) + "horses";
+    let regex = Regex::compile("(cat|mouse|bird|fish|cow|horse)s");
+
+    c.bench_function("alternation", |b| {
+        b.iter(|| regex.is_match(black_box(&text)))
+    });
+}
+
+fn bench_alternation_long_line(c: &mut Criterion) {
+    let text = haystack(100_000) + "horses";
+    let regex = Regex::compile("(cat|mouse|bird|fish|cow|horse)s");
+
+    c.bench_function("alternation captures long line", |b| {
+        b.iter(|| regex.captures(black_box(&text)).is_some())
+    });
+}
+
+/// Returns an alternation of many words, none of which occur in the haystack.
+fn word_alternation() -> String {
+    let words: Vec<String> = (0..50).map(|i| format!("word{}", i)).collect();
+    format!("({})", words.join("|"))
+}
+
+fn bench_literal_alternation(c: &mut Criterion) {
+    let text = haystack(10_000);
+    let regex = Regex::compile(&word_alternation());
+
+    c.bench_function("literal alternation", |b| {
+        b.iter(|| regex.is_match(black_box(&text)))
+    });
+}
+
+fn bench_literal_alternation_naive(c: &mut Criterion) {
+    let text = haystack(10_000);
+    // The nested group keeps the pattern from being searched as a set of literals.
+    let regex = Regex::compile(&format!("({})", word_alternation()));
+
+    c.bench_function("literal alternation naive", |b| {
+        b.iter(|| regex.is_match(black_box(&text)))
+    });
+}
+
+fn bench_start_anchored(c: &mut Criterion) {
+    let text = haystack(100_000);
+    let regex = Regex::compile("^the quick brown cat");
+
+    c.bench_function("start anchored miss", |b| {
+        b.iter(|| regex.is_match(black_box(&text)))
+    });
+}
+
+fn bench_end_anchored(c: &mut Criterion) {
+    let text = haystack(10_000);
+    let regex = Regex::compile("\\d+ dogs$");
+
+    c.bench_function("end anchored miss", |b| {
+        b.iter(|| regex.is_match(black_box(&text)))
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_literal,
+    bench_literal_run,
+    bench_word_run,
+    bench_pathological,
+    bench_pathological_nfa,
+    bench_alternation,
+    bench_alternation_long_line,
+    bench_literal_alternation,
+    bench_literal_alternation_naive,
+    bench_start_anchored,
+    bench_end_anchored
+);
+criterion_main!(benches);
@@ -0,0 +1,130 @@
+//! Compares `Regex::is_match` against the `regex` crate.
+//!
+//! The input is a pattern and a text separated by a NUL byte. Only patterns from the
+//! subset both engines agree on are compared, see [`is_supported`], and only ASCII
+//! texts, as the `regex` crate has Unicode aware classes.
+//!
+//! Run from the repository root, starting from the seeds derived from the regression tests:
+//!
+//! ```text
+//! cargo +nightly fuzz run matches_regex_crate fuzz/corpus/matches_regex_crate fuzz/seeds/matches_regex_crate
+//! ```
+
+#![no_main]
+
+use codecrafters_grep::Regex;
+use libfuzzer_sys::fuzz_target;
+
+/// Longer inputs only slow down the search, backtracking is exponential in the worst case.
+const MAX_PATTERN_LEN: usize = 32;
+const MAX_TEXT_LEN: usize = 64;
+
+fuzz_target!(|data: &[u8]| {
+    let Some(separator) = data.iter().position(|b| *b == 0) else {
+        return;
+    };
+
+    let (Ok(pattern), Ok(text)) = (
+        std::str::from_utf8(&data[..separator]),
+        std::str::from_utf8(&data[separator + 1..]),
+    ) else {
+        return;
+    };
+
+    if pattern.len() > MAX_PATTERN_LEN || text.len() > MAX_TEXT_LEN {
+        return;
+    }
+
+    if !is_supported(pattern) || !text.is_ascii() {
+        return;
+    }
+
+    // Patterns rejected by the reference, like reversed class ranges, are not compared.
+    let Ok(reference) = regex::Regex::new(pattern) else {
+        return;
+    };
+
+    let regex = Regex::compile(pattern);
+    assert_eq!(
+        reference.is_match(text),
+        regex.is_match(text),
+        "pattern {:?} on text {:?}",
+        pattern,
+        text
+    );
+});
+
+/// Returns true if the pattern only uses syntax both engines interpret the same way:
+/// literals, `.`, `\d`, `\w`, simple character classes, a leading `^`, `$`, groups
+/// with alternation and the quantifiers `+`, `*` and `?` after single char items and groups.
+fn is_supported(pattern: &str) -> bool {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut index = 0;
+    let mut depth = 0;
+    let mut is_quantifiable = false;
+
+    if chars.is_empty() {
+        return false;
+    }
+
+    if chars[0] == '^' {
+        index = 1;
+    }
+
+    while index < chars.len() {
+        let char = chars[index];
+        index += 1;
+
+        match char {
+            '+' | '*' | '?' => {
+                if !is_quantifiable {
+                    return false;
+                }
+
+                is_quantifiable = false;
+                continue;
+            }
+            '\\' => match chars.get(index) {
+                Some('d' | 'w' | '\\') => index += 1,
+                _ => return false,
+            },
+            '[' => {
+                let Some(len) = class_len(&chars[index..]) else {
+                    return false;
+                };
+
+                index += len;
+            }
+            '(' => depth += 1,
+            ')' if depth > 0 => depth -= 1,
+            '|' if depth > 0 => {}
+            '$' => {}
+            c if is_plain(c) || c == '.' => {}
+            _ => return false,
+        }
+
+        // Quantified anchors are not supported by this engine.
+        is_quantifiable = !matches!(char, '(' | '|' | '$');
+    }
+
+    depth == 0
+}
+
+/// Returns the length of a character class after its opening bracket, including the
+/// closing bracket. Set operations like `--` of the `regex` crate are not supported.
+fn class_len(chars: &[char]) -> Option<usize> {
+    let start = usize::from(chars.first() == Some(&'^'));
+    let end = start + chars[start..].iter().position(|c| *c == ']')?;
+    let members = &chars[start..end];
+
+    let is_valid = !members.is_empty()
+        && members.iter().all(|c| is_plain(*c))
+        && !members.windows(2).any(|w| w == ['-', '-']);
+
+    is_valid.then_some(end + 1)
+}
+
+/// Returns true for chars without special meaning in either engine.
+fn is_plain(char: char) -> bool {
+    char.is_ascii_alphanumeric() || matches!(char, ' ' | '_' | '-' | ',' | '=' | '\'')
+}
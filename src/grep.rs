@@ -1,262 +1,109 @@
+pub mod anchors;
+mod charset;
+mod error;
+mod flags;
+mod literals;
+mod matcher;
+mod nfa;
 mod patterns;
-mod str;
+mod regex;
+mod stream;
 mod syntax;
 mod tokens;
 
-use std::{collections::HashMap, ops::Deref};
-use str::StringUtils;
 use syntax::Syntax;
 
-#[derive(Clone, Debug)]
-struct Match {
-    text: Vec<char>,
-}
-
-impl Match {
-    /// Creates a match for the empty string.
-    fn empty() -> Match {
-        Match { text: vec![] }
-    }
-
-    /// Creates a Match from a single char that matched a single syntax item.
-    fn from_char(text: char) -> Match {
-        Match { text: vec![text] }
-    }
-
-    fn from_str(text: &str) -> Match {
-        Match {
-            text: text.chars().collect(),
-        }
-    }
-
-    /// Merges two Matches, creating a new instance.
-    fn merge(head: Match, tail: Match) -> Match {
-        Match {
-            text: [head.text, tail.text].concat(),
-        }
-    }
-
-    /// Merges this Match instance with another one, mutating this instance.
-    fn merge_with(&mut self, other: Match) {
-        self.text.extend(other.text);
-    }
-}
+pub use error::ParseError;
+pub use flags::Flags;
+pub use matcher::MatchResult;
+pub use regex::{
+    decode_bytes, Captures, CapturesIter, Engine, FindIter, Limits, Regex, RegexBuilder, Span,
+};
+pub use stream::StreamMatcher;
 
-fn is_match(char: char, pattern: &Syntax) -> Option<Match> {
-    let is_match = match pattern {
-        Syntax::Wildcard => true,
-        Syntax::Literal { char: c } => *c == char,
-        Syntax::Digit => patterns::is_digit(char),
-        Syntax::Word => patterns::is_word(char),
-        Syntax::CharacterClass {
-            chars: cs,
-            is_negated: true,
-        } => !patterns::is_any_of(&cs, char),
-        Syntax::CharacterClass {
-            chars: cs,
-            is_negated: false,
-        } => patterns::is_any_of(&cs, char),
-
-        Syntax::StartOfLineAnchor => panic!(
-            "Only one-character matching syntax expected here, but found start of line anchor"
-        ),
-
-        Syntax::EndOfLineAnchor => {
-            panic!("Only one-character matching syntax expected here, but found end of line anchor")
-        }
-
-        Syntax::OneOrMore { .. } => panic!(
-            "Only one-character matching syntax expected here, but found one or more quantifier"
-        ),
-
-        Syntax::ZeroOrOne { .. } => panic!(
-            "Only one-character matching syntax expected here, but found zero or more quantifier"
-        ),
-
-        Syntax::CaptureGroup { .. } => panic!(
-            "Only one-character matching syntax expected here, but found capture group quantifier"
-        ),
-
-        Syntax::CaptureGroupEnd { .. } => {
-            panic!("Only one-character matching syntax expected here, but found capture group end")
-        }
-
-        Syntax::BackReference { .. } => {
-            panic!("Only one-character matching syntax expected here, but found backreference")
-        }
-    };
-
-    if is_match {
-        Some(Match::from_char(char))
-    } else {
-        None
-    }
+pub fn match_pattern(input_line: &str, pattern: &str) -> bool {
+    find_pattern(input_line, pattern).is_some()
 }
 
-fn match_star(
-    text: &str,
-    syntax: &Syntax,
-    remainder: &[Syntax],
-    cgroups: &mut HashMap<u32, Match>,
-) -> Option<Match> {
-    let mut match_head = Match::empty();
-    let mut text_remainder = text;
-    loop {
-        if let Some(match_tail) = match_here(text_remainder, remainder, cgroups) {
-            match_head.merge_with(match_tail);
-            return Some(match_head);
-        };
-
-        let char = text_remainder.chars().next()?;
-        let match_char = is_match(char, &syntax)?;
+/// Returns the leftmost match of the pattern within the line, with the spans of all its
+/// capture groups.
+pub fn find_pattern(input_line: &str, pattern: &str) -> Option<MatchResult> {
+    let tokens = tokens::tokenize_pattern(pattern);
+    let syntax = syntax::merge_literals(syntax::parse_pattern(&tokens));
+    let group_count = syntax::capture_names(&syntax).len();
 
-        match_head.merge_with(match_char);
-        text_remainder = &text_remainder.slice(1..);
+    if let Some(Syntax::StartOfLineAnchor) = syntax.first() {
+        return matcher::run(&syntax[1..], input_line, 0, 0, group_count);
     }
-}
 
-fn match_question_mark(
-    text: &str,
-    syntax: &Syntax,
-    pattern: &[Syntax],
-    cgroups: &mut HashMap<u32, Match>
-) -> Option<Match> {
-    let pattern_once: Vec<Syntax> = [&[syntax.clone()], pattern].concat();
+    // Start at every char boundary, including the end of the line for empty matches.
+    let mut start_indices = input_line
+        .char_indices()
+        .map(|(index, _)| index)
+        .chain(std::iter::once(input_line.len()));
 
-    if let Some(match_once) = match_here(text, &pattern_once, cgroups) {
-        return Some(match_once);
-    } else {
-        return match_here(text, pattern, cgroups);
-    }
+    start_indices.find_map(|start| matcher::run(&syntax, input_line, start, 0, group_count))
 }
 
-fn match_here(text: &str, pattern: &[Syntax], cgroups: &mut HashMap<u32, Match>) -> Option<Match> {
-    let Some(syntax) = pattern.get(0) else {
-        // The entire pattern matched, return success.
-        return Some(Match::empty());
-    };
-
-    if let Syntax::OneOrMore { syntax: s } = syntax {
-        let match_head = match_here(text, &[(**s).clone()], cgroups)?;
-        let match_tail = match_star(
-            text.slice(match_head.text.len()..),
-            s,
-            &pattern[1..],
-            cgroups,
-        )?;
-
-        return Some(Match::merge(match_head, match_tail));
-    }
-
-    if let Syntax::ZeroOrOne { syntax: s } = syntax {
-        return match_question_mark(text, &s.deref(), &pattern[1..], cgroups);
-    }
-
-    if let Syntax::CaptureGroup { options: os, id } = syntax {
-        let pattern_remainder = &pattern[1..];
-
-        for option in os {
-            let end = Syntax::CaptureGroupEnd {
-                text: text.chars().collect(),
-                id: *id,
-            };
-            let pattern_total = [option.as_slice(), &[end], pattern_remainder].concat();
-
-            if let Some(match_total) = match_here(text, &pattern_total, cgroups) {
-                return Some(match_total);
-            }
-        }
-
-        return None;
-    }
-
-    if let Syntax::CaptureGroupEnd {
-        text: text_original,
-        id,
-    } = syntax
-    {
-        let match_len = text_original.len() - text.len();
-        let match_group = Match::from_str(text_original.slice(..match_len));
-
-        let None = cgroups.insert(*id, match_group) else {
-            panic!("Duplicate capture group result '{}'", id);
-        };
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        if let Some(match_remainder) = match_here(text, &pattern[1..], cgroups) {
-            return Some(match_remainder);
-        } else {
-            // If the remainder does not match, we continue with the next option,
-            // but the capture group result has to be discarded again.
-            // Ignore the result here, since the capture group matching might or
-            // might not have been successful.
-            cgroups.remove(id).expect("Unable to remove capture group");
-            return None;
-        }
-    }
-
-    if let Syntax::BackReference { id } = syntax {
-        let Some(match_original) = cgroups.get(id) else {
-            panic!("No capture group with id '{}' has been matched yet", id);
+    /// Returns the text consumed by the leftmost match of the pattern, which allows
+    /// asserting how much input a quantifier consumed.
+    fn matched_text(input: &str, pattern: &str) -> Option<String> {
+        let syntax = syntax::parse_pattern(&tokens::tokenize_pattern(pattern));
+        let (pattern, is_anchored) = match syntax.first() {
+            Some(Syntax::StartOfLineAnchor) => (&syntax[1..], true),
+            _ => (syntax.as_slice(), false),
         };
+        let starts = input.char_indices().map(|(index, _)| index).chain([input.len()]);
 
-        let search_string: String = match_original.text.iter().collect();
-        if text.starts_with(search_string.as_str()) {
-            let match_ref = match_original.clone();
-            let match_remainder = match_here(
-                text.slice(match_original.text.len()..),
-                &pattern[1..],
-                cgroups,
-            )?;
-
-            return Some(Match::merge(match_ref, match_remainder));
-        } else {
-            return None;
-        }
-    }
-
-    if let Syntax::EndOfLineAnchor = syntax {
-        return (pattern.len() == 1 && text.len() == 0).then(|| Match::empty());
+        starts
+            .take(if is_anchored { 1 } else { usize::MAX })
+            .find_map(|start| matcher::run(pattern, input, start, 0, 0))
+            .map(|result| input[result.start..result.end].to_string())
     }
 
-    if let Some(c) = text.chars().next() {
-        let match_char = is_match(c, syntax)?;
-        let match_remainder = match_here(&text.slice(1..), &pattern[1..], cgroups)?;
-
-        return Some(Match::merge(match_char, match_remainder));
+    #[test]
+    fn test_matched_text() {
+        assert_eq!(Some("b".to_string()), matched_text("abc", "b"));
+        assert_eq!(Some("".to_string()), matched_text("abc", "x?"));
+        assert_eq!(Some("ab".to_string()), matched_text("abab", "^ab"));
+        assert_eq!(None, matched_text("abc", "^b"));
     }
 
-    return None;
-}
-
-pub fn match_pattern(input_line: &str, pattern: &str) -> bool {
-    let tokens = tokens::tokenize_pattern(pattern);
-    let syntax = syntax::parse_pattern(&tokens);
-    let mut capture_groups = HashMap::new();
-
-    if let Some(Syntax::StartOfLineAnchor) = syntax.get(0) {
-        return match match_here(input_line, &syntax[1..], &mut capture_groups) {
-            Some(_) => true,
-            None => false,
-        };
+    #[test]
+    fn test_find_pattern() {
+        let span = |start, end| Some(Span { start, end });
+
+        assert_eq!(
+            Some(MatchResult {
+                start: 4,
+                end: 11,
+                groups: vec![span(4, 11), span(4, 7), None, span(8, 11)],
+            }),
+            find_pattern("key=abc-def", "([a-z]+)-(x)?([a-z]+)")
+        );
+        assert_eq!(
+            Some(MatchResult {
+                start: 0,
+                end: 2,
+                groups: vec![span(0, 2), span(1, 2)],
+            }),
+            find_pattern("ab", "^a(b|c)")
+        );
+        assert_eq!(None, find_pattern("ab", "^b"));
     }
 
-    for start_index in 0..input_line.len() {
-        if let Some(_) = match_here(
-            &input_line.slice(start_index..),
-            &syntax,
-            &mut capture_groups,
-        ) {
-            return true;
-        }
+    #[test]
+    fn test_match_pattern_mixed_items() {
+        assert!(match_pattern("x1_y", "^x\\d\\w.$"));
+        assert!(match_pattern("ab12", "a[b-c]+\\d{2}$"));
+        assert!(!match_pattern("ab1", "a[b-c]+\\d{2}$"));
+        assert!(match_pattern("say hi", "(?=s)\\w+ hi"));
     }
 
-    false
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
     #[test]
     fn test_match_pattern_single_char() {
         assert!(match_pattern("abcdefg", "e"))
@@ -323,6 +170,54 @@ mod tests {
         assert!(!match_pattern("1 dog", "\\d \\w\\w\\ws"));
     }
 
+    #[test]
+    fn test_match_pattern_character_group_escape_sequences() {
+        assert!(match_pattern("7", "[\\d]"));
+        assert!(match_pattern(".", "[\\d.]"));
+        assert!(!match_pattern("a", "[\\d.]"));
+        assert!(match_pattern("-", "[\\w-]"));
+        assert!(match_pattern("x", "[\\w-]"));
+        assert!(!match_pattern("!", "[\\w-]"));
+        assert!(match_pattern("a b", "a[\\s]b"));
+        assert!(match_pattern("x", "[\\D]"));
+        assert!(!match_pattern("7", "[\\D]"));
+        assert!(match_pattern("]", "[\\]]"));
+        assert!(match_pattern("(", "[()]"));
+    }
+
+    #[test]
+    fn test_match_pattern_character_group_ranges() {
+        assert!(match_pattern("q", "[a-z]"));
+        assert!(!match_pattern("Q", "[a-z]"));
+        assert!(match_pattern("Q", "[a-zA-Z]"));
+        assert!(match_pattern("5", "[0-9a-f]"));
+        assert!(!match_pattern("g", "[0-9a-f]"));
+        assert!(match_pattern("g", "[^0-9a-f]"));
+        assert!(!match_pattern("abc", "[^\\w]"));
+    }
+
+    #[test]
+    fn test_match_pattern_character_group_duplicates() {
+        assert!(match_pattern("b", "[aabbcc]"));
+        assert!(!match_pattern("d", "[aabbcc]"));
+        assert!(match_pattern("d", "[^aabbcc]"));
+        assert!(!match_pattern("e", "[^a-fc-e]"));
+        assert!(match_pattern("g", "[^a-fc-e]"));
+    }
+
+    #[test]
+    fn test_match_pattern_character_group_bar() {
+        assert!(match_pattern("|", "[|]"));
+        assert!(match_pattern("a", "[a|b]"));
+        assert!(match_pattern("|", "[a|b]"));
+        assert!(match_pattern("b", "[a|b]"));
+        assert!(!match_pattern("c", "[a|b]"));
+        assert!(match_pattern("x|", "(x[|]|y)"));
+        assert!(match_pattern("y", "(x[|]|y)"));
+        assert!(!match_pattern("x", "(x[|]|y)"));
+        assert!(match_pattern("(", "(x|[(])"));
+    }
+
     #[test]
     fn test_match_pattern_start_of_line_anchor() {
         assert!(match_pattern("log", "^log"));
@@ -335,6 +230,30 @@ mod tests {
         assert!(!match_pattern("dogs", "dog$"));
     }
 
+    #[test]
+    fn test_match_pattern_end_of_line_anchor_in_group() {
+        assert!(match_pattern("hotdog", "(dog$)"));
+        assert!(!match_pattern("dogs", "(dog$)"));
+        assert!(match_pattern("dogs", "(dog$|dogs)"));
+        assert!(!match_pattern("dog", "dog$s"));
+    }
+
+    #[test]
+    fn test_match_pattern_previous_match_end_anchor() {
+        assert!(match_pattern("1a", "\\G\\d"));
+        assert!(!match_pattern("a1", "\\G\\d"));
+    }
+
+    #[test]
+    fn test_match_pattern_alternation_anchors() {
+        assert!(match_pattern("abc", "(^a|b$)"));
+        assert!(match_pattern("cab", "(^a|b$)"));
+        assert!(!match_pattern("xby", "(^a|b$)"));
+        assert!(!match_pattern("xay", "(^a|b$)"));
+        assert!(match_pattern("xa", "x(^a|a$)"));
+        assert!(!match_pattern("xab", "x(^a|a$)"));
+    }
+
     #[test]
     fn test_match_pattern_empty_anchors() {
         assert!(match_pattern("", "^$"));
@@ -346,6 +265,11 @@ mod tests {
         assert!(match_pattern("caats", "ca+ts"));
         assert!(match_pattern("caaaaa", "ca+"));
         assert!(!match_pattern("cts", "ca+ts"));
+
+        // The quantifier is greedy, but gives back chars if the remainder requires it.
+        assert_eq!(Some("caaaaa".to_string()), matched_text("caaaaa", "ca+"));
+        assert_eq!(Some("caaaa".to_string()), matched_text("caaaab", "ca+a"));
+        assert_eq!(Some("123".to_string()), matched_text("abc123def", "\\d+"));
     }
 
     #[test]
@@ -353,6 +277,73 @@ mod tests {
         assert!(match_pattern("dogs", "dogs?"));
         assert!(match_pattern("dog", "dogs?"));
         assert!(!match_pattern("cat", "dogs?"));
+
+        assert_eq!(Some("dogs".to_string()), matched_text("dogsled", "dogs?"));
+        assert_eq!(Some("dog".to_string()), matched_text("doggy", "dogs?"));
+        assert_eq!(Some("ab".to_string()), matched_text("ab", "ab?b?"));
+    }
+
+    #[test]
+    fn test_match_pattern_star_quantifier() {
+        assert!(match_pattern("cts", "ca*ts"));
+        assert!(match_pattern("caaats", "ca*ts"));
+        assert!(!match_pattern("cbts", "ca*ts"));
+
+        assert_eq!(Some("caaa".to_string()), matched_text("caaab", "ca*"));
+        assert_eq!(Some("c".to_string()), matched_text("cb", "ca*"));
+        assert_eq!(Some("aabab".to_string()), matched_text("xaababc", "a.*b"));
+    }
+
+    #[test]
+    fn test_match_pattern_repetition_quantifier() {
+        assert!(match_pattern("caaat", "ca{2,3}t"));
+        assert!(!match_pattern("cat", "ca{2,3}t"));
+        assert!(!match_pattern("caaaat", "ca{2,3}t"));
+        assert!(match_pattern("caaaat", "ca{2,}t"));
+        assert!(match_pattern("caat", "ca{2}t"));
+
+        assert_eq!(Some("aaa".to_string()), matched_text("aaaa", "a{1,3}"));
+        assert_eq!(Some("a1b".to_string()), matched_text("a12b a1b", "a\\d{1}b"));
+    }
+
+    #[test]
+    fn test_match_pattern_repetition_missing_lower_bound() {
+        assert_eq!(Some("".to_string()), matched_text("", "a{,2}"));
+        assert_eq!(Some("a".to_string()), matched_text("a", "a{,2}"));
+        assert_eq!(Some("aa".to_string()), matched_text("aa", "a{,2}"));
+        assert_eq!(Some("aa".to_string()), matched_text("aaa", "a{,2}"));
+        assert_eq!(Some("".to_string()), matched_text("bbb", "a{,2}"));
+
+        assert!(match_pattern("xy", "^xa{,2}y$"));
+        assert!(match_pattern("xaay", "^xa{,2}y$"));
+        assert!(!match_pattern("xaaay", "^xa{,2}y$"));
+    }
+
+    #[test]
+    fn test_match_pattern_repetition_missing_both_bounds() {
+        assert_eq!(Some("".to_string()), matched_text("b", "a{,}"));
+        assert_eq!(Some("aaaa".to_string()), matched_text("aaaa", "a{,}"));
+    }
+
+    #[test]
+    fn test_match_pattern_quantified_character_class() {
+        assert!(match_pattern("abc123", "[a-z]+"));
+        assert!(!match_pattern("123", "[a-z]+"));
+
+        assert_eq!(Some("abc".to_string()), matched_text("abc123", "[a-z]+"));
+        assert_eq!(Some("abc".to_string()), matched_text("abc123", "[^0-9]+"));
+        assert_eq!(Some("xy".to_string()), matched_text("12xy3", "[^0-9]+"));
+        assert_eq!(Some("123".to_string()), matched_text("123abc", "[0-9]*"));
+        assert_eq!(Some("".to_string()), matched_text("abc", "[0-9]*"));
+        assert_eq!(Some("a1b2".to_string()), matched_text("-a1b2-", "[a-z0-9]+"));
+        assert_eq!(Some("abc1".to_string()), matched_text("abc1", "[a-z]*\\d"));
+    }
+
+    #[test]
+    fn test_match_pattern_empty_pattern() {
+        assert!(match_pattern("abc", ""));
+        assert!(match_pattern("", ""));
+        assert_eq!(Some("".to_string()), matched_text("abc", ""));
     }
 
     #[test]
@@ -361,6 +352,40 @@ mod tests {
         assert!(!match_pattern("cat", "d.g"));
     }
 
+    #[test]
+    fn test_match_pattern_wildcard_excludes_newline() {
+        assert!(!match_pattern("d\ng", "d.g"));
+        assert!(!match_pattern("d\ng", "d.*g"));
+        assert!(match_pattern("d\ndog", "d.g"));
+        assert!(match_pattern("d\ng", "d[^x]g"));
+    }
+
+    #[test]
+    fn test_match_pattern_quoted_literal() {
+        assert!(match_pattern("a.b", "\\Qa.b\\E"));
+        assert!(!match_pattern("axb", "\\Qa.b\\E"));
+        assert!(match_pattern("1+1=2", "^\\Q1+1\\E=\\d$"));
+        assert!(match_pattern("(a|b)*", "\\Q(a|b)*"));
+        assert!(!match_pattern("a", "\\Q(a|b)*"));
+        assert!(match_pattern("[x]", "\\Q[x]\\E"));
+        assert!(!match_pattern("x", "\\Q[x]\\E"));
+    }
+
+    #[test]
+    fn test_match_pattern_quoted_literal_quantified() {
+        // A quantifier after the quote applies to its last char only, like in Perl.
+        assert!(match_pattern("a.bbb", "^\\Qa.b\\E+$"));
+        assert!(!match_pattern("a.ba.b", "^\\Qa.b\\E+$"));
+    }
+
+    #[test]
+    fn test_match_pattern_comment() {
+        assert!(match_pattern("ab", "a(?#foo)b"));
+        assert!(!match_pattern("afoob", "^a(?#foo)b$"));
+        assert!(match_pattern("ab", "^a(?#nested (parens) here)b$"));
+        assert!(match_pattern("a(?#)b", "a[(?#)]+b"));
+    }
+
     #[test]
     fn test_match_pattern_alternation() {
         assert!(match_pattern("cat", "(cat|dog)"));
@@ -368,6 +393,37 @@ mod tests {
         assert!(!match_pattern("apple", "(cat|dog)"));
     }
 
+    #[test]
+    fn test_match_pattern_alternation_options_of_different_lengths() {
+        // A failing remainder retries the group with its longer option.
+        assert_eq!(Some("abc".to_string()), matched_text("abc", "(a|ab)c"));
+        assert_eq!(Some("abc".to_string()), matched_text("abc", "(a|ab)(c|bc)"));
+        assert!(match_pattern("abcd", "^(a|ab)(c|bcd)$"));
+        assert!(!match_pattern("abd", "^(a|ab)(c|bcd)$"));
+
+        // Quantifiers after a group retry every iteration with each option.
+        assert!(match_pattern("ababc", "^(a|ab)+c$"));
+        assert!(match_pattern("aabc", "^(a|ab)*bc$"));
+        assert!(match_pattern("abc", "^(a|ab)?bc$"));
+        assert!(match_pattern("aabc", "^(a|ab){2}c$"));
+        assert!(!match_pattern("ababac", "^(a|ab){2}c$"));
+    }
+
+    #[test]
+    fn test_match_pattern_alternation_empty_branches() {
+        assert_eq!(Some("a".to_string()), matched_text("a", "(a|)"));
+        assert_eq!(Some("".to_string()), matched_text("b", "(a|)"));
+        assert_eq!(Some("".to_string()), matched_text("a", "(|a)"));
+        assert_eq!(Some("".to_string()), matched_text("a", "(|)"));
+
+        // A failing remainder retries the group with its other branch.
+        assert_eq!(Some("ab".to_string()), matched_text("ab", "(|a)b"));
+        assert_eq!(Some("b".to_string()), matched_text("b", "(a|)b"));
+        assert_eq!(Some("ab".to_string()), matched_text("ab", "(|)ab"));
+        assert!(match_pattern("x", "^(a|)x$"));
+        assert!(!match_pattern("bx", "^(a|)x$"));
+    }
+
     #[test]
     fn test_match_pattern_backreference() {
         assert!(match_pattern("cat and cat", "(cat) and \\1"));
@@ -380,6 +436,201 @@ mod tests {
         assert!(match_pattern("'cat and cat' is the same as 'cat and cat'", "('(cat) and \\2') is the same as \\1"));
     }
 
+    #[test]
+    fn test_match_pattern_quantified_group() {
+        assert!(match_pattern("xababc", "(ab)+c"));
+        assert!(!match_pattern("xabac", "^x(ab)+c"));
+        assert!(match_pattern("c", "^(ab)*c$"));
+        assert!(match_pattern("one two end", "^(\\w+ )*end$"));
+        assert!(match_pattern("abac", "^(a|ab)+c$"));
+        assert!(match_pattern("b", "^(a)?b$"));
+        assert_eq!(Some("ababab".to_string()), matched_text("abababab", "(ab){2,3}"));
+        assert!(!match_pattern("abx", "(ab){2}"));
+    }
+
+    #[test]
+    fn test_match_pattern_quantified_group_empty_iteration() {
+        assert!(match_pattern("aab", "^(a|)*b$"));
+        assert!(match_pattern("ab", "^(|a){3}b$"));
+        assert!(!match_pattern("aac", "^(a|)+b"));
+    }
+
+    #[test]
+    fn test_match_pattern_quantified_group_backreference() {
+        assert!(match_pattern("123 3", "^(\\d)+ \\1$"));
+        assert!(!match_pattern("123 1", "^(\\d)+ \\1$"));
+        assert!(match_pattern("abab-b", "^((a)|(b))+-\\1$"));
+    }
+
+    #[test]
+    fn test_match_pattern_backreference_multibyte() {
+        assert!(match_pattern("café café", "(café) \\1"));
+        assert!(match_pattern("un café café!", "(caf.) \\1!$"));
+        assert!(!match_pattern("café cafe", "(café) \\1"));
+        assert!(match_pattern("ünï-ünï", "^(...)-\\1$"));
+        assert_eq!(Some("äö-äö".to_string()), matched_text("xäö-äöy", "(..)-\\1"));
+    }
+
+    #[test]
+    fn test_match_pattern_named_capture_group() {
+        assert!(match_pattern("2024-10", "(?<year>\\d+)-(?P<month>\\d+)"));
+        assert!(match_pattern("ab-b", "(?<first>a)(?<second>b)-\\2"));
+        assert!(!match_pattern("ab-a", "(?<first>a)(?<second>b)-\\2"));
+    }
+
+    #[test]
+    fn test_match_pattern_nested_alternation_backreference() {
+        assert!(match_pattern("ab-a-b", "((a)(b)|(c))-\\2-\\3"));
+        assert!(match_pattern("c-c", "((a)(b)|(c))-\\4"));
+        assert!(!match_pattern("c-a", "((a)(b)|(c))-\\4"));
+        assert!(match_pattern("ab-ab", "((a)(b)|(c))-\\1"));
+        assert!(match_pattern("bb", "((a)|(b))\\3"));
+        assert!(match_pattern("aa", "((a)|(b))\\2"));
+    }
+
+    #[test]
+    fn test_match_pattern_backreference_to_group_in_untaken_option() {
+        assert!(!match_pattern("bb", "((a)|(b))\\2"));
+        assert!(!match_pattern("c-", "((a)(b)|(c))-\\2"));
+    }
+
+    #[test]
+    fn test_match_pattern_lookahead() {
+        assert!(match_pattern("foobar", "foo(?=bar)"));
+        assert!(!match_pattern("foobaz", "foo(?=bar)"));
+        assert!(match_pattern("foobaz", "foo(?=bar|baz)"));
+        assert!(match_pattern("foo", "foo(?=)"));
+        assert!(!match_pattern("foo", "foo(?=b)"));
+        assert!(match_pattern("ab", "a(?=b)b$"));
+        assert!(match_pattern("foo", "foo(?=$)"));
+        assert!(!match_pattern("foox", "^foo(?=$)"));
+    }
+
+    #[test]
+    fn test_match_pattern_negative_lookahead() {
+        assert!(match_pattern("foobaz", "foo(?!bar)"));
+        assert!(!match_pattern("foobar", "foo(?!bar)"));
+        assert!(match_pattern("foo", "foo(?!bar)"));
+        assert!(!match_pattern("foo", "foo(?!)"));
+        assert!(!match_pattern("foo", "foo(?!$)"));
+        assert!(match_pattern("foobar foobaz", "foo(?!bar)ba"));
+    }
+
+    #[test]
+    fn test_match_pattern_lookahead_does_not_consume() {
+        assert_eq!(Some("foo".to_string()), matched_text("foobar", "foo(?=bar)"));
+        assert_eq!(Some("abc".to_string()), matched_text("abc1", "\\w+(?=\\d)"));
+        assert!(match_pattern("password1", "^(?=.*\\d)\\w{8,}$"));
+        assert!(!match_pattern("password", "^(?=.*\\d)\\w{8,}$"));
+    }
+
+    #[test]
+    fn test_match_pattern_lookbehind() {
+        assert!(match_pattern("costs $42", "(?<=[$])\\d+"));
+        assert!(!match_pattern("costs 42", "(?<=[$])\\d+"));
+        assert_eq!(Some("42".to_string()), matched_text("a $42", "(?<=[$])\\d+"));
+        assert!(match_pattern("xab", "(?<=xa|ya)b"));
+        assert!(match_pattern("é1", "(?<=é)1"));
+        assert!(!match_pattern("a", "(?<=x)a"));
+        assert!(match_pattern("a", "(?<=^)a"));
+        assert!(match_pattern("ab", "a(?<=a)b"));
+        assert!(match_pattern("ab", "(?<=ab)$"));
+    }
+
+    #[test]
+    fn test_match_pattern_negative_lookbehind() {
+        assert!(match_pattern("a", "(?<!x)a"));
+        assert!(!match_pattern("xa", "^.(?<!x)a"));
+        assert!(match_pattern("$4 and 2", "(?<![$\\d])\\d"));
+        assert!(!match_pattern("$4", "(?<![$\\d])\\d"));
+        assert!(!match_pattern("a", "(?<!)a"));
+    }
+
+    #[test]
+    fn test_match_pattern_character_class_intersection() {
+        assert!(match_pattern("rhythms", "^[a-z&&[^aeiou]]+$"));
+        assert!(!match_pattern("strange", "^[a-z&&[^aeiou]]+$"));
+        assert_eq!(Some("str".to_string()), matched_text("strange", "[a-z&&[^aeiou]]+"));
+        assert!(match_pattern("x7", "[\\w&&[^a-z]]"));
+        assert!(!match_pattern("abc", "[\\w&&[^a-z]]"));
+        assert!(match_pattern("b", "[a-c&&[b-d]&&[^c]]"));
+        assert!(!match_pattern("c", "[a-c&&[b-d]&&[^c]]"));
+        assert!(match_pattern("E", "[^a-z&&[^aeiou]]"));
+        assert!(!match_pattern("b", "[^a-z&&[^aeiou]]"));
+    }
+
+    #[test]
+    fn test_match_pattern_atomic_group() {
+        assert!(match_pattern("aaab", "(?>a+)b"));
+        assert!(!match_pattern("aaa", "(?>a+)a"));
+        assert!(match_pattern("aaa", "(a+)a"));
+        assert!(match_pattern("abc", "(?>ab|a)c"));
+        assert!(!match_pattern("abc", "(?>a|ab)c"));
+    }
+
+    #[test]
+    fn test_match_pattern_atomic_group_capture_groups() {
+        assert!(match_pattern("ab-b", "(?>a(b))-\\1"));
+        assert!(match_pattern("xab ab", "(?>(a)b|x) \\1b"));
+    }
+
+    #[test]
+    fn test_match_pattern_control_characters() {
+        assert!(match_pattern("name\tvalue", "name\\tvalue"));
+        assert!(!match_pattern("name value", "name\\tvalue"));
+        assert!(match_pattern("a\tb", "\\w[\\t ]\\w"));
+        assert!(match_pattern("line\r", "line\\r$"));
+        assert!(match_pattern("a\0b", "a\\0b"));
+    }
+
+    #[test]
+    fn test_match_pattern_multibyte_start() {
+        assert!(match_pattern("🦀 crab", "crab"));
+        assert!(match_pattern("🦀🐕dog", "^..dog$"));
+        assert!(match_pattern("héllo wörld", "w.rld$"));
+        assert!(!match_pattern("🦀 crab", "^crab"));
+    }
+
+    #[test]
+    fn test_match_pattern_empty_match_at_end() {
+        assert!(match_pattern("", "x?"));
+        assert!(match_pattern("🦀", "🦀x?$"));
+    }
+
+    #[test]
+    fn test_match_pattern_long_input() {
+        let input = "a".repeat(100_000);
+
+        assert!(match_pattern(&input, "a+"));
+        assert!(match_pattern(&input, "^a+$"));
+        assert!(match_pattern(&format!("{}b", input), "a+b"));
+        assert_eq!(Some(input.clone()), matched_text(&input, "a+"));
+    }
+
+    #[test]
+    fn test_match_pattern_long_literal_sequence() {
+        let input = "ab".repeat(50_000);
+
+        assert!(match_pattern(&format!("x{}y", input), &input));
+        assert!(match_pattern(&input, &format!("^{}$", input)));
+        assert!(!match_pattern(&input, &format!("^{}c", input)));
+    }
+
+    #[test]
+    fn test_match_pattern_pathological_time_budget() {
+        // Same pattern as the catastrophic backtracking benchmark, which tries all
+        // ways of splitting the input between the optional and the required items.
+        let pattern = format!("{}{}", "a?".repeat(12), "a".repeat(12));
+        let input = "a".repeat(12);
+
+        let start = std::time::Instant::now();
+        assert!(match_pattern(&input, &pattern));
+        assert!(!match_pattern(&input[1..], &pattern));
+
+        let elapsed = start.elapsed();
+        assert!(elapsed.as_secs() < 5, "Pathological pattern took {:?}", elapsed);
+    }
+
     #[test]
     fn test_match_pattern_regression_tests() {
         assert!(!match_pattern("×-+=÷%", "\\w"));
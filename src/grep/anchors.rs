@@ -0,0 +1,118 @@
+//! Boundary assertions shared by the matching engine and the command line flags.
+//!
+//! Positions are byte offsets into the text the assertion is evaluated against.
+
+use super::patterns;
+
+/// Returns true if the position is at the start of the text.
+pub fn matches_start(pos: usize) -> bool {
+    pos == 0
+}
+
+/// Returns true if the position is at the end of a text with byte length `len`.
+pub fn matches_end(pos: usize, len: usize) -> bool {
+    pos == len
+}
+
+/// Returns true if the position is at the start of a line within the text,
+/// i.e. at the start of the text or directly after a newline.
+pub fn matches_line_start(text: &str, pos: usize) -> bool {
+    matches_start(pos) || text[..pos].ends_with('\n')
+}
+
+/// Returns true if the position is at the end of a line within the text,
+/// i.e. at the end of the text or directly before a newline.
+pub fn matches_line_end(text: &str, pos: usize) -> bool {
+    matches_end(pos, text.len()) || text[pos..].starts_with('\n')
+}
+
+/// Returns true if the position lies between a word and a non-word char.
+/// The start and end of the text count as non-word chars.
+pub fn matches_word_boundary(text: &str, pos: usize) -> bool {
+    let is_word_before = text[..pos].chars().next_back().is_some_and(patterns::is_word);
+    let is_word_after = text[pos..].chars().next().is_some_and(patterns::is_word);
+
+    is_word_before != is_word_after
+}
+
+/// Returns true if the part of the text between the positions is a whole word, i.e.
+/// neither preceded nor followed by a word char. Like for word boundaries, the start
+/// and end of the text count as non-word chars.
+pub fn matches_whole_word(text: &str, start: usize, end: usize) -> bool {
+    let is_word_before = text[..start].chars().next_back().is_some_and(patterns::is_word);
+    let is_word_after = text[end..].chars().next().is_some_and(patterns::is_word);
+
+    !is_word_before && !is_word_after
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_start() {
+        assert!(matches_start(0));
+        assert!(!matches_start(1));
+    }
+
+    #[test]
+    fn test_matches_end() {
+        assert!(matches_end(3, 3));
+        assert!(matches_end(0, 0));
+        assert!(!matches_end(2, 3));
+    }
+
+    #[test]
+    fn test_matches_line_start() {
+        assert!(matches_line_start("ab\ncd", 0));
+        assert!(matches_line_start("ab\ncd", 3));
+        assert!(!matches_line_start("ab\ncd", 1));
+        assert!(!matches_line_start("ab\ncd", 2));
+    }
+
+    #[test]
+    fn test_matches_line_end() {
+        assert!(matches_line_end("ab\ncd", 2));
+        assert!(matches_line_end("ab\ncd", 5));
+        assert!(!matches_line_end("ab\ncd", 1));
+        assert!(!matches_line_end("ab\ncd", 3));
+    }
+
+    #[test]
+    fn test_matches_word_boundary() {
+        assert!(matches_word_boundary("a cat", 2));
+        assert!(matches_word_boundary("a cat", 5));
+        assert!(!matches_word_boundary("a cat", 3));
+        assert!(!matches_word_boundary("a  b", 2));
+    }
+
+    #[test]
+    fn test_matches_word_boundary_at_edges() {
+        assert!(matches_word_boundary("cat", 0));
+        assert!(matches_word_boundary("cat", 3));
+        assert!(!matches_word_boundary(" cat ", 0));
+        assert!(!matches_word_boundary("", 0));
+    }
+
+    #[test]
+    fn test_matches_word_boundary_multibyte() {
+        assert!(matches_word_boundary("ü cat", 3));
+        assert!(!matches_word_boundary("üü", 2));
+    }
+
+    #[test]
+    fn test_matches_whole_word() {
+        assert!(matches_whole_word("a cat sat", 2, 5));
+        assert!(!matches_whole_word("a cats", 2, 5));
+        assert!(!matches_whole_word("scat", 1, 4));
+        assert!(matches_whole_word("a-cat-", 2, 5));
+    }
+
+    #[test]
+    fn test_matches_whole_word_at_edges() {
+        assert!(matches_whole_word("cat sat", 0, 3));
+        assert!(matches_whole_word("a cat", 2, 5));
+        assert!(matches_whole_word("cat", 0, 3));
+        assert!(matches_whole_word("", 0, 0));
+    }
+}
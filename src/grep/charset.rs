@@ -0,0 +1,351 @@
+use std::fmt;
+
+use super::patterns;
+use super::syntax::{self, ClassShorthand};
+
+/// The members of a character class: single chars, inclusive ranges and shorthand
+/// classes, optionally negated. Members may further be restricted to the chars contained
+/// in each of a number of nested classes (`[a-z&&[^aeiou]]`).
+///
+/// Single chars and ranges are kept sorted and without duplicates, so membership is
+/// tested by binary search instead of scanning all members. Single chars covered by a
+/// range are dropped and overlapping or adjacent ranges are merged into one.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CharSet {
+    chars: Vec<char>,
+    ranges: Vec<(char, char)>,
+    shorthands: Vec<ClassShorthand>,
+    is_negated: bool,
+
+    /// Nested classes every member has to be contained in as well, before negation.
+    intersections: Vec<CharSet>,
+
+    /// Evaluate the shorthand classes with their Unicode instead of their ASCII definitions.
+    is_unicode: bool,
+}
+
+impl CharSet {
+    pub fn new(
+        chars: Vec<char>,
+        ranges: Vec<(char, char)>,
+        shorthands: Vec<ClassShorthand>,
+        is_negated: bool,
+    ) -> CharSet {
+        let mut ranges = ranges;
+        ranges.sort();
+
+        let mut merged_ranges: Vec<(char, char)> = vec![];
+        for (lower, upper) in ranges {
+            match merged_ranges.last_mut() {
+                Some((_, last_upper)) if lower as u32 <= *last_upper as u32 + 1 => {
+                    *last_upper = (*last_upper).max(upper);
+                }
+                _ => merged_ranges.push((lower, upper)),
+            }
+        }
+
+        let mut shorthands = shorthands;
+        shorthands.dedup();
+
+        let mut set = CharSet {
+            chars: vec![],
+            ranges: merged_ranges,
+            shorthands,
+            is_negated,
+            intersections: vec![],
+            is_unicode: false,
+        };
+
+        let mut chars = chars;
+        chars.sort();
+        chars.dedup();
+        chars.retain(|c| !set.is_in_ranges(*c));
+        set.chars = chars;
+
+        set
+    }
+
+    /// Returns the set changed to evaluate shorthand classes like `\w` with their
+    /// Unicode definitions, e.g. to match letters with diacritics.
+    pub fn with_unicode_shorthands(self) -> CharSet {
+        CharSet {
+            intersections: self
+                .intersections
+                .into_iter()
+                .map(CharSet::with_unicode_shorthands)
+                .collect(),
+            is_unicode: true,
+            ..self
+        }
+    }
+
+    /// Returns the set restricted to chars that are contained in the other set as well.
+    /// Intersecting with a negated set subtracts its members, e.g. to match consonants
+    /// as letters except vowels.
+    pub fn with_intersection(mut self, other: CharSet) -> CharSet {
+        self.intersections.push(other);
+        self
+    }
+
+    /// Returns true if the char is matched by the class, taking negation into account.
+    pub fn contains(&self, char: char) -> bool {
+        self.contains_any([char])
+    }
+
+    /// Returns true if any of the chars is a member of the class, or for a negated class,
+    /// if none of them is. Used to match all case variants of a char at once.
+    pub fn contains_any(&self, chars: impl IntoIterator<Item = char>) -> bool {
+        chars.into_iter().any(|char| self.is_member(char)) != self.is_negated
+    }
+
+    /// Returns true if the char is a single char, within a range or matched by a
+    /// shorthand class of the set and contained in all intersected sets, disregarding
+    /// negation.
+    fn is_member(&self, char: char) -> bool {
+        let is_own_member = self.chars.binary_search(&char).is_ok()
+            || self.is_in_ranges(char)
+            || self
+                .shorthands
+                .iter()
+                .any(|s| is_shorthand_match(char, s, self.is_unicode));
+
+        is_own_member && self.intersections.iter().all(|set| set.contains(char))
+    }
+
+    fn is_in_ranges(&self, char: char) -> bool {
+        // The last range starting at or before the char is the only candidate.
+        let index = self.ranges.partition_point(|(lower, _)| *lower <= char);
+
+        index > 0 && char <= self.ranges[index - 1].1
+    }
+}
+
+impl fmt::Display for CharSet {
+    /// Renders the set as a character class, escaping chars with a special meaning
+    /// within classes.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn write_char(f: &mut fmt::Formatter, char: char) -> fmt::Result {
+            match syntax::control_char_escapee(char) {
+                Some(escapee) => write!(f, "\\{}", escapee),
+                None if matches!(char, '\\' | '[' | ']' | '-' | '^') => write!(f, "\\{}", char),
+                None => write!(f, "{}", char),
+            }
+        }
+
+        write!(f, "[")?;
+        if self.is_negated {
+            write!(f, "^")?;
+        }
+
+        for char in &self.chars {
+            write_char(f, *char)?;
+        }
+
+        for (lower, upper) in &self.ranges {
+            write_char(f, *lower)?;
+            write!(f, "-")?;
+            write_char(f, *upper)?;
+        }
+
+        for shorthand in &self.shorthands {
+            write!(f, "{}", shorthand)?;
+        }
+
+        for set in &self.intersections {
+            write!(f, "&&{}", set)?;
+        }
+
+        write!(f, "]")
+    }
+}
+
+fn is_shorthand_match(char: char, shorthand: &ClassShorthand, is_unicode: bool) -> bool {
+    let is_member = |ascii: fn(char) -> bool, unicode: fn(char) -> bool| {
+        if is_unicode {
+            unicode(char)
+        } else {
+            ascii(char)
+        }
+    };
+
+    match shorthand {
+        ClassShorthand::Digit => is_member(patterns::is_digit, patterns::is_unicode_digit),
+        ClassShorthand::NonDigit => !is_member(patterns::is_digit, patterns::is_unicode_digit),
+        ClassShorthand::Word => is_member(patterns::is_word, patterns::is_unicode_word),
+        ClassShorthand::NonWord => !is_member(patterns::is_word, patterns::is_unicode_word),
+        ClassShorthand::Whitespace => {
+            is_member(patterns::is_whitespace, patterns::is_unicode_whitespace)
+        }
+        ClassShorthand::NonWhitespace => {
+            !is_member(patterns::is_whitespace, patterns::is_unicode_whitespace)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_char_set_contains() {
+        let set = CharSet::new(vec!['x', '_'], vec![('a', 'f'), ('0', '9')], vec![], false);
+
+        assert!(set.contains('x'));
+        assert!(set.contains('_'));
+        assert!(set.contains('a'));
+        assert!(set.contains('c'));
+        assert!(set.contains('f'));
+        assert!(set.contains('0'));
+        assert!(!set.contains('g'));
+        assert!(!set.contains('y'));
+        assert!(!set.contains('A'));
+    }
+
+    #[test]
+    fn test_char_set_contains_shorthands() {
+        let set = CharSet::new(vec![], vec![], vec![ClassShorthand::Digit], false);
+        assert!(set.contains('7'));
+        assert!(!set.contains('a'));
+
+        let set = CharSet::new(vec![], vec![], vec![ClassShorthand::NonDigit], false);
+        assert!(set.contains('a'));
+        assert!(!set.contains('7'));
+
+        let set = CharSet::new(vec![], vec![], vec![ClassShorthand::Word], false);
+        assert!(set.contains('_'));
+        assert!(!set.contains('-'));
+
+        let set = CharSet::new(vec![], vec![], vec![ClassShorthand::NonWord], false);
+        assert!(set.contains('-'));
+        assert!(!set.contains('q'));
+
+        let set = CharSet::new(vec![], vec![], vec![ClassShorthand::Whitespace], false);
+        assert!(set.contains('\t'));
+        assert!(!set.contains('x'));
+
+        let set = CharSet::new(vec![], vec![], vec![ClassShorthand::NonWhitespace], false);
+        assert!(set.contains('x'));
+        assert!(!set.contains(' '));
+    }
+
+    #[test]
+    fn test_char_set_contains_all_member_kinds() {
+        let set = CharSet::new(vec!['.'], vec![('a', 'c')], vec![ClassShorthand::Digit], false);
+
+        assert!(set.contains('.'));
+        assert!(set.contains('b'));
+        assert!(set.contains('5'));
+        assert!(!set.contains('d'));
+        assert!(!set.contains(','));
+    }
+
+    #[test]
+    fn test_char_set_negated() {
+        let set = CharSet::new(vec!['.'], vec![('a', 'c')], vec![ClassShorthand::Digit], true);
+
+        assert!(!set.contains('.'));
+        assert!(!set.contains('b'));
+        assert!(!set.contains('5'));
+        assert!(set.contains('d'));
+        assert!(set.contains(','));
+        assert!(CharSet::new(vec![], vec![], vec![], true).contains('x'));
+    }
+
+    #[test]
+    fn test_char_set_unicode_shorthands() {
+        let set = CharSet::new(vec![], vec![], vec![ClassShorthand::Word], false);
+        assert!(!set.contains('é'));
+        assert!(set.with_unicode_shorthands().contains('é'));
+
+        let set = CharSet::new(vec![], vec![], vec![ClassShorthand::NonDigit], false);
+        assert!(set.contains('٣'));
+        assert!(!set.with_unicode_shorthands().contains('٣'));
+    }
+
+    #[test]
+    fn test_char_set_contains_any() {
+        let set = CharSet::new(vec![], vec![('a', 'c')], vec![], false);
+        assert!(set.contains_any(['B', 'b']));
+        assert!(!set.contains_any(['D', 'd']));
+
+        let set = CharSet::new(vec![], vec![('a', 'c')], vec![], true);
+        assert!(!set.contains_any(['B', 'b']));
+        assert!(set.contains_any(['D', 'd']));
+    }
+
+    #[test]
+    fn test_char_set_intersection() {
+        let vowels = CharSet::new(vec!['a', 'e', 'i', 'o', 'u'], vec![], vec![], true);
+        let set = CharSet::new(vec![], vec![('a', 'z')], vec![], false).with_intersection(vowels);
+
+        assert!(set.contains('b'));
+        assert!(set.contains('z'));
+        assert!(!set.contains('a'));
+        assert!(!set.contains('u'));
+        assert!(!set.contains('B'));
+        assert!(set.contains_any(['B', 'b']));
+        assert_eq!("[a-z&&[^aeiou]]", set.to_string());
+    }
+
+    #[test]
+    fn test_char_set_negated_intersection() {
+        let digits = CharSet::new(vec![], vec![], vec![ClassShorthand::Digit], false);
+        let set = CharSet::new(vec![], vec![('0', '9'), ('a', 'f')], vec![], true)
+            .with_intersection(digits);
+
+        assert!(!set.contains('3'));
+        assert!(set.contains('a'));
+        assert!(set.contains('x'));
+    }
+
+    #[test]
+    fn test_char_set_intersection_unicode_shorthands() {
+        let words = CharSet::new(vec![], vec![], vec![ClassShorthand::Word], false);
+        let set = CharSet::new(vec!['é', 'x'], vec![], vec![], false).with_intersection(words);
+
+        assert!(!set.contains('é'));
+        assert!(set.with_unicode_shorthands().contains('é'));
+    }
+
+    #[test]
+    fn test_char_set_display() {
+        let set = CharSet::new(vec!['x', '-'], vec![('a', 'c')], vec![ClassShorthand::Digit], false);
+        assert_eq!("[\\-xa-c\\d]", set.to_string());
+
+        let set = CharSet::new(vec![']', '\t'], vec![], vec![], true);
+        assert_eq!("[^\\t\\]]", set.to_string());
+    }
+
+    #[test]
+    fn test_char_set_empty() {
+        let set = CharSet::new(vec![], vec![], vec![], false);
+
+        assert!(!set.contains('a'));
+        assert_eq!(CharSet::default(), set);
+    }
+
+    #[test]
+    fn test_char_set_deduplicates_chars() {
+        let set = CharSet::new(vec!['c', 'a', 'a', 'b', 'b', 'c'], vec![], vec![], false);
+
+        assert_eq!(vec!['a', 'b', 'c'], set.chars);
+    }
+
+    #[test]
+    fn test_char_set_drops_chars_within_ranges() {
+        let set = CharSet::new(vec!['b', 'z'], vec![('a', 'c')], vec![], false);
+
+        assert_eq!(vec!['z'], set.chars);
+        assert!(set.contains('b'));
+    }
+
+    #[test]
+    fn test_char_set_merges_ranges() {
+        let ranges = vec![('m', 'p'), ('a', 'f'), ('d', 'h'), ('i', 'k')];
+        let set = CharSet::new(vec![], ranges, vec![], false);
+
+        assert_eq!(vec![('a', 'k'), ('m', 'p')], set.ranges);
+        assert!(set.contains('j'));
+        assert!(!set.contains('l'));
+    }
+}
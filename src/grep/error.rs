@@ -0,0 +1,77 @@
+use thiserror::Error;
+
+/// Errors reported when compiling a pattern.
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum ParseError {
+    /// A character class, group or comment is not closed. Like all indices of parse
+    /// errors, `index` is the index of the offending char within the pattern.
+    #[error("Incomplete {construct} (missing closing bracket) at index {index}")]
+    MissingClosingBracket { construct: &'static str, index: usize },
+
+    /// The pattern ends with a backslash that escapes nothing.
+    #[error("Incomplete escape sequence at index {index}")]
+    IncompleteEscape { index: usize },
+
+    /// A backslash escapes a char that has no escape sequence.
+    #[error("Unrecognized escape sequence '\\{escapee}' at index {index}")]
+    UnrecognizedEscape { escapee: char, index: usize },
+
+    /// A range within a character class ends before it starts, e.g. `[z-a]`.
+    #[error("Invalid character class range '{lower}-{upper}' at index {index}")]
+    InvalidClassRange { lower: char, upper: char, index: usize },
+
+    /// Members follow the intersection of a character class, e.g. `[a&&[b]c]`.
+    #[error("Invalid character class intersection, expected '&&[' at index {index}")]
+    InvalidClassIntersection { index: usize },
+
+    /// The name of a capture group is not closed by `>`.
+    #[error("Incomplete capture group name (missing '>') at index {index}")]
+    IncompleteGroupName { index: usize },
+
+    /// The name of a capture group is empty, starts with a digit or contains a char
+    /// other than a word char.
+    #[error("Invalid capture group name '{name}' at index {index}")]
+    InvalidGroupName { name: String, index: usize },
+
+    /// A quantifier does not follow an item it could repeat.
+    #[error("The {quantifier} modifier can only appear after another token at index {index}")]
+    MissingQuantifiedItem { quantifier: &'static str, index: usize },
+
+    /// The minimum of a repetition exceeds its maximum.
+    #[error("Invalid repetition, minimum exceeds maximum at index {index}")]
+    InvalidRepetition { index: usize },
+
+    /// A token cannot start any syntax item.
+    #[error("Malformed pattern, cannot parse token at index {index}")]
+    UnexpectedToken { index: usize },
+
+    /// The pattern contains more capture groups than allowed.
+    #[error("Pattern contains {count} capture groups, but at most {limit} are allowed")]
+    TooManyGroups { count: usize, limit: usize },
+
+    /// The groups of the pattern are nested deeper than allowed.
+    #[error("Pattern nests groups {depth} levels deep, but at most {limit} are allowed")]
+    TooDeeplyNested { depth: usize, limit: usize },
+
+    /// A backreference refers to a group that does not exist.
+    #[error("Backreference \\{id} refers to a capture group that does not exist")]
+    UnknownGroupReference { id: u32 },
+
+    /// A backreference refers to a group that is not closed at its position,
+    /// because it contains the backreference or follows it.
+    #[error("Backreference \\{id} refers to a capture group that is not closed before it")]
+    OpenGroupReference { id: u32 },
+
+    /// The pattern uses a construct of Perl-compatible patterns that is not supported,
+    /// like lookarounds or conditionals.
+    #[error("Pattern uses unsupported {feature} at index {index}")]
+    UnsupportedFeature { feature: &'static str, index: usize },
+
+    /// A lookbehind may match texts of different lengths, only fixed lengths are supported.
+    #[error("Lookbehind at index {index} does not have a fixed length")]
+    VariableLengthLookbehind { index: usize },
+
+    /// The pattern uses syntax the selected engine cannot execute.
+    #[error("The NFA engine does not support {feature}")]
+    UnsupportedByEngine { feature: &'static str },
+}
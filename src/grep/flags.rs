@@ -0,0 +1,123 @@
+use super::syntax::{self, Syntax};
+
+/// Options changing how a pattern matches, decided once when the pattern is compiled,
+/// see [`RegexBuilder`](super::RegexBuilder).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Flags {
+    /// Letters match regardless of their case.
+    pub case_insensitive: bool,
+
+    /// `^` and `$` match at the start and end of every line within the text.
+    pub multiline: bool,
+
+    /// `.` matches newlines as well.
+    pub dot_matches_newline: bool,
+
+    /// `\d`, `\w`, `\s` and their negations use Unicode instead of ASCII definitions.
+    pub unicode: bool,
+}
+
+impl Flags {
+    /// Returns the syntax items changed to match as the flags require. The matcher then
+    /// only needs to execute the items, it does not need to know about any flags.
+    pub fn apply(self, mut syntax: Vec<Syntax>) -> Vec<Syntax> {
+        if self.multiline {
+            syntax = syntax::multiline(syntax);
+        }
+
+        if self.dot_matches_newline {
+            syntax = syntax::dot_matches_newline(syntax);
+        }
+
+        if self.unicode {
+            syntax = syntax::unicode(syntax);
+        }
+
+        // Applied last, so classes created by the other flags are case insensitive too.
+        if self.case_insensitive {
+            syntax = syntax::ignore_case(syntax);
+        }
+
+        syntax
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grep::matcher;
+    use crate::grep::tokens::tokenize_pattern;
+
+    /// Returns true if the pattern with the flags applied matches at the start of the text.
+    fn matches(pattern: &str, text: &str, flags: Flags) -> bool {
+        let syntax = flags.apply(syntax::parse_pattern(&tokenize_pattern(pattern)));
+        matcher::run(&syntax, text, 0, 0, 0).is_some()
+    }
+
+    #[test]
+    fn test_flags_default() {
+        let flags = Flags::default();
+
+        assert!(!matches("abc", "ABC", flags));
+        assert!(!matches("a$", "a\nb", flags));
+        assert!(!matches("a.b", "a\nb", flags));
+        assert!(!matches("\\w", "é", flags));
+    }
+
+    #[test]
+    fn test_flags_case_insensitive() {
+        let flags = Flags {
+            case_insensitive: true,
+            ..Flags::default()
+        };
+
+        assert!(matches("abc", "ABC", flags));
+        assert!(matches("[a-c]+", "aBC", flags));
+        assert!(!matches("abc", "ABD", flags));
+    }
+
+    #[test]
+    fn test_flags_multiline() {
+        let flags = Flags {
+            multiline: true,
+            ..Flags::default()
+        };
+
+        assert!(matches("a$", "a\nb", flags));
+        assert!(!matches("a$", "ab", flags));
+    }
+
+    #[test]
+    fn test_flags_dot_matches_newline() {
+        let flags = Flags {
+            dot_matches_newline: true,
+            ..Flags::default()
+        };
+
+        assert!(matches("a.b", "a\nb", flags));
+        assert!(matches("a.b", "axb", flags));
+    }
+
+    #[test]
+    fn test_flags_unicode() {
+        let flags = Flags {
+            unicode: true,
+            ..Flags::default()
+        };
+
+        assert!(matches("\\w", "é", flags));
+        assert!(matches("\\d", "٣", flags));
+    }
+
+    #[test]
+    fn test_flags_combined() {
+        let flags = Flags {
+            case_insensitive: true,
+            unicode: true,
+            ..Flags::default()
+        };
+
+        assert!(matches("É\\w", "éÉ", flags));
+        assert!(!matches("É\\w", "é-", flags));
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::{HashMap, VecDeque};
+
+/// A state of the automaton, reached after reading a prefix of some of the literals.
+#[derive(Clone, Debug, Default)]
+struct State {
+    /// The states reached by reading one more char.
+    next: HashMap<char, usize>,
+
+    /// The state of the longest proper suffix of this prefix that is a prefix of some
+    /// literal, continued with when there is no transition for a char.
+    fail: usize,
+
+    /// Byte length of the prefix.
+    depth: usize,
+
+    /// Index of the first literal equal to the prefix, if any.
+    literal: Option<usize>,
+
+    /// The longest literal ending with this prefix, as the state of its last char.
+    /// Further literals ending here are found through the fail state of that state.
+    output: Option<usize>,
+}
+
+/// Which of the literals starting at the leftmost position is matched.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MatchKind {
+    /// The longest literal, like GNU grep matches a set of fixed strings.
+    LeftmostLongest,
+
+    /// The first literal in the given order, like an alternation is backtracked through.
+    LeftmostFirst,
+}
+
+/// A set of fixed strings searched all at once with an Aho-Corasick automaton, which
+/// reads every char of the text only once regardless of the number of literals.
+#[derive(Clone, Debug)]
+pub struct LiteralSet {
+    states: Vec<State>,
+    kind: MatchKind,
+}
+
+impl LiteralSet {
+    pub fn new(literals: &[&str], kind: MatchKind) -> LiteralSet {
+        let mut states = vec![State::default()];
+
+        for (index, literal) in literals.iter().enumerate() {
+            let mut current = 0;
+            for char in literal.chars() {
+                current = match states[current].next.get(&char) {
+                    Some(next) => *next,
+                    None => {
+                        let next = states.len();
+                        states.push(State {
+                            depth: states[current].depth + char.len_utf8(),
+                            ..Default::default()
+                        });
+                        states[current].next.insert(char, next);
+                        next
+                    }
+                };
+            }
+
+            states[current].literal.get_or_insert(index);
+        }
+
+        states[0].output = states[0].literal.map(|_| 0);
+
+        // Suffixes are shorter than the prefix, so visiting the states by increasing
+        // depth completes each fail state before the states falling back to it.
+        let mut queue = VecDeque::from([0]);
+        while let Some(current) = queue.pop_front() {
+            let transitions: Vec<(char, usize)> =
+                states[current].next.iter().map(|(c, s)| (*c, *s)).collect();
+
+            for (char, next) in transitions {
+                let fail = if current == 0 {
+                    0
+                } else {
+                    step(&states, states[current].fail, char)
+                };
+
+                states[next].fail = fail;
+                states[next].output = match states[next].literal {
+                    Some(_) => Some(next),
+                    None => states[fail].output,
+                };
+
+                queue.push_back(next);
+            }
+        }
+
+        LiteralSet { states, kind }
+    }
+
+    /// Returns the byte offsets of the leftmost match starting at or after `start`,
+    /// choosing among the literals starting there by the match kind.
+    pub fn find_at(&self, text: &str, start: usize) -> Option<(usize, usize)> {
+        let mut best = self.states[0].literal.map(|literal| (start, start, literal));
+        let mut current = 0;
+
+        for (index, char) in text[start..].char_indices() {
+            let end = start + index + char.len_utf8();
+            current = step(&self.states, current, char);
+
+            let mut output = self.states[current].output;
+            while let Some(state) = output {
+                let literal = self.states[state].literal.expect("Outputs are literals");
+                let candidate = (end - self.states[state].depth, end, literal);
+                if self.is_better(candidate, best) {
+                    best = Some(candidate);
+                }
+
+                output = match state {
+                    0 => None,
+                    _ => self.states[self.states[state].fail].output,
+                };
+            }
+
+            // Later matches start no earlier than the prefix read so far, so once that
+            // lies after the best match, no better match can follow.
+            if best.is_some_and(|(best_start, ..)| end - self.states[current].depth > best_start) {
+                break;
+            }
+        }
+
+        best.map(|(start, end, _)| (start, end))
+    }
+
+    /// Returns true if the candidate is to be matched instead of the best match so far,
+    /// both given by their start, end and literal index.
+    fn is_better(&self, candidate: (usize, usize, usize), best: Option<(usize, usize, usize)>) -> bool {
+        let Some(best) = best else {
+            return true;
+        };
+
+        let is_preferred = match self.kind {
+            MatchKind::LeftmostLongest => candidate.1 > best.1,
+            MatchKind::LeftmostFirst => candidate.2 < best.2,
+        };
+
+        candidate.0 < best.0 || (candidate.0 == best.0 && is_preferred)
+    }
+
+    /// Returns the byte offset at which the shortest literal matching at `start` ends.
+    pub fn shortest_at(&self, text: &str, start: usize) -> Option<usize> {
+        if self.states[0].literal.is_some() {
+            return Some(start);
+        }
+
+        let mut current = 0;
+        for (index, char) in text[start..].char_indices() {
+            current = *self.states[current].next.get(&char)?;
+
+            if self.states[current].literal.is_some() {
+                return Some(start + index + char.len_utf8());
+            }
+        }
+
+        None
+    }
+}
+
+/// Returns the state reached from `current` by reading the char, following the fail
+/// states until one has a transition for it.
+fn step(states: &[State], mut current: usize, char: char) -> usize {
+    loop {
+        if let Some(next) = states[current].next.get(&char) {
+            return *next;
+        }
+
+        if current == 0 {
+            return 0;
+        }
+
+        current = states[current].fail;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_literal_set_find_at() {
+        let set = LiteralSet::new(&["he", "she", "his", "hers"], MatchKind::LeftmostLongest);
+
+        assert_eq!(Some((1, 4)), set.find_at("ushers", 0));
+        assert_eq!(Some((2, 6)), set.find_at("ushers", 2));
+        assert_eq!(Some((0, 3)), set.find_at("his", 0));
+        assert_eq!(None, set.find_at("history", 3));
+    }
+
+    #[test]
+    fn test_literal_set_find_at_leftmost_longest() {
+        let set = LiteralSet::new(&["bcd", "abcde", "ab"], MatchKind::LeftmostLongest);
+
+        assert_eq!(Some((0, 5)), set.find_at("abcdef", 0));
+        assert_eq!(Some((0, 2)), set.find_at("abcxbcd", 0));
+        assert_eq!(Some((4, 7)), set.find_at("abcxbcd", 2));
+    }
+
+    #[test]
+    fn test_literal_set_find_at_leftmost_first() {
+        let set = LiteralSet::new(&["ab", "abcde", "bcd"], MatchKind::LeftmostFirst);
+
+        assert_eq!(Some((0, 2)), set.find_at("abcdef", 0));
+        assert_eq!(Some((1, 4)), set.find_at("abcdef", 1));
+
+        let set = LiteralSet::new(&["bc", "abcd", "b"], MatchKind::LeftmostFirst);
+        assert_eq!(Some((0, 4)), set.find_at("abcd", 0));
+        assert_eq!(Some((1, 3)), set.find_at("abcx", 0));
+    }
+
+    #[test]
+    fn test_literal_set_find_at_suffix_literals() {
+        // All of "abc", "bc" and "c" end at the same position.
+        let set = LiteralSet::new(&["c", "bc", "abc"], MatchKind::LeftmostFirst);
+        assert_eq!(Some((0, 3)), set.find_at("abc", 0));
+
+        let set = LiteralSet::new(&["c", "bc", "xabcd"], MatchKind::LeftmostLongest);
+        assert_eq!(Some((1, 3)), set.find_at("abc", 0));
+    }
+
+    #[test]
+    fn test_literal_set_find_at_multibyte() {
+        let set = LiteralSet::new(&["café", "é"], MatchKind::LeftmostLongest);
+
+        assert_eq!(Some((3, 8)), set.find_at("le café", 0));
+        assert_eq!(Some((6, 8)), set.find_at("le café", 4));
+    }
+
+    #[test]
+    fn test_literal_set_find_at_empty() {
+        assert_eq!(Some((2, 2)), LiteralSet::new(&["", "x"], MatchKind::LeftmostLongest).find_at("abc", 2));
+        assert_eq!(Some((0, 1)), LiteralSet::new(&["", "x"], MatchKind::LeftmostLongest).find_at("xyz", 0));
+        assert_eq!(None, LiteralSet::new(&[], MatchKind::LeftmostLongest).find_at("abc", 0));
+    }
+
+    #[test]
+    fn test_literal_set_shortest_at() {
+        let set = LiteralSet::new(&["abcde", "ab", "x"], MatchKind::LeftmostLongest);
+
+        assert_eq!(Some(2), set.shortest_at("abcdef", 0));
+        assert_eq!(None, set.shortest_at("abcdef", 1));
+        assert_eq!(Some(0), LiteralSet::new(&[""], MatchKind::LeftmostLongest).shortest_at("abc", 0));
+    }
+}
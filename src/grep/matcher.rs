@@ -0,0 +1,1012 @@
+//! Executes parsed patterns by backtracking, see [`run`].
+
+use std::{collections::HashMap, iter, ops::Deref};
+
+use super::anchors;
+use super::charset::CharSet;
+use super::patterns;
+use super::regex::Span;
+use super::syntax::{self, Syntax};
+
+/// The result of matching a pattern at a position within the text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchResult {
+    /// Byte offset of the start of the match within the text.
+    pub start: usize,
+
+    /// Byte offset of the end of the match within the text.
+    pub end: usize,
+
+    /// The spans of the capture groups by capture group id, where group 0 is the whole
+    /// match. Groups that did not take part in the match are `None`.
+    pub groups: Vec<Option<Span>>,
+}
+
+impl MatchResult {
+    /// Returns the span of the whole match.
+    pub fn span(&self) -> Span {
+        Span {
+            start: self.start,
+            end: self.end,
+        }
+    }
+}
+
+/// Matches the pattern at the byte offset `start` of the text, trying all options and
+/// quantifier lengths before giving up. Anchors are evaluated against the whole text
+/// and `\G` matches at the byte offset `search_start`, where the search began. The
+/// groups of the result are reported up to the id `group_count`.
+pub fn run(
+    pattern: &[Syntax],
+    text: &str,
+    start: usize,
+    search_start: usize,
+    group_count: usize,
+) -> Option<MatchResult> {
+    let mut cgroups = HashMap::new();
+    let bounds = Bounds {
+        text,
+        search_start_len: text.len() - search_start,
+        is_capturing: true,
+    };
+
+    let m = match_here(&text[start..], pattern, &mut cgroups, bounds)?;
+    let end = start + m.byte_len();
+    let groups = iter::once(Some(Span { start, end }))
+        .chain((1..=group_count as u32).map(|id| cgroups.get(&id).map(|c| c.span(text))))
+        .collect();
+
+    Some(MatchResult { start, end, groups })
+}
+
+/// Returns true if the pattern matches at the byte offset `start` of the text, like
+/// [`run`], but without recording capture groups, so nothing is allocated for them.
+/// The pattern must not contain backreferences, they need the captured texts.
+pub fn is_match_at(pattern: &[Syntax], text: &str, start: usize, search_start: usize) -> bool {
+    let bounds = Bounds {
+        text,
+        search_start_len: text.len() - search_start,
+        is_capturing: false,
+    };
+
+    match_here(&text[start..], pattern, &mut HashMap::new(), bounds).is_some()
+}
+
+/// Returns true if the pattern matches from the byte offset `start` of the text up to
+/// exactly the byte offset `end`. Other options and quantifier lengths are tried until
+/// a match ends there, so `(a|ab)` matches "ab" up to its end although the leftmost
+/// match is "a". Like for [`run`] starting at 0, `\G` matches at the start of the text.
+pub fn is_match_until(pattern: &[Syntax], text: &str, start: usize, end: usize) -> bool {
+    let bounds = Bounds {
+        text,
+        search_start_len: text.len(),
+        is_capturing: syntax::has_back_references(pattern),
+    };
+    let until_end = Continuation::MatchEnd {
+        remaining_len: text.len() - end,
+    };
+
+    match_then(&text[start..], pattern, &until_end, &mut HashMap::new(), bounds).is_some()
+}
+
+/// The text matched by (a part of) a pattern, which starts where the pattern was
+/// matched. Only its length is kept, so matching allocates nothing for it.
+#[derive(Clone, Copy, Debug)]
+struct Match {
+    /// Byte length of the matched text.
+    len: usize,
+}
+
+impl Match {
+    /// Creates a match for the empty string.
+    fn empty() -> Match {
+        Match { len: 0 }
+    }
+
+    fn from_str(text: &str) -> Match {
+        Match { len: text.len() }
+    }
+
+    /// Merges two Matches, the tail starting where the head ends.
+    fn merge(head: Match, tail: Match) -> Match {
+        Match {
+            len: head.len + tail.len,
+        }
+    }
+
+    /// Returns the length of the matched text in bytes.
+    fn byte_len(&self) -> usize {
+        self.len
+    }
+}
+
+/// The position of the text matched by a capture group.
+#[derive(Clone, Copy, Debug)]
+struct Capture {
+    /// Byte length of the text remaining at the start of the capture. Since all
+    /// matched texts are suffixes of the same input, this identifies the position
+    /// of the capture within the input.
+    remaining_len: usize,
+
+    /// Byte length of the captured text.
+    len: usize,
+}
+
+impl Capture {
+    /// Returns the span of the captured text within the whole searched text.
+    fn span(&self, text: &str) -> Span {
+        let start = text.len() - self.remaining_len;
+        Span {
+            start,
+            end: start + self.len,
+        }
+    }
+
+    /// Returns the captured text within the whole searched text.
+    fn as_str<'t>(&self, text: &'t str) -> &'t str {
+        let span = self.span(text);
+        &text[span.start..span.end]
+    }
+}
+
+/// Results of all capture groups matched so far, by capture group id.
+type CaptureGroups = HashMap<u32, Capture>;
+
+/// Positions within the searched text that anchors are evaluated against. Like for
+/// capture groups, positions are given as the byte length of the text remaining there.
+#[derive(Clone, Copy, Debug)]
+struct Bounds<'t> {
+    /// The whole searched text, which starts at the position of `^`.
+    text: &'t str,
+
+    /// Byte length of the text remaining where the search started, i.e. the position of `\G`.
+    search_start_len: usize,
+
+    /// Capture groups are recorded. Only backreferences and the caller need them.
+    is_capturing: bool,
+}
+
+impl Bounds<'_> {
+    /// Returns the byte offset into the whole text where the remaining text starts.
+    fn position(&self, remaining_text: &str) -> usize {
+        self.text.len() - remaining_text.len()
+    }
+}
+
+/// What remains to be matched once the current pattern slice has matched. Groups
+/// extend the continuation of their options instead of copying the remainder of the
+/// pattern into each of them.
+#[derive(Clone, Copy, Debug)]
+enum Continuation<'c> {
+    /// Nothing remains, the match ends here.
+    Done,
+
+    /// The pattern is matched next, followed by the next continuation.
+    Pattern {
+        pattern: &'c [Syntax],
+        next: &'c Continuation<'c>,
+    },
+
+    /// The capture group that started where `remaining_len` bytes of text remained
+    /// ends here, followed by the next continuation.
+    CaptureGroupEnd {
+        id: u32,
+        remaining_len: usize,
+        next: &'c Continuation<'c>,
+    },
+
+    /// An iteration of the repeated syntax that started where `remaining_len` bytes
+    /// of text remained ends here. At least `min` and at most `max` more iterations
+    /// follow, then the next continuation.
+    Repeat {
+        syntax: &'c Syntax,
+        min: usize,
+        max: Option<usize>,
+        remaining_len: usize,
+        next: &'c Continuation<'c>,
+    },
+
+    /// The match ends here if exactly `remaining_len` bytes of text remain, otherwise
+    /// it fails and backtracking continues.
+    MatchEnd { remaining_len: usize },
+}
+
+/// A syntax item that always matches exactly one char, the only kind of item
+/// [`matches_char`] accepts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SingleCharSyntax<'s> {
+    Literal(char),
+    Digit,
+    Word,
+    Wildcard,
+    Class(&'s CharSet),
+}
+
+impl<'s> SingleCharSyntax<'s> {
+    /// Returns the single char syntax of the item together with whether it matches any
+    /// case variant of a char, or `None` if the item does not always match exactly one char.
+    pub fn of(syntax: &'s Syntax) -> Option<(SingleCharSyntax<'s>, bool)> {
+        let single = match syntax {
+            Syntax::Literal { char } => SingleCharSyntax::Literal(*char),
+            Syntax::Digit => SingleCharSyntax::Digit,
+            Syntax::Word => SingleCharSyntax::Word,
+            Syntax::Wildcard => SingleCharSyntax::Wildcard,
+            Syntax::CharacterClass { set } => SingleCharSyntax::Class(set),
+            Syntax::CaseInsensitive { syntax } => {
+                return SingleCharSyntax::of(syntax).map(|(single, _)| (single, true));
+            }
+            _ => return None,
+        };
+
+        Some((single, false))
+    }
+}
+
+/// Returns true if the single char syntax matches the char. Unlike matching a whole
+/// pattern, this cannot fail on syntax that does not consume exactly one char.
+pub fn matches_char(char: char, syntax: SingleCharSyntax, is_case_insensitive: bool) -> bool {
+    if is_case_insensitive {
+        // Ranges may cross cases (e.g. `[A-z]`), so every variant is tested on its own.
+        let mut variants = iter::once(char).chain(patterns::case_variants(char));
+
+        return match syntax {
+            // Negation applies to the class as a whole, a negated class must not
+            // match any variant of the char.
+            SingleCharSyntax::Class(set) => set.contains_any(variants),
+            other => variants.any(|v| matches_char(v, other, false)),
+        };
+    }
+
+    match syntax {
+        // Lines are matched one at a time, so `.` never crosses into the next line.
+        SingleCharSyntax::Wildcard => char != '\n',
+        SingleCharSyntax::Literal(c) => c == char,
+        SingleCharSyntax::Digit => patterns::is_digit(char),
+        SingleCharSyntax::Word => patterns::is_word(char),
+        SingleCharSyntax::Class(set) => set.contains(char),
+    }
+}
+
+/// Returns the repeated item of a quantifier with its minimum and maximum number of
+/// iterations, `None` for the maximum if unlimited.
+fn quantifier(syntax: &Syntax) -> (&Syntax, usize, Option<usize>) {
+    let (item, min, max) = match syntax {
+        Syntax::OneOrMore { syntax } => (syntax, 1, None),
+        Syntax::ZeroOrMore { syntax } => (syntax, 0, None),
+        Syntax::ZeroOrOne { syntax } => (syntax, 0, Some(1)),
+        Syntax::Repeat { syntax, min, max } => (syntax, *min, *max),
+        other => panic!("Quantifier expected, but found {:?}", other),
+    };
+
+    (item.deref(), min, max)
+}
+
+/// Returns true if every iteration of the item matches the same number of chars, at least
+/// one, and the item captures nothing but its own group. Two matches of such an item at
+/// the same position end at the same position and capture the same text, so it is
+/// repeated by [`match_fixed_repeat`] without backtracking into the iterations.
+fn is_fixed_iteration(item: &Syntax) -> bool {
+    let pattern = std::slice::from_ref(item);
+    let has_nested_groups = match item {
+        Syntax::CaptureGroup { options, .. } => {
+            options.iter().any(|o| syntax::has_capture_groups(o))
+        }
+        _ => syntax::has_capture_groups(pattern),
+    };
+
+    let len = syntax::min_len(pattern);
+    len > 0 && syntax::max_len(pattern) == Some(len) && !has_nested_groups
+}
+
+/// Stack space below which the stack is grown before another iteration is matched,
+/// enough for everything matched between two iterations.
+const STACK_RED_ZONE: usize = 128 * 1024;
+
+/// Size of the stack segments allocated on the heap when the stack is grown.
+const STACK_SEGMENT_SIZE: usize = 4 * 1024 * 1024;
+
+/// Matches the syntax at least `min` and at most `max` times, or without limit if
+/// `max` is `None`, followed by the continuation. Each iteration is matched like a
+/// group, so the syntax may match any number of chars.
+fn match_repeat(
+    text: &str,
+    syntax: &Syntax,
+    min: usize,
+    max: Option<usize>,
+    next: &Continuation,
+    cgroups: &mut CaptureGroups,
+    bounds: Bounds,
+) -> Option<Match> {
+    // Greedily try another iteration first, then continue without it.
+    if max != Some(0) {
+        let iteration_end = Continuation::Repeat {
+            syntax,
+            min: min.saturating_sub(1),
+            max: max.map(|max| max - 1),
+            remaining_len: text.len(),
+            next,
+        };
+
+        // The iterations of a long text need more stack than a thread has, so the
+        // stack is grown on the heap instead of overflowing.
+        let pattern = std::slice::from_ref(syntax);
+        let match_total = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, || {
+            match_then(text, pattern, &iteration_end, cgroups, bounds)
+        });
+        if match_total.is_some() {
+            return match_total;
+        }
+    }
+
+    if min > 0 {
+        return None;
+    }
+
+    match_continuation(text, next, cgroups, bounds)
+}
+
+/// Matches the single char syntax at least `min` and at most `max` times, or without
+/// limit if `max` is `None`, followed by the continuation.
+fn match_star(
+    text: &str,
+    syntax: &Syntax,
+    min: usize,
+    max: Option<usize>,
+    next: &Continuation,
+    cgroups: &mut CaptureGroups,
+    bounds: Bounds,
+) -> Option<Match> {
+    // Greedily consume as many chars as possible, then backtrack one char
+    // at a time until the remainder of the pattern matches as well.
+    let Some((single, is_case_insensitive)) = SingleCharSyntax::of(syntax) else {
+        panic!("Only single char syntax can be repeated, but found {:?}", syntax);
+    };
+
+    // The repetitions are counted on the borrowed item, so nothing is cloned or allocated.
+    let mut repetitions = 0;
+    let mut head_len = 0;
+    for c in text.chars().take(max.unwrap_or(usize::MAX)) {
+        if !matches_char(c, single, is_case_insensitive) {
+            break;
+        }
+
+        repetitions += 1;
+        head_len += c.len_utf8();
+    }
+
+    if repetitions < min {
+        return None;
+    }
+
+    loop {
+        let remaining_text = &text[head_len..];
+        if let Some(match_tail) = match_continuation(remaining_text, next, cgroups, bounds) {
+            return Some(Match::merge(Match { len: head_len }, match_tail));
+        }
+
+        if repetitions == min {
+            return None;
+        }
+
+        repetitions -= 1;
+        head_len -= text[..head_len].chars().next_back().map_or(0, char::len_utf8);
+    }
+}
+
+/// Matches the item at least `min` and at most `max` times, or without limit if `max`
+/// is `None`, followed by the continuation. Like single chars in [`match_star`], the
+/// iterations are counted in a loop, which requires the item to be
+/// [a fixed iteration](is_fixed_iteration). Only the group of the item captures, so
+/// its capture is recorded for the last iteration instead of by each of them.
+fn match_fixed_repeat(
+    text: &str,
+    syntax: &Syntax,
+    min: usize,
+    max: Option<usize>,
+    next: &Continuation,
+    cgroups: &mut CaptureGroups,
+    bounds: Bounds,
+) -> Option<Match> {
+    let pattern = std::slice::from_ref(syntax);
+    let iteration_len = syntax::min_len(pattern);
+    let iteration_bounds = Bounds {
+        is_capturing: false,
+        ..bounds
+    };
+
+    let mut repetitions = 0;
+    let mut head_len = 0;
+    while max.map_or(true, |max| repetitions < max) {
+        let Some(iteration) = match_here(&text[head_len..], pattern, cgroups, iteration_bounds)
+        else {
+            break;
+        };
+
+        repetitions += 1;
+        head_len += iteration.byte_len();
+    }
+
+    if repetitions < min {
+        return None;
+    }
+
+    loop {
+        let iteration_start = head_len
+            - text[..head_len]
+                .chars()
+                .rev()
+                .take(iteration_len)
+                .map(char::len_utf8)
+                .sum::<usize>();
+
+        let group = match syntax {
+            Syntax::CaptureGroup { id, .. } if bounds.is_capturing && repetitions > 0 => {
+                let capture = Capture {
+                    remaining_len: text.len() - iteration_start,
+                    len: head_len - iteration_start,
+                };
+                Some((*id, cgroups.insert(*id, capture)))
+            }
+            _ => None,
+        };
+
+        let remaining_text = &text[head_len..];
+        if let Some(match_tail) = match_continuation(remaining_text, next, cgroups, bounds) {
+            return Some(Match::merge(Match { len: head_len }, match_tail));
+        }
+
+        // Restore the capture of the previous iteration before backtracking into it.
+        match group {
+            Some((id, Some(previous))) => cgroups.insert(id, previous),
+            Some((id, None)) => cgroups.remove(&id),
+            None => None,
+        };
+
+        if repetitions == min {
+            return None;
+        }
+
+        repetitions -= 1;
+        head_len = iteration_start;
+    }
+}
+
+/// Matches the pattern at the start of the text, which is a suffix of the searched text.
+fn match_here(
+    text: &str,
+    pattern: &[Syntax],
+    cgroups: &mut CaptureGroups,
+    bounds: Bounds,
+) -> Option<Match> {
+    match_then(text, pattern, &Continuation::Done, cgroups, bounds)
+}
+
+/// Matches the pattern followed by the continuation at the start of the text.
+fn match_then(
+    text: &str,
+    pattern: &[Syntax],
+    next: &Continuation,
+    cgroups: &mut CaptureGroups,
+    bounds: Bounds,
+) -> Option<Match> {
+    let Some(syntax) = pattern.first() else {
+        return match_continuation(text, next, cgroups, bounds);
+    };
+
+    match syntax {
+        Syntax::OneOrMore { .. }
+        | Syntax::ZeroOrMore { .. }
+        | Syntax::ZeroOrOne { .. }
+        | Syntax::Repeat { .. } => {
+            let (item, min, max) = quantifier(syntax);
+            let remainder = Continuation::Pattern {
+                pattern: &pattern[1..],
+                next,
+            };
+
+            // Single chars and fixed iterations are counted in a loop, any other item is
+            // matched like a group.
+            return match SingleCharSyntax::of(item) {
+                Some(_) => match_star(text, item, min, max, &remainder, cgroups, bounds),
+                None if is_fixed_iteration(item) => {
+                    match_fixed_repeat(text, item, min, max, &remainder, cgroups, bounds)
+                }
+                None => match_repeat(text, item, min, max, &remainder, cgroups, bounds),
+            };
+        }
+
+        Syntax::CaptureGroup { options: os, id, .. } => {
+            // Each option is followed by the end of the group, which records the
+            // capture, and then by the remainder of the pattern.
+            let remainder = Continuation::Pattern {
+                pattern: &pattern[1..],
+                next,
+            };
+            let end = Continuation::CaptureGroupEnd {
+                id: *id,
+                remaining_len: text.len(),
+                next: &remainder,
+            };
+
+            return os
+                .iter()
+                .find_map(|option| match_then(text, option, &end, cgroups, bounds));
+        }
+
+        Syntax::AtomicGroup { options: os } => {
+            // The group is matched on its own and the first successful option is final,
+            // a failing remainder does not retry the group with another option or length.
+            let cgroups_before = cgroups.clone();
+            let match_group = os
+                .iter()
+                .find_map(|option| match_here(text, option, cgroups, bounds))?;
+
+            let remaining_text = &text[match_group.byte_len()..];
+            let Some(match_remainder) =
+                match_then(remaining_text, &pattern[1..], next, cgroups, bounds)
+            else {
+                // Discard the capture groups matched within the atomic group.
+                *cgroups = cgroups_before;
+                return None;
+            };
+
+            return Some(Match::merge(match_group, match_remainder));
+        }
+
+        Syntax::Lookahead {
+            options: os,
+            is_negated,
+        } => {
+            // The options are matched on their own without consuming any text. Groups
+            // captured within a positive lookahead are kept, like for an atomic group,
+            // while a negative lookahead only succeeds if no option captured anything.
+            let cgroups_before = cgroups.clone();
+            let is_match = os
+                .iter()
+                .any(|option| match_here(text, option, cgroups, bounds).is_some());
+
+            return match_after_assertion(
+                is_match != *is_negated,
+                text,
+                &pattern[1..],
+                next,
+                cgroups,
+                cgroups_before,
+                bounds,
+            );
+        }
+
+        Syntax::Lookbehind {
+            options: os,
+            is_negated,
+            len,
+        } => {
+            // The options have to match the `len` chars before the current position exactly,
+            // which may lie before where the search started. Groups are kept like for a lookahead.
+            let position = bounds.position(text);
+            let start = match len {
+                0 => Some(position),
+                len => bounds.text[..position]
+                    .char_indices()
+                    .rev()
+                    .nth(len - 1)
+                    .map(|(index, _)| index),
+            };
+
+            let cgroups_before = cgroups.clone();
+            let until_end = Continuation::MatchEnd {
+                remaining_len: text.len(),
+            };
+            let is_match = start.is_some_and(|start| {
+                os.iter().any(|option| {
+                    match_then(&bounds.text[start..], option, &until_end, cgroups, bounds).is_some()
+                })
+            });
+
+            return match_after_assertion(
+                is_match != *is_negated,
+                text,
+                &pattern[1..],
+                next,
+                cgroups,
+                cgroups_before,
+                bounds,
+            );
+        }
+
+        Syntax::LiteralRun { text: run } => {
+            let remaining_text = text.strip_prefix(run.as_str())?;
+            let match_remainder = match_then(remaining_text, &pattern[1..], next, cgroups, bounds)?;
+
+            return Some(Match::merge(Match::from_str(run), match_remainder));
+        }
+
+        Syntax::BackReference { id } => {
+            // A group within an alternation option that was not taken has not
+            // captured anything, so a reference to it cannot match.
+            let captured = cgroups.get(id)?.as_str(bounds.text);
+            let remaining_text = text.strip_prefix(captured)?;
+            let match_remainder = match_then(remaining_text, &pattern[1..], next, cgroups, bounds)?;
+
+            return Some(Match::merge(Match::from_str(captured), match_remainder));
+        }
+
+        Syntax::EndOfLineAnchor => {
+            // Positions are relative to the remaining text, which starts at 0.
+            if !anchors::matches_end(0, text.len()) {
+                return None;
+            }
+
+            return match_then(text, &pattern[1..], next, cgroups, bounds);
+        }
+
+        Syntax::StartOfLineAnchor => {
+            if !anchors::matches_start(bounds.position(text)) {
+                return None;
+            }
+
+            return match_then(text, &pattern[1..], next, cgroups, bounds);
+        }
+
+        Syntax::StartOfAnyLineAnchor => {
+            if !anchors::matches_line_start(bounds.text, bounds.position(text)) {
+                return None;
+            }
+
+            return match_then(text, &pattern[1..], next, cgroups, bounds);
+        }
+
+        Syntax::EndOfAnyLineAnchor => {
+            if !anchors::matches_line_end(bounds.text, bounds.position(text)) {
+                return None;
+            }
+
+            return match_then(text, &pattern[1..], next, cgroups, bounds);
+        }
+
+        Syntax::PreviousMatchEnd => {
+            if text.len() != bounds.search_start_len {
+                return None;
+            }
+
+            return match_then(text, &pattern[1..], next, cgroups, bounds);
+        }
+
+        // Single char items are matched as a run below.
+        Syntax::Literal { .. }
+        | Syntax::Digit
+        | Syntax::Word
+        | Syntax::Wildcard
+        | Syntax::CharacterClass { .. }
+        | Syntax::CaseInsensitive { .. } => {}
+    }
+
+    // The pattern starts with syntax matching a single char. A run of such items is matched in a
+    // loop instead of recursing per item, so long literal sequences do not grow the stack.
+    let mut run_len = 0;
+    let mut run_byte_len = 0;
+
+    for (single, is_case_insensitive) in pattern.iter().map_while(SingleCharSyntax::of) {
+        let c = text[run_byte_len..].chars().next()?;
+        if !matches_char(c, single, is_case_insensitive) {
+            return None;
+        }
+
+        run_len += 1;
+        run_byte_len += c.len_utf8();
+    }
+
+    let remaining_text = &text[run_byte_len..];
+    let match_remainder = match_then(remaining_text, &pattern[run_len..], next, cgroups, bounds)?;
+
+    let match_run = Match { len: run_byte_len };
+    Some(Match::merge(match_run, match_remainder))
+}
+
+/// Continues matching the remainder at the position of a lookaround if its assertion
+/// holds. The capture groups are reset if it does not or if the remainder fails.
+fn match_after_assertion(
+    is_asserted: bool,
+    text: &str,
+    pattern_remainder: &[Syntax],
+    next: &Continuation,
+    cgroups: &mut CaptureGroups,
+    cgroups_before: CaptureGroups,
+    bounds: Bounds,
+) -> Option<Match> {
+    let match_remainder = is_asserted
+        .then(|| match_then(text, pattern_remainder, next, cgroups, bounds))
+        .flatten();
+
+    if match_remainder.is_none() {
+        *cgroups = cgroups_before;
+    }
+
+    match_remainder
+}
+
+/// Matches the continuation once the pattern before it matched.
+fn match_continuation(
+    text: &str,
+    next: &Continuation,
+    cgroups: &mut CaptureGroups,
+    bounds: Bounds,
+) -> Option<Match> {
+    match next {
+        // The entire pattern matched, return success.
+        Continuation::Done => Some(Match::empty()),
+        Continuation::Pattern { pattern, next } => match_then(text, pattern, next, cgroups, bounds),
+        Continuation::CaptureGroupEnd {
+            id,
+            remaining_len,
+            next,
+        } => {
+            if !bounds.is_capturing {
+                return match_continuation(text, next, cgroups, bounds);
+            }
+
+            let capture = Capture {
+                remaining_len: *remaining_len,
+                len: remaining_len - text.len(),
+            };
+
+            // A repeated group replaces the capture of its previous iteration.
+            let previous = cgroups.insert(*id, capture);
+
+            let match_remainder = match_continuation(text, next, cgroups, bounds);
+            if match_remainder.is_none() {
+                // If the remainder does not match, the next option of the group is
+                // tried, so the capture group result has to be restored again.
+                match previous {
+                    Some(previous) => cgroups.insert(*id, previous),
+                    None => cgroups.remove(id),
+                };
+            }
+
+            match_remainder
+        }
+
+        Continuation::Repeat {
+            syntax,
+            min,
+            max,
+            remaining_len,
+            next,
+        } => {
+            // Another optional iteration without consuming text would repeat forever,
+            // so an empty iteration ends the repetition.
+            if *min == 0 && text.len() == *remaining_len {
+                return match_continuation(text, next, cgroups, bounds);
+            }
+
+            match_repeat(text, syntax, *min, *max, next, cgroups, bounds)
+        }
+
+        Continuation::MatchEnd { remaining_len } => {
+            (text.len() == *remaining_len).then(Match::empty)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grep::syntax::{self, parse_pattern};
+    use crate::grep::tokens::{self, tokenize_pattern};
+
+    /// Runs the pattern at the byte offset `start`, returning the matched text.
+    fn run_at(pattern: &str, text: &str, start: usize) -> Option<String> {
+        let syntax = parse_pattern(&tokenize_pattern(pattern));
+        run(&syntax, text, start, start, 0).map(|result| text[start..result.end].to_string())
+    }
+
+    #[test]
+    fn test_run() {
+        assert_eq!(Some("abb".to_string()), run_at("ab+", "xabbc", 1));
+        assert_eq!(None, run_at("ab+", "xabbc", 0));
+        assert_eq!(Some("".to_string()), run_at("x*", "abc", 3));
+        assert_eq!(Some("é".to_string()), run_at(".", "aé", 1));
+    }
+
+    #[test]
+    fn test_run_backtracks() {
+        assert_eq!(Some("abc".to_string()), run_at("(a|ab)c", "abc", 0));
+        assert_eq!(Some("aaab".to_string()), run_at("a*a*ab", "aaab", 0));
+        assert_eq!(None, run_at("(?>a*)ab", "aaab", 0));
+    }
+
+    #[test]
+    fn test_run_single_char_repetitions() {
+        assert_eq!(Some("ééé".to_string()), run_at("é{2,}", "xééé", 1));
+        assert_eq!(Some("ééé".to_string()), run_at("é{2,3}é", "ééé", 0));
+        assert_eq!(None, run_at("é{2}", "éx", 0));
+        assert_eq!(Some("abcd".to_string()), run_at("[a-z]+d", "abcd", 0));
+        assert_eq!(Some("ab".to_string()), run_at("a?ab", "ab", 0));
+        assert_eq!(None, run_at("a{3}", "aab", 0));
+    }
+
+    #[test]
+    fn test_run_fixed_iterations() {
+        assert_eq!(Some("abab".to_string()), run_at("(ab)+", "ababa", 0));
+        assert_eq!(Some("ababab".to_string()), run_at("(ab)+ab", "ababab", 0));
+        assert_eq!(Some("abb".to_string()), run_at("(a|b){2,}b", "abbc", 0));
+        assert_eq!(None, run_at("(ab){2}ab", "ababc", 0));
+        assert_eq!(Some("éxéx".to_string()), run_at("(?>é.)*", "éxéx", 0));
+        assert_eq!(Some("abab".to_string()), run_at("(a(?=b)b)+", "ababa", 0));
+    }
+
+    #[test]
+    fn test_run_fixed_iterations_capture_last_iteration() {
+        let span = |start, end| Some(Span { start, end });
+        let groups = |pattern, text| {
+            let syntax = parse_pattern(&tokenize_pattern(pattern));
+            run(&syntax, text, 0, 0, 2).map(|result| result.groups)
+        };
+
+        assert_eq!(Some(vec![span(0, 6), span(4, 6), None]), groups("(ab)+", "ababab"));
+        assert_eq!(Some(vec![span(0, 6), span(2, 4), None]), groups("(ab)+ab", "ababab"));
+        assert_eq!(Some(vec![span(0, 1), None, None]), groups("(ab)*a", "ab"));
+        assert_eq!(Some(vec![span(0, 4), span(2, 3), None]), groups("(.)+(?<=b)c", "abbc"));
+        assert_eq!(Some(vec![span(0, 2), span(0, 1), span(1, 2)]), groups("(a)(b)*", "ab"));
+    }
+
+    #[test]
+    fn test_run_anchors_within_whole_text() {
+        let syntax = parse_pattern(&tokenize_pattern("^b"));
+        assert!(run(&syntax, "ab", 1, 1, 0).is_none());
+
+        assert_eq!(Some("b".to_string()), run_at("b$", "ab", 1));
+        assert_eq!(None, run_at("b$", "abc", 1));
+        assert_eq!(Some("a".to_string()), run_at("(?<=x)a", "xa", 1));
+    }
+
+    #[test]
+    fn test_run_previous_match_end() {
+        let syntax = parse_pattern(&tokenize_pattern("\\Gb"));
+
+        assert!(run(&syntax, "ab", 1, 1, 0).is_some());
+        assert!(run(&syntax, "ab", 1, 0, 0).is_none());
+    }
+
+    #[test]
+    fn test_run_capture_groups() {
+        let syntax = parse_pattern(&tokenize_pattern("(\\d+)-(x)?(\\d+)"));
+        let span = |start, end| Some(Span { start, end });
+
+        assert_eq!(
+            Some(MatchResult {
+                start: 2,
+                end: 7,
+                groups: vec![span(2, 7), span(2, 4), None, span(5, 7)],
+            }),
+            run(&syntax, "n=12-34", 2, 0, 3)
+        );
+        assert_eq!(
+            Some(MatchResult {
+                start: 2,
+                end: 7,
+                groups: vec![span(2, 7)],
+            }),
+            run(&syntax, "n=12-34", 2, 0, 0)
+        );
+        assert_eq!(None, run(&syntax, "n=12-34", 0, 0, 3));
+    }
+
+    #[test]
+    fn test_is_match_at() {
+        let is_match_at = |pattern, text, start| {
+            is_match_at(&parse_pattern(&tokenize_pattern(pattern)), text, start, start)
+        };
+
+        assert!(is_match_at("(\\d+)-(x)?(\\d+)", "n=12-34", 2));
+        assert!(!is_match_at("(\\d+)-(x)?(\\d+)", "n=12-34", 0));
+        assert!(is_match_at("^(a|ab)(c|bcd)(d*)$", "abcd", 0));
+        assert!(is_match_at("(?>(a+))b", "aab", 0));
+        assert!(!is_match_at("(?>a*)ab", "aab", 0));
+        assert!(is_match_at("(?<=(x))a", "xa", 1));
+    }
+
+    #[test]
+    fn test_is_match_until() {
+        let is_match_until = |pattern, text, start, end| {
+            is_match_until(&parse_pattern(&tokenize_pattern(pattern)), text, start, end)
+        };
+
+        assert!(is_match_until("(a|ab)", "ab", 0, 2));
+        assert!(is_match_until("(a|ab)", "ab", 0, 1));
+        assert!(is_match_until("a+", "xaaa", 1, 3));
+        assert!(!is_match_until("a+b", "xaaab", 1, 4));
+        assert!(is_match_until("(\\w)\\1+", "aaa", 0, 3));
+        assert!(is_match_until("\\Ga", "ab", 0, 1));
+        assert!(!is_match_until("\\Gb", "ab", 1, 2));
+    }
+
+    /// Parses a pattern consisting of a single item.
+    fn parse_item(pattern: &str) -> Syntax {
+        let mut syntax = syntax::parse_pattern(&tokens::tokenize_pattern(pattern));
+        assert_eq!(1, syntax.len(), "{:?} is not a single item", pattern);
+        syntax.remove(0)
+    }
+
+    /// Returns true if the single char pattern matches the char.
+    fn matches_single(char: char, pattern: &str) -> bool {
+        let item = parse_item(pattern);
+        let (single, is_case_insensitive) = SingleCharSyntax::of(&item).unwrap();
+        matches_char(char, single, is_case_insensitive)
+    }
+
+    #[test]
+    fn test_single_char_syntax_of() {
+        assert_eq!(
+            Some((SingleCharSyntax::Literal('a'), false)),
+            SingleCharSyntax::of(&parse_item("a"))
+        );
+        assert_eq!(
+            Some((SingleCharSyntax::Digit, false)),
+            SingleCharSyntax::of(&parse_item("\\d"))
+        );
+        assert_eq!(
+            Some((SingleCharSyntax::Word, false)),
+            SingleCharSyntax::of(&parse_item("\\w"))
+        );
+        assert_eq!(
+            Some((SingleCharSyntax::Wildcard, false)),
+            SingleCharSyntax::of(&parse_item("."))
+        );
+        assert!(matches!(
+            SingleCharSyntax::of(&parse_item("[abc]")),
+            Some((SingleCharSyntax::Class(_), false))
+        ));
+
+        let case_insensitive = Syntax::CaseInsensitive {
+            syntax: Box::new(Syntax::Literal { char: 'a' }),
+        };
+        assert_eq!(
+            Some((SingleCharSyntax::Literal('a'), true)),
+            SingleCharSyntax::of(&case_insensitive)
+        );
+    }
+
+    #[test]
+    fn test_single_char_syntax_of_other_syntax() {
+        for pattern in ["^", "$", "a+", "a*", "a?", "a{2}", "(a)", "(?>a)", "(?=a)", "(?<=a)"] {
+            assert_eq!(None, SingleCharSyntax::of(&parse_item(pattern)), "{:?}", pattern);
+        }
+
+        let run = Syntax::LiteralRun {
+            text: "ab".to_string(),
+        };
+        assert_eq!(None, SingleCharSyntax::of(&run));
+    }
+
+    #[test]
+    fn test_matches_char() {
+        assert!(matches_single('a', "a"));
+        assert!(!matches_single('b', "a"));
+        assert!(matches_single('7', "\\d"));
+        assert!(!matches_single('x', "\\d"));
+        assert!(matches_single('_', "\\w"));
+        assert!(!matches_single('-', "\\w"));
+        assert!(matches_single('x', "."));
+        assert!(!matches_single('\n', "."));
+        assert!(matches_single('b', "[a-c]"));
+        assert!(!matches_single('b', "[^a-c]"));
+        assert!(matches_single('d', "[^a-c]"));
+    }
+
+    #[test]
+    fn test_matches_char_case_insensitive() {
+        let matches = |char, single| matches_char(char, single, true);
+
+        assert!(matches('A', SingleCharSyntax::Literal('a')));
+        assert!(matches('a', SingleCharSyntax::Literal('A')));
+        assert!(!matches('b', SingleCharSyntax::Literal('a')));
+
+        let negated = parse_item("[^a]");
+        let Syntax::CharacterClass { set } = &negated else {
+            panic!("Character class expected, but found {:?}", negated);
+        };
+        assert!(!matches('A', SingleCharSyntax::Class(set)));
+        assert!(matches('b', SingleCharSyntax::Class(set)));
+    }
+}
@@ -0,0 +1,337 @@
+use super::anchors;
+use super::error::ParseError;
+use super::syntax::Syntax;
+use super::matcher::{self, SingleCharSyntax};
+
+/// A single instruction of an [`Nfa`] program.
+#[derive(Clone, Debug)]
+enum Inst {
+    /// Consumes a single char matched by the single char syntax item.
+    Char(Syntax),
+
+    /// Continues at both instructions.
+    Split(usize, usize),
+
+    /// Continues at the instruction.
+    Jump(usize),
+
+    /// Continues with the next instruction if the anchor matches at the current position.
+    Assert(Syntax),
+
+    /// The whole pattern matched.
+    Match,
+}
+
+/// A pattern compiled into a nondeterministic finite automaton, which is executed by
+/// following all paths through the pattern at once instead of backtracking. Matching
+/// takes time linear in the length of the text, but backreferences, atomic groups and
+/// lookarounds cannot be expressed.
+#[derive(Clone, Debug)]
+pub struct Nfa {
+    program: Vec<Inst>,
+}
+
+impl Nfa {
+    /// Compiles the syntax items, failing for items the automaton cannot express.
+    pub fn compile(syntax: &[Syntax]) -> Result<Nfa, ParseError> {
+        let mut program = vec![];
+        compile_sequence(syntax, &mut program)?;
+        program.push(Inst::Match);
+
+        Ok(Nfa { program })
+    }
+
+    /// Returns true if the pattern matches anywhere within the text, or only at its
+    /// start if anchored.
+    pub fn is_match(&self, text: &str, is_anchored: bool) -> bool {
+        let mut current = Threads::new(self.program.len());
+        let mut next = Threads::new(self.program.len());
+
+        // Every char boundary is a position, including the very end of the text.
+        let positions = text
+            .char_indices()
+            .map(|(pos, char)| (pos, Some(char)))
+            .chain(std::iter::once((text.len(), None)));
+
+        for (pos, char) in positions {
+            if (pos == 0 || !is_anchored) && self.add_thread(&mut current, 0, text, pos) {
+                return true;
+            }
+
+            let Some(char) = char else {
+                break;
+            };
+
+            next.clear();
+            for pc in current.pcs.clone() {
+                let Inst::Char(item) = &self.program[pc] else {
+                    continue;
+                };
+
+                let is_char_match = SingleCharSyntax::of(item)
+                    .is_some_and(|(single, ci)| matcher::matches_char(char, single, ci));
+
+                if is_char_match
+                    && self.add_thread(&mut next, pc + 1, text, pos + char.len_utf8())
+                {
+                    return true;
+                }
+            }
+
+            std::mem::swap(&mut current, &mut next);
+
+            // No thread is started after the first position, so once all threads
+            // died the rest of the text does not need to be read.
+            if is_anchored && current.pcs.is_empty() {
+                break;
+            }
+        }
+
+        false
+    }
+
+    /// Adds the thread at `pc` and all threads reachable from it without consuming a
+    /// char at the byte offset `pos`. Returns true if any of them reached the end of
+    /// the pattern.
+    fn add_thread(&self, threads: &mut Threads, pc: usize, text: &str, pos: usize) -> bool {
+        if !threads.insert(pc) {
+            return false;
+        }
+
+        match &self.program[pc] {
+            Inst::Char(_) => false,
+            Inst::Split(first, second) => {
+                self.add_thread(threads, *first, text, pos)
+                    || self.add_thread(threads, *second, text, pos)
+            }
+            Inst::Jump(target) => self.add_thread(threads, *target, text, pos),
+            Inst::Assert(anchor) => {
+                matches_anchor(anchor, text, pos) && self.add_thread(threads, pc + 1, text, pos)
+            }
+            Inst::Match => true,
+        }
+    }
+}
+
+/// The instructions active at a position, in the order they were added.
+struct Threads {
+    pcs: Vec<usize>,
+    is_active: Vec<bool>,
+}
+
+impl Threads {
+    fn new(len: usize) -> Threads {
+        Threads {
+            pcs: Vec::with_capacity(len),
+            is_active: vec![false; len],
+        }
+    }
+
+    /// Adds the instruction, returning false if it was already active.
+    fn insert(&mut self, pc: usize) -> bool {
+        if self.is_active[pc] {
+            return false;
+        }
+
+        self.is_active[pc] = true;
+        self.pcs.push(pc);
+        true
+    }
+
+    fn clear(&mut self) {
+        for pc in self.pcs.drain(..) {
+            self.is_active[pc] = false;
+        }
+    }
+}
+
+fn matches_anchor(anchor: &Syntax, text: &str, pos: usize) -> bool {
+    match anchor {
+        Syntax::StartOfLineAnchor => anchors::matches_start(pos),
+        Syntax::EndOfLineAnchor => anchors::matches_end(pos, text.len()),
+        Syntax::StartOfAnyLineAnchor => anchors::matches_line_start(text, pos),
+        Syntax::EndOfAnyLineAnchor => anchors::matches_line_end(text, pos),
+        // The search always starts at the start of the text.
+        Syntax::PreviousMatchEnd => pos == 0,
+        other => panic!("Anchor expected, but found {:?}", other),
+    }
+}
+
+fn compile_sequence(syntax: &[Syntax], program: &mut Vec<Inst>) -> Result<(), ParseError> {
+    syntax.iter().try_for_each(|item| compile_item(item, program))
+}
+
+fn compile_item(item: &Syntax, program: &mut Vec<Inst>) -> Result<(), ParseError> {
+    match item {
+        Syntax::Literal { .. }
+        | Syntax::Digit
+        | Syntax::Word
+        | Syntax::Wildcard
+        | Syntax::CharacterClass { .. }
+        | Syntax::CaseInsensitive { .. } => program.push(Inst::Char(item.clone())),
+        Syntax::LiteralRun { text } => {
+            program.extend(text.chars().map(|char| Inst::Char(Syntax::Literal { char })));
+        }
+        Syntax::StartOfLineAnchor
+        | Syntax::EndOfLineAnchor
+        | Syntax::StartOfAnyLineAnchor
+        | Syntax::EndOfAnyLineAnchor
+        | Syntax::PreviousMatchEnd => program.push(Inst::Assert(item.clone())),
+        Syntax::OneOrMore { syntax } => {
+            let start = program.len();
+            compile_item(syntax, program)?;
+            program.push(Inst::Split(start, program.len() + 1));
+        }
+        Syntax::ZeroOrMore { syntax } => compile_star(syntax, program)?,
+        Syntax::ZeroOrOne { syntax } => compile_optional(syntax, program)?,
+        Syntax::Repeat { syntax, min, max } => {
+            for _ in 0..*min {
+                compile_item(syntax, program)?;
+            }
+
+            match max {
+                Some(max) => {
+                    for _ in *min..*max {
+                        compile_optional(syntax, program)?;
+                    }
+                }
+                None => compile_star(syntax, program)?,
+            }
+        }
+        // Capture groups only matter for their options, no spans are reported.
+        Syntax::CaptureGroup { options, .. } => compile_alternation(options, program)?,
+        Syntax::BackReference { .. } => {
+            return Err(ParseError::UnsupportedByEngine {
+                feature: "backreferences",
+            })
+        }
+        Syntax::AtomicGroup { .. } => {
+            return Err(ParseError::UnsupportedByEngine {
+                feature: "atomic groups",
+            })
+        }
+        Syntax::Lookahead { .. } | Syntax::Lookbehind { .. } => {
+            return Err(ParseError::UnsupportedByEngine {
+                feature: "lookarounds",
+            })
+        }
+    }
+
+    Ok(())
+}
+
+fn compile_star(item: &Syntax, program: &mut Vec<Inst>) -> Result<(), ParseError> {
+    let split = program.len();
+    program.push(Inst::Split(split + 1, 0));
+    compile_item(item, program)?;
+    program.push(Inst::Jump(split));
+    program[split] = Inst::Split(split + 1, program.len());
+
+    Ok(())
+}
+
+fn compile_optional(item: &Syntax, program: &mut Vec<Inst>) -> Result<(), ParseError> {
+    let split = program.len();
+    program.push(Inst::Split(split + 1, 0));
+    compile_item(item, program)?;
+    program[split] = Inst::Split(split + 1, program.len());
+
+    Ok(())
+}
+
+fn compile_alternation(options: &[Vec<Syntax>], program: &mut Vec<Inst>) -> Result<(), ParseError> {
+    let Some((last, others)) = options.split_last() else {
+        return Ok(());
+    };
+
+    let mut jumps = vec![];
+    for option in others {
+        let split = program.len();
+        program.push(Inst::Split(split + 1, 0));
+        compile_sequence(option, program)?;
+
+        jumps.push(program.len());
+        program.push(Inst::Jump(0));
+        program[split] = Inst::Split(split + 1, program.len());
+    }
+
+    compile_sequence(last, program)?;
+
+    let end = program.len();
+    for jump in jumps {
+        program[jump] = Inst::Jump(end);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grep::syntax::parse_pattern;
+    use crate::grep::tokens::tokenize_pattern;
+
+    fn is_match(text: &str, pattern: &str) -> bool {
+        Nfa::compile(&parse_pattern(&tokenize_pattern(pattern)))
+            .unwrap()
+            .is_match(text, false)
+    }
+
+    #[test]
+    fn test_nfa_is_match() {
+        assert!(is_match("pineapple", "apple"));
+        assert!(is_match("sally has 1 apple", "\\d apple"));
+        assert!(is_match("a dog", "a (cat|dog)"));
+        assert!(is_match("cat and fish", "((c.t|d.g) and (f..h|b..d))"));
+        assert!(!is_match("cab", "[^abc]"));
+        assert!(is_match("cts", "ca*ts"));
+        assert!(!is_match("slog", "^log"));
+        assert!(is_match("hotdog", "dog$"));
+        assert!(is_match("", ""));
+        assert!(!is_match("", "a"));
+    }
+
+    #[test]
+    fn test_nfa_is_match_repetition() {
+        assert!(is_match("xaaay", "xa{2,3}y"));
+        assert!(!is_match("xaaaay", "xa{2,3}y"));
+        assert!(!is_match("xay", "xa{2,}y"));
+        assert!(is_match("xaaaaay", "xa{2,}y"));
+        assert!(is_match("xy", "xa{,2}y"));
+    }
+
+    #[test]
+    fn test_nfa_is_match_quantified_groups() {
+        assert!(is_match("ababc", "^(ab)+c"));
+        assert!(is_match("c", "^(ab)*c"));
+        assert!(is_match("b", "^(a|)*b"));
+        assert!(!is_match("abac", "^(ab)+c"));
+    }
+
+    #[test]
+    fn test_nfa_is_match_pathological() {
+        let pattern = format!("{}{}", "a?".repeat(30), "a".repeat(30));
+
+        assert!(is_match(&"a".repeat(30), &pattern));
+        assert!(!is_match(&"a".repeat(29), &pattern));
+    }
+
+    #[test]
+    fn test_nfa_is_match_anchored() {
+        let nfa = Nfa::compile(&parse_pattern(&tokenize_pattern("b"))).unwrap();
+
+        assert!(nfa.is_match("b", true));
+        assert!(!nfa.is_match("ab", true));
+    }
+
+    #[test]
+    fn test_nfa_compile_unsupported() {
+        let compile = |pattern: &str| Nfa::compile(&parse_pattern(&tokenize_pattern(pattern))).err();
+        let unsupported = |feature| Some(ParseError::UnsupportedByEngine { feature });
+
+        assert_eq!(unsupported("backreferences"), compile("(a)\\1"));
+        assert_eq!(unsupported("atomic groups"), compile("(?>a)"));
+        assert_eq!(unsupported("lookarounds"), compile("a(?=b)"));
+        assert_eq!(unsupported("lookarounds"), compile("(?<=a)b"));
+    }
+}
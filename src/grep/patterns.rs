@@ -1,4 +1,4 @@
-fn is_in_range(lower_inclusive: char, upper_inclusive: char, char: char) -> bool {
+pub fn is_in_range(lower_inclusive: char, upper_inclusive: char, char: char) -> bool {
     lower_inclusive <= char && char <= upper_inclusive
 }
 
@@ -18,8 +18,39 @@ pub fn is_word(char: char) -> bool {
     is_digit(char) || is_lower_case_letter(char) || is_upper_case_letter(char) || char == '_'
 }
 
-pub fn is_any_of(search_values: &[char], char: char) -> bool {
-    search_values.contains(&char)
+pub fn is_whitespace(char: char) -> bool {
+    matches!(char, ' ' | '\t' | '\n' | '\r' | '\x0B' | '\x0C')
+}
+
+/// Returns true for Unicode numeric chars, of which ASCII digits are a subset.
+pub fn is_unicode_digit(char: char) -> bool {
+    char.is_numeric()
+}
+
+/// Returns true for Unicode letters and numeric chars, and for `_`.
+pub fn is_unicode_word(char: char) -> bool {
+    char.is_alphanumeric() || char == '_'
+}
+
+/// Returns true for Unicode whitespace, e.g. including the no-break space.
+pub fn is_unicode_whitespace(char: char) -> bool {
+    char.is_whitespace()
+}
+
+/// Returns the lower and upper case variants of the char, leaving out case mappings
+/// that result in multiple chars (e.g. 'ß' to "SS").
+pub fn case_variants(char: char) -> Vec<char> {
+    let mut variants = vec![];
+
+    for mapping in [char.to_lowercase().collect::<Vec<_>>(), char.to_uppercase().collect()] {
+        if let [variant] = mapping[..] {
+            if variant != char && !variants.contains(&variant) {
+                variants.push(variant);
+            }
+        }
+    }
+
+    variants
 }
 
 #[cfg(test)]
@@ -121,16 +152,57 @@ mod tests {
     }
 
     #[test]
-    fn test_is_any_of() {
-        assert!(is_any_of(&['a'], 'a'));
-        assert!(is_any_of(&['a', 'z'], 'a'));
-        assert!(is_any_of(&['a', 'z'], 'z'));
+    fn test_is_whitespace() {
+        assert!(is_whitespace(' '));
+        assert!(is_whitespace('\t'));
+        assert!(is_whitespace('\n'));
+        assert!(is_whitespace('\r'));
+    }
+
+    #[test]
+    fn test_is_whitespace_no_whitespace() {
+        assert!(!is_whitespace('a'));
+        assert!(!is_whitespace('_'));
+        assert!(!is_whitespace('7'));
+    }
+
+    #[test]
+    fn test_is_in_range() {
+        assert!(is_in_range('a', 'f', 'a'));
+        assert!(is_in_range('a', 'f', 'c'));
+        assert!(is_in_range('a', 'f', 'f'));
+        assert!(!is_in_range('a', 'f', 'g'));
+        assert!(!is_in_range('a', 'f', 'A'));
+    }
+
+    #[test]
+    fn test_case_variants() {
+        assert_eq!(vec!['A'], case_variants('a'));
+        assert_eq!(vec!['q'], case_variants('Q'));
+        assert_eq!(vec!['Ü'], case_variants('ü'));
+        assert!(case_variants('7').is_empty());
+        assert!(case_variants('ß').is_empty());
+    }
+
+    #[test]
+    fn test_is_unicode_digit() {
+        assert!(is_unicode_digit('7'));
+        assert!(is_unicode_digit('٣'));
+        assert!(!is_unicode_digit('a'));
+    }
+
+    #[test]
+    fn test_is_unicode_word() {
+        assert!(is_unicode_word('é'));
+        assert!(is_unicode_word('_'));
+        assert!(is_unicode_word('ж'));
+        assert!(!is_unicode_word('-'));
     }
 
     #[test]
-    fn test_is_any_of_no_match() {
-        assert!(!is_any_of(&[], 'a'));
-        assert!(!is_any_of(&['b'], 'a'));
-        assert!(!is_any_of(&['b', '_'], 'a'));
+    fn test_is_unicode_whitespace() {
+        assert!(is_unicode_whitespace('\u{A0}'));
+        assert!(is_unicode_whitespace(' '));
+        assert!(!is_unicode_whitespace('x'));
     }
 }
@@ -0,0 +1,2047 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::iter;
+use std::str;
+
+use super::error::ParseError;
+use super::flags::Flags;
+use super::literals::{LiteralSet, MatchKind};
+use super::nfa::Nfa;
+use super::syntax::{self, Syntax};
+use super::tokens::{self, Spanned, Token};
+use super::anchors;
+use super::matcher::{self, MatchResult};
+
+/// Byte offsets of a match within the searched text.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    /// Byte offset of the first matched character.
+    pub start: usize,
+
+    /// Byte offset directly after the last matched character.
+    pub end: usize,
+}
+
+impl Span {
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Returns the matched part of the text the span was found in.
+    pub fn as_str<'t>(&self, text: &'t str) -> &'t str {
+        &text[self.start..self.end]
+    }
+}
+
+/// Limits on the complexity of a pattern, guarding against pathological patterns,
+/// see [`Regex::compile_with_limits`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limits {
+    /// Maximum number of capture groups.
+    pub max_groups: usize,
+
+    /// Maximum nesting depth of groups.
+    pub max_depth: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            max_groups: 256,
+            max_depth: 32,
+        }
+    }
+}
+
+/// The way a [`Regex`] is executed, see [`Regex::with_engine`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Engine {
+    /// Tries the options of the pattern one after another, which supports all syntax
+    /// but takes exponential time for some patterns.
+    #[default]
+    Backtrack,
+
+    /// Follows all options of the pattern at once, which takes linear time but does not
+    /// support backreferences, atomic groups and lookarounds.
+    Nfa,
+}
+
+/// A compiled pattern that can be matched against text repeatedly.
+#[derive(Clone, Debug)]
+pub struct Regex {
+    syntax: Vec<Syntax>,
+
+    /// The flags the syntax was compiled with, already applied to it.
+    flags: Flags,
+
+    /// The pattern contains backreferences. Without them, capture groups are not
+    /// recorded when only checking for a match, see [`Regex::is_match`].
+    has_back_references: bool,
+
+    /// Number of capture groups, counted once so searches do not collect their names.
+    captures_len: usize,
+
+    /// Only match at the start of the text, as if the pattern started with `^`.
+    is_anchored: bool,
+
+    /// Minimum number of chars of any match.
+    min_len: usize,
+
+    /// Maximum number of chars of any match, `None` if unlimited or unknown.
+    max_len: Option<usize>,
+
+    /// The automaton executing `is_match`, if the NFA engine is selected.
+    nfa: Option<Nfa>,
+
+    /// The automaton searching for the leftmost match, if the pattern only consists
+    /// of literals, see [`LiteralSearch`].
+    literals: Option<LiteralSearch>,
+}
+
+/// A set of literals searched all at once instead of trying each of them at every
+/// position. Used for a set of fixed strings (see [`Regex::compile_fixed_set`]) and
+/// for a pattern consisting of a single group with only literal options, like `(cat|dog)`.
+#[derive(Clone, Debug)]
+struct LiteralSearch {
+    set: LiteralSet,
+
+    /// The capture group around the literals, which spans the whole match.
+    group: Option<u32>,
+}
+
+impl LiteralSearch {
+    /// Returns the search for a pattern consisting of a single group with only literal
+    /// options, or `None` for any other pattern.
+    fn of_alternation(syntax: &[Syntax]) -> Option<LiteralSearch> {
+        let [Syntax::CaptureGroup { options, id, .. }] = syntax else {
+            return None;
+        };
+
+        let literals = options
+            .iter()
+            .map(|option| literal_text(option))
+            .collect::<Option<Vec<String>>>()?;
+        let literals: Vec<&str> = literals.iter().map(String::as_str).collect();
+
+        Some(LiteralSearch {
+            set: LiteralSet::new(&literals, MatchKind::LeftmostFirst),
+            group: Some(*id),
+        })
+    }
+}
+
+/// Returns the text matched by the syntax items if they are all literals.
+fn literal_text(syntax: &[Syntax]) -> Option<String> {
+    syntax
+        .iter()
+        .map(|item| match item {
+            Syntax::Literal { char } => Some(char.to_string()),
+            Syntax::LiteralRun { text } => Some(text.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+impl Regex {
+    pub fn compile(pattern: &str) -> Regex {
+        Regex::from_tokens(&tokens::tokenize_pattern_spanned(pattern))
+    }
+
+    /// Compiles the pattern in basic syntax (BRE), in which `(`, `)`, `{`, `}`, `+`, `?`
+    /// and `|` match themselves unless escaped, e.g. `\(a\+\)` is `(a+)` in extended syntax.
+    pub fn compile_basic(pattern: &str) -> Regex {
+        Regex::from_tokens(&tokens::tokenize_pattern_basic(pattern))
+    }
+
+    /// Compiles the pattern so that it only matches at the start of the text,
+    /// which is useful for tokenizing input from left to right.
+    pub fn compile_anchored(pattern: &str) -> Regex {
+        Regex {
+            is_anchored: true,
+            literals: None,
+            ..Regex::compile(pattern)
+        }
+    }
+
+    /// Compiles the pattern, failing if it exceeds any of the limits.
+    pub fn compile_with_limits(pattern: &str, limits: Limits) -> Result<Regex, ParseError> {
+        Regex::from_tokens_with_limits(&tokens::tokenize_pattern_spanned(pattern), limits)
+    }
+
+    /// Compiles the pattern in basic syntax like [`Regex::compile_basic`], failing if it
+    /// exceeds any of the limits.
+    pub fn compile_basic_with_limits(pattern: &str, limits: Limits) -> Result<Regex, ParseError> {
+        Regex::from_tokens_with_limits(&tokens::tokenize_pattern_basic(pattern), limits)
+    }
+
+    /// Compiles the pattern as a fixed string, in which every character
+    /// (including regex metacharacters) only matches itself.
+    pub fn compile_fixed(pattern: &str) -> Regex {
+        Regex::from_syntax(pattern.chars().map(|char| Syntax::Literal { char }).collect())
+    }
+
+    /// Compiles the patterns as fixed strings, any of which may match. Like GNU grep,
+    /// the leftmost match is found and among the strings matching there the longest.
+    /// The set is searched all at once, which stays fast for many strings.
+    pub fn compile_fixed_set(patterns: &[&str]) -> Regex {
+        // Trying the longest strings first makes the first option that matches
+        // the longest one, so the group never needs to try another option.
+        let mut options: Vec<Vec<Syntax>> = patterns
+            .iter()
+            .map(|pattern| pattern.chars().map(|char| Syntax::Literal { char }).collect())
+            .collect();
+        options.sort_by_key(|option| std::cmp::Reverse(option.len()));
+
+        Regex {
+            literals: Some(LiteralSearch {
+                set: LiteralSet::new(patterns, MatchKind::LeftmostLongest),
+                group: None,
+            }),
+            ..Regex::from_syntax(vec![Syntax::AtomicGroup { options }])
+        }
+    }
+
+    /// Returns the regex changed to match letters regardless of their case.
+    /// Backreferences still require the exact text captured by their group.
+    /// Literals are backtracked through afterwards, their automaton only matches
+    /// the exact strings.
+    pub fn ignore_case(self) -> Regex {
+        let syntax = syntax::ignore_case(self.syntax);
+        let nfa = self.nfa.map(|_| {
+            Nfa::compile(&syntax).expect("Ignoring case does not add unsupported syntax")
+        });
+
+        Regex {
+            syntax,
+            flags: Flags {
+                case_insensitive: true,
+                ..self.flags
+            },
+            nfa,
+            literals: None,
+            ..self
+        }
+    }
+
+    /// Returns the regex executed by the engine, failing if the engine does not support
+    /// the pattern. Only [`Regex::is_match`] is executed by the NFA engine, all other
+    /// methods always backtrack. Literals are always searched by their automaton
+    /// instead, so the engine does not change them.
+    pub fn with_engine(self, engine: Engine) -> Result<Regex, ParseError> {
+        if self.literals.is_some() {
+            return Ok(self);
+        }
+
+        let nfa = match engine {
+            Engine::Backtrack => None,
+            Engine::Nfa => Some(Nfa::compile(&self.syntax)?),
+        };
+
+        Ok(Regex { nfa, ..self })
+    }
+
+    /// Returns the flags the pattern was compiled with.
+    pub fn flags(&self) -> Flags {
+        self.flags
+    }
+
+    /// Returns the number of capture groups in the pattern.
+    pub fn captures_len(&self) -> usize {
+        self.captures_len
+    }
+
+    /// Returns the names of all capture groups in the order of their ids,
+    /// with `None` for unnamed groups.
+    pub fn capture_names(&self) -> Vec<Option<String>> {
+        syntax::capture_names(&self.syntax)
+    }
+
+    /// Renders the parsed pattern as an indented tree, which helps to understand
+    /// how a pattern was interpreted. Groups list their options and quantifiers
+    /// nest the quantified item.
+    pub fn debug_tree(&self) -> String {
+        syntax::debug_tree(&self.syntax)
+    }
+
+    /// Returns true if the pattern matches anywhere within the text.
+    /// Capture groups are only recorded if backreferences need them, so a pattern
+    /// without backreferences is matched without allocating.
+    pub fn is_match(&self, text: &str) -> bool {
+        if let Some(literals) = &self.literals {
+            return literals.set.find_at(text, 0).is_some();
+        }
+
+        match &self.nfa {
+            Some(nfa) => nfa.is_match(text, self.is_start_anchored()),
+            None if self.has_back_references => self.find(text).is_some(),
+            None => {
+                let pattern = self.unanchored_pattern();
+                self.candidates(text, 0)
+                    .any(|candidate| matcher::is_match_at(pattern, text, candidate, 0))
+            }
+        }
+    }
+
+    /// Returns true if the pattern matches the whole text, from its start to its end.
+    pub fn is_full_match(&self, text: &str) -> bool {
+        matcher::is_match_until(&self.syntax, text, 0, text.len())
+    }
+
+    /// Returns true if the pattern matches anywhere within the bytes, which do not
+    /// need to be valid UTF-8, see [`decode_bytes`]. Valid UTF-8 is matched char by
+    /// char like any text, so `é` matches its own encoding. Each invalid byte is matched
+    /// as the char with the same value, so `.` matches it as well.
+    pub fn is_match_bytes(&self, bytes: &[u8]) -> bool {
+        self.is_match(&decode_bytes(bytes))
+    }
+
+    /// Returns the leftmost match within the text.
+    pub fn find(&self, text: &str) -> Option<Span> {
+        self.find_at(text, 0)
+    }
+
+    /// Returns an iterator over all non-overlapping matches within the text.
+    pub fn find_iter<'r, 't>(&'r self, text: &'t str) -> FindIter<'r, 't> {
+        FindIter {
+            regex: self,
+            text,
+            next_start: 0,
+            last_end: None,
+            is_overlapping: false,
+        }
+    }
+
+    /// Returns an iterator over all matches within the text, including overlapping ones.
+    /// After each match the search continues one char after the start of that match,
+    /// so at most one match is reported per start position.
+    pub fn find_iter_overlapping<'r, 't>(&'r self, text: &'t str) -> FindIter<'r, 't> {
+        FindIter {
+            is_overlapping: true,
+            ..self.find_iter(text)
+        }
+    }
+
+    /// Splits the text at each non-overlapping match of the pattern.
+    pub fn split(&self, text: &str) -> Vec<String> {
+        let mut parts = vec![];
+        let mut part_start = 0;
+
+        for span in self.find_iter(text) {
+            parts.push(text[part_start..span.start].to_string());
+            part_start = span.end;
+        }
+
+        parts.push(text[part_start..].to_string());
+        parts
+    }
+
+    /// Returns the byte offset at which the shortest match ends, among the matches
+    /// starting at the leftmost position where the pattern matches at all.
+    pub fn shortest_match(&self, text: &str) -> Option<usize> {
+        let span = self.find(text)?;
+        if let Some(literals) = &self.literals {
+            return literals.set.shortest_at(text, span.start);
+        }
+
+        let pattern = self.unanchored_pattern();
+
+        // The greedy match found above is the longest candidate, so there is always a result.
+        let mut ends = text[span.start..span.end]
+            .char_indices()
+            .map(|(index, _)| span.start + index)
+            .chain(iter::once(span.end));
+
+        ends.find(|end| matcher::is_match_until(pattern, text, span.start, *end))
+    }
+
+    /// Replaces the first `n` non-overlapping matches within the text
+    /// with the replacement, leaving any further matches unchanged.
+    pub fn replacen(&self, text: &str, n: usize, replacement: &str) -> String {
+        let mut replaced = String::with_capacity(text.len());
+        let mut last_end = 0;
+
+        for span in self.find_iter(text).take(n) {
+            replaced.push_str(&text[last_end..span.start]);
+            replaced.push_str(replacement);
+            last_end = span.end;
+        }
+
+        replaced.push_str(&text[last_end..]);
+        replaced
+    }
+
+    /// Replaces all non-overlapping matches within the text with the result of the
+    /// replacer, which is called with the captures of each match.
+    pub fn replace_all_with(&self, text: &str, mut replacer: impl FnMut(&Captures) -> String) -> String {
+        let mut replaced = String::with_capacity(text.len());
+        let mut last_end = 0;
+
+        for captures in self.captures_iter(text) {
+            let span = captures.get(0).expect("Overall match is always present");
+
+            replaced.push_str(&text[last_end..span.start]);
+            replaced.push_str(&replacer(&captures));
+            last_end = span.end;
+        }
+
+        replaced.push_str(&text[last_end..]);
+        replaced
+    }
+
+    /// Returns every distinct text matched by the capture group across all matches
+    /// within the text, see [`Captures::get_text`]. Group 0 is the overall match.
+    pub fn distinct_group_values(&self, text: &str, group: usize) -> HashSet<String> {
+        self.captures_iter(text)
+            .filter_map(|captures| captures.get_text(group).map(String::from))
+            .collect()
+    }
+
+    /// Returns the leftmost match within the text together with the
+    /// spans of all capture groups.
+    pub fn captures<'t>(&self, text: &'t str) -> Option<Captures<'t>> {
+        self.captures_at(text, 0)
+    }
+
+    /// Returns the leftmost match starting at or after the byte offset `start` together
+    /// with the spans of all capture groups, see [`Regex::captures`]. The spans are
+    /// offsets into the whole text.
+    pub fn captures_at<'t>(&self, text: &'t str, start: usize) -> Option<Captures<'t>> {
+        let result = self.search_at(text, start)?;
+        Some(Regex::to_captures(text, result))
+    }
+
+    /// Returns an iterator over all non-overlapping matches within the text, each
+    /// together with the spans of its capture groups, see [`Regex::captures`].
+    pub fn captures_iter<'r, 't>(&'r self, text: &'t str) -> CapturesIter<'r, 't> {
+        CapturesIter {
+            matches: self.find_iter(text),
+        }
+    }
+
+    /// Collects the spans of the match and of all capture groups.
+    fn to_captures(text: &str, result: MatchResult) -> Captures<'_> {
+        Captures {
+            text,
+            spans: result.groups,
+        }
+    }
+
+
+    /// Parses the tokens, panicking on malformed patterns like the parser itself does.
+    fn from_tokens(pattern: &[Spanned<Token>]) -> Regex {
+        let (tokens, indices) = tokens::split_indices(pattern);
+        if let Err(error) = syntax::check_supported(&tokens, &indices) {
+            panic!("{}", error);
+        }
+
+        let syntax = syntax::parse_tokens(&tokens, &indices).unwrap_or_else(|error| {
+            panic!("{}", error);
+        });
+        if let Err(error) = syntax::check_back_references(&syntax) {
+            panic!("{}", error);
+        }
+
+        Regex::from_syntax(syntax)
+    }
+
+    /// Parses the tokens, failing if they exceed any of the limits.
+    fn from_tokens_with_limits(
+        pattern: &[Spanned<Token>],
+        limits: Limits,
+    ) -> Result<Regex, ParseError> {
+        let (tokens, indices) = tokens::split_indices(pattern);
+
+        // The depth is checked first, parsing recurses into nested groups.
+        let depth = syntax::group_depth(&tokens);
+        if depth > limits.max_depth {
+            return Err(ParseError::TooDeeplyNested {
+                depth,
+                limit: limits.max_depth,
+            });
+        }
+
+        syntax::check_supported(&tokens, &indices)?;
+
+        let syntax = syntax::parse_tokens(&tokens, &indices)?;
+        syntax::check_back_references(&syntax)?;
+
+        let regex = Regex::from_syntax(syntax);
+
+        let count = regex.captures_len();
+        if count > limits.max_groups {
+            return Err(ParseError::TooManyGroups {
+                count,
+                limit: limits.max_groups,
+            });
+        }
+
+        Ok(regex)
+    }
+
+    fn from_syntax(syntax: Vec<Syntax>) -> Regex {
+        let syntax = syntax::merge_literals(syntax);
+
+        Regex {
+            min_len: syntax::min_len(&syntax),
+            max_len: syntax::max_len(&syntax),
+            has_back_references: syntax::has_back_references(&syntax),
+            captures_len: syntax::capture_names(&syntax).len(),
+            literals: LiteralSearch::of_alternation(&syntax),
+            syntax,
+            flags: Flags::default(),
+            is_anchored: false,
+            nfa: None,
+        }
+    }
+
+    /// Returns the byte offsets at or after `start` at which a match may start. Offsets
+    /// with fewer chars remaining than the minimum match length are left out, as are
+    /// offsets too far from the end for a pattern that ends with `$`. An anchored pattern
+    /// has at most the start of the text as candidate, so a search never gets quadratic.
+    fn candidates<'t>(&self, text: &'t str, start: usize) -> impl Iterator<Item = usize> + 't {
+        let is_start_anchored = self.is_start_anchored();
+        let remaining_text = &text[start..];
+        let last = char_offset_from_end(remaining_text, self.min_len);
+
+        let is_end_anchored = matches!(self.syntax.last(), Some(Syntax::EndOfLineAnchor));
+        let first = match self.max_len {
+            Some(max_len) if is_end_anchored => {
+                char_offset_from_end(remaining_text, max_len).unwrap_or(0)
+            }
+            _ => 0,
+        };
+
+        // Every char boundary is a candidate, including the very end of the text
+        // where only empty matches are possible.
+        last.filter(|last| first <= *last)
+            .into_iter()
+            .flat_map(move |last| {
+                remaining_text[first..last]
+                    .char_indices()
+                    .map(move |(index, _)| start + first + index)
+                    .chain(iter::once(start + last))
+            })
+            .take_while(move |candidate| !is_start_anchored || anchors::matches_start(*candidate))
+    }
+
+    /// Returns true if matches can only start at the start of the text.
+    fn is_start_anchored(&self) -> bool {
+        self.is_anchored || matches!(self.syntax.first(), Some(Syntax::StartOfLineAnchor))
+    }
+
+    /// Returns the pattern without a leading start of line anchor, which is
+    /// handled by only searching at the start of the text instead.
+    fn unanchored_pattern(&self) -> &[Syntax] {
+        match self.syntax.first() {
+            Some(Syntax::StartOfLineAnchor) => &self.syntax[1..],
+            _ => &self.syntax,
+        }
+    }
+
+    /// Returns the leftmost match starting at or after the byte offset `start`.
+    fn find_at(&self, text: &str, start: usize) -> Option<Span> {
+        self.search_at(text, start).map(|result| result.span())
+    }
+
+    /// Returns the leftmost match starting at or after the byte offset `start`,
+    /// together with the spans of the capture groups.
+    fn search_at(&self, text: &str, start: usize) -> Option<MatchResult> {
+        if let Some(literals) = &self.literals {
+            let (start, end) = literals.set.find_at(text, start)?;
+            let mut groups = vec![None; self.captures_len + 1];
+            groups[0] = Some(Span { start, end });
+            if let Some(id) = literals.group {
+                groups[id as usize] = groups[0];
+            }
+
+            return Some(MatchResult { start, end, groups });
+        }
+
+        let pattern = self.unanchored_pattern();
+
+        self.candidates(text, start)
+            .find_map(|candidate| matcher::run(pattern, text, candidate, start, self.captures_len))
+    }
+}
+
+/// Decodes the bytes as UTF-8, where every byte not part of valid UTF-8 becomes the
+/// char with the same value, like in Latin-1. Nothing of the input is lost, and valid
+/// UTF-8 is borrowed without copying it.
+pub fn decode_bytes(bytes: &[u8]) -> Cow<'_, str> {
+    if let Ok(text) = str::from_utf8(bytes) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut text = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        text.push_str(chunk.valid());
+        text.extend(chunk.invalid().iter().map(|byte| char::from(*byte)));
+    }
+
+    Cow::Owned(text)
+}
+
+/// Returns the byte offset at which the last `count` chars of the text start,
+/// or `None` if the text is shorter.
+fn char_offset_from_end(text: &str, count: usize) -> Option<usize> {
+    if count == 0 {
+        return Some(text.len());
+    }
+
+    text.char_indices().rev().nth(count - 1).map(|(index, _)| index)
+}
+
+/// Compiles a pattern with options beyond the defaults of [`Regex::compile`].
+/// All options are disabled by default.
+#[derive(Clone, Debug)]
+pub struct RegexBuilder {
+    pattern: String,
+    limits: Limits,
+    flags: Flags,
+    ignore_whitespace: bool,
+    engine: Engine,
+}
+
+impl RegexBuilder {
+    pub fn new(pattern: &str) -> RegexBuilder {
+        RegexBuilder {
+            pattern: pattern.to_string(),
+            limits: Limits::default(),
+            flags: Flags::default(),
+            ignore_whitespace: false,
+            engine: Engine::Backtrack,
+        }
+    }
+
+    /// Limits the complexity of the pattern, see [`Regex::compile_with_limits`].
+    pub fn limits(mut self, limits: Limits) -> RegexBuilder {
+        self.limits = limits;
+        self
+    }
+
+    /// Matches letters regardless of their case, see [`Regex::ignore_case`].
+    pub fn case_insensitive(mut self, yes: bool) -> RegexBuilder {
+        self.flags.case_insensitive = yes;
+        self
+    }
+
+    /// Makes `^` and `$` match at the start and end of every line within the text.
+    pub fn multiline(mut self, yes: bool) -> RegexBuilder {
+        self.flags.multiline = yes;
+        self
+    }
+
+    /// Makes `.` match newlines as well.
+    pub fn dot_matches_newline(mut self, yes: bool) -> RegexBuilder {
+        self.flags.dot_matches_newline = yes;
+        self
+    }
+
+    /// Makes `\d`, `\w`, `\s` and their negations use Unicode instead of ASCII definitions.
+    pub fn unicode(mut self, yes: bool) -> RegexBuilder {
+        self.flags.unicode = yes;
+        self
+    }
+
+    /// Ignores whitespace and `#` comments in the pattern, like a leading `(?x)`,
+    /// see [`tokens::tokenize_pattern_free_spacing`].
+    pub fn ignore_whitespace(mut self, yes: bool) -> RegexBuilder {
+        self.ignore_whitespace = yes;
+        self
+    }
+
+    /// Executes the regex with the engine, see [`Regex::with_engine`].
+    pub fn engine(mut self, engine: Engine) -> RegexBuilder {
+        self.engine = engine;
+        self
+    }
+
+    /// Compiles the pattern with the configured options.
+    pub fn build(&self) -> Result<Regex, ParseError> {
+        let tokens = match self.ignore_whitespace {
+            true => tokens::tokenize_pattern_free_spacing(&self.pattern),
+            false => tokens::tokenize_pattern_spanned(&self.pattern),
+        };
+
+        let regex = Regex::from_tokens_with_limits(&tokens, self.limits)?;
+        let syntax = self.flags.apply(regex.syntax);
+
+        Regex {
+            literals: LiteralSearch::of_alternation(&syntax),
+            syntax,
+            flags: self.flags,
+            ..regex
+        }
+        .with_engine(self.engine)
+    }
+}
+
+/// The spans of the overall match and of all capture groups, see [`Regex::captures`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Captures<'t> {
+    text: &'t str,
+    spans: Vec<Option<Span>>,
+}
+
+impl<'t> Captures<'t> {
+    /// Returns the span of the capture group with the given id, where id 0 is the
+    /// overall match. Returns `None` if the group did not participate in the match.
+    pub fn get(&self, id: usize) -> Option<Span> {
+        self.spans.get(id).copied().flatten()
+    }
+
+    /// Returns the text matched by the capture group with the given id,
+    /// see [`Captures::get`].
+    pub fn get_text(&self, id: usize) -> Option<&'t str> {
+        self.get(id).map(|span| span.as_str(self.text))
+    }
+
+    /// Returns the number of groups, including the overall match.
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+}
+
+/// Returns the byte length of the char at the byte offset `index`,
+/// or 1 at the end of the text so that iteration steps past it.
+fn char_len_at(text: &str, index: usize) -> usize {
+    text[index..].chars().next().map_or(1, char::len_utf8)
+}
+
+/// Iterator over the matches of a [`Regex`],
+/// see [`Regex::find_iter`] and [`Regex::find_iter_overlapping`].
+pub struct FindIter<'r, 't> {
+    regex: &'r Regex,
+    text: &'t str,
+    next_start: usize,
+    last_end: Option<usize>,
+    is_overlapping: bool,
+}
+
+impl FindIter<'_, '_> {
+    /// Searches the next match, keeping the spans of its capture groups.
+    fn next_search(&mut self) -> Option<MatchResult> {
+        loop {
+            if self.next_start > self.text.len() {
+                return None;
+            }
+
+            let result = self.regex.search_at(self.text, self.next_start)?;
+            let span = result.span();
+
+            if self.is_overlapping {
+                self.next_start = span.start + char_len_at(self.text, span.start);
+                return Some(result);
+            }
+
+            if span.is_empty() {
+                // Step over the next char, otherwise the same empty match is found forever.
+                self.next_start = span.end + char_len_at(self.text, span.end);
+
+                // Empty matches directly following the previous match are not reported.
+                if self.last_end == Some(span.end) {
+                    continue;
+                }
+            } else {
+                self.next_start = span.end;
+            }
+
+            self.last_end = Some(span.end);
+            return Some(result);
+        }
+    }
+}
+
+impl Iterator for FindIter<'_, '_> {
+    type Item = Span;
+
+    fn next(&mut self) -> Option<Span> {
+        self.next_search().map(|result| result.span())
+    }
+}
+
+/// Iterator over the matches of a [`Regex`] with their capture groups,
+/// see [`Regex::captures_iter`].
+pub struct CapturesIter<'r, 't> {
+    matches: FindIter<'r, 't>,
+}
+
+impl<'t> Iterator for CapturesIter<'_, 't> {
+    type Item = Captures<'t>;
+
+    fn next(&mut self) -> Option<Captures<'t>> {
+        // Every search starts with empty capture groups, so no group of an earlier
+        // match leaks into a later one.
+        let result = self.matches.next_search()?;
+        Some(Regex::to_captures(self.matches.text, result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    fn find_all(pattern: &str, text: &str) -> Vec<(usize, usize)> {
+        Regex::compile(pattern)
+            .find_iter(text)
+            .map(|span| (span.start, span.end))
+            .collect()
+    }
+
+    #[test]
+    fn test_regex_is_match() {
+        assert!(Regex::compile("\\d+").is_match("abc123"));
+        assert!(!Regex::compile("\\d+").is_match("abc"));
+    }
+
+    #[test]
+    fn test_regex_is_match_groups() {
+        // Without backreferences, the captures of the groups are not recorded.
+        assert!(Regex::compile("(a|b)+(c)").is_match("xabc"));
+        assert!(!Regex::compile("(a|b)+(c)").is_match("xab"));
+        assert!(Regex::compile("^(\\d+)(?=px)").is_match("12px"));
+
+        assert!(Regex::compile("(a|b)\\1").is_match("xbb"));
+        assert!(!Regex::compile("(a|b)\\1").is_match("xab"));
+    }
+
+    #[test]
+    fn test_regex_is_match_bytes() {
+        let bytes: Vec<u8> = vec![b'a', 0xFF, b'b', b' ', 0xC3, b'4', b'2'];
+
+        assert!(Regex::compile("a.b").is_match_bytes(&bytes));
+        assert!(Regex::compile("\\d+$").is_match_bytes(&bytes));
+        assert!(Regex::compile("^\\w[^\\w]").is_match_bytes(&bytes));
+        assert!(!Regex::compile("a..b").is_match_bytes(&bytes));
+        assert!(!Regex::compile("c").is_match_bytes(&bytes));
+    }
+
+    #[test]
+    fn test_regex_is_match_bytes_multibyte_chars() {
+        let bytes = "aüb".as_bytes();
+
+        assert!(Regex::compile("^a.b$").is_match_bytes(bytes));
+        assert!(!Regex::compile("^a..b$").is_match_bytes(bytes));
+        assert!(Regex::compile("é").is_match_bytes("café".as_bytes()));
+        assert!(Regex::compile("^caf.$").is_match_bytes("café".as_bytes()));
+    }
+
+    #[test]
+    fn test_regex_is_match_bytes_invalid_utf8_next_to_chars() {
+        // The invalid byte 0xE9 is matched as 'é', valid chars around it as usual.
+        let bytes = [&b"caf"[..], &[0xE9], "ü!".as_bytes()].concat();
+
+        assert!(Regex::compile("café").is_match_bytes(&bytes));
+        assert!(Regex::compile("^\\w+.ü!$").is_match_bytes(&bytes));
+        assert!(!Regex::compile("ü..").is_match_bytes(&bytes));
+    }
+
+    #[test]
+    fn test_decode_bytes() {
+        assert!(matches!(decode_bytes(b"abc"), Cow::Borrowed("abc")));
+        assert_eq!("aÿb", decode_bytes(&[b'a', 0xFF, b'b']));
+        assert_eq!("éÃ4", decode_bytes(&[0xC3, 0xA9, 0xC3, b'4']));
+    }
+
+    #[test]
+    fn test_regex_ignore_case() {
+        assert!(Regex::compile("dog").ignore_case().is_match("Hot DOG"));
+        assert!(Regex::compile("^[a-f]+$").ignore_case().is_match("CafE"));
+        assert!(Regex::compile("[^x]").ignore_case().is_match("X1"));
+        assert!(!Regex::compile("^[^x]$").ignore_case().is_match("X"));
+        assert!(!Regex::compile("[a-f]").ignore_case().is_match("G"));
+        assert!(!Regex::compile("[a-f]").is_match("C"));
+        assert!(Regex::compile_fixed("A.B").ignore_case().is_match("a.b"));
+    }
+
+    #[test]
+    fn test_regex_ignore_case_ranges() {
+        let regex = Regex::compile("[a-f]").ignore_case();
+
+        assert!(regex.is_match("C"));
+        assert!(regex.is_match("c"));
+        assert!(Regex::compile("[A-F]").ignore_case().is_match("c"));
+        assert!(Regex::compile("^[Y-b]+$").ignore_case().is_match("yZAb"));
+        assert!(Regex::compile("ü").ignore_case().is_match("Ü"));
+    }
+
+    #[test]
+    fn test_regex_compile_anchored() {
+        assert!(Regex::compile_anchored("ab").is_match("abc"));
+        assert!(!Regex::compile_anchored("bc").is_match("abc"));
+        assert!(Regex::compile("bc").is_match("abc"));
+        assert!(Regex::compile_anchored("^ab").is_match("abc"));
+    }
+
+    #[test]
+    fn test_regex_is_full_match() {
+        assert!(Regex::compile("a.c").is_full_match("abc"));
+        assert!(!Regex::compile("a.c").is_full_match("abcd"));
+        assert!(!Regex::compile("a.c").is_full_match("xabc"));
+        assert!(Regex::compile("^a.c$").is_full_match("abc"));
+        assert!(Regex::compile("a*").is_full_match(""));
+    }
+
+    #[test]
+    fn test_regex_is_full_match_backtracks() {
+        assert!(Regex::compile("(a|ab)").is_full_match("ab"));
+        assert!(Regex::compile("a(b|bc)").is_full_match("abc"));
+        assert!(Regex::compile("\\w+\\d").is_full_match("abc1"));
+    }
+
+    #[test]
+    fn test_regex_compile_with_limits_invalid_back_reference() {
+        let compile = |pattern: &str| Regex::compile_with_limits(pattern, Limits::default()).err();
+
+        assert_eq!(Some(ParseError::OpenGroupReference { id: 1 }), compile("(\\1)"));
+        assert_eq!(Some(ParseError::UnknownGroupReference { id: 2 }), compile("(a)\\2"));
+        assert_eq!(None, compile("(a)\\1"));
+    }
+
+    #[test]
+    fn test_regex_compile_with_limits_perl_constructs() {
+        let compile = |pattern: &str| Regex::compile_with_limits(pattern, Limits::default());
+
+        assert!(compile("(?<word>\\w+) (?>\\d+)\\1").is_ok());
+        assert!(compile("\\Ga{2,3}[^\\s]*$").is_ok());
+        assert_eq!(
+            "Pattern uses unsupported conditional at index 3",
+            compile("(a)(?(1)b|c)").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_regex_compile_with_limits_error_index() {
+        let compile = |pattern: &str| Regex::compile_with_limits(pattern, Limits::default()).err();
+        let unclosed = |index| {
+            Some(ParseError::MissingClosingBracket {
+                construct: "alternation",
+                index,
+            })
+        };
+
+        assert_eq!(unclosed(6), compile("\\Qab\\E("));
+        assert_eq!(unclosed(12), compile("(?x)  a  b  ("));
+        assert_eq!(unclosed(4), compile("x(a)(b"));
+        assert_eq!(
+            Some(ParseError::UnrecognizedEscape {
+                escapee: 'q',
+                index: 6,
+            }),
+            compile("\\Q(\\E(\\q)")
+        );
+        assert_eq!(
+            Some(ParseError::UnsupportedFeature {
+                feature: "non-capturing group",
+                index: 11,
+            }),
+            compile("(?x) \\Qa\\E (?:b)")
+        );
+    }
+
+    #[test]
+    fn test_regex_compile_with_limits_malformed() {
+        let compile = |pattern: &str| Regex::compile_with_limits(pattern, Limits::default()).err();
+
+        assert_eq!(
+            Some(ParseError::InvalidClassRange {
+                lower: 'z',
+                upper: 'a',
+                index: 1,
+            }),
+            compile("x[z-a]")
+        );
+        assert_eq!(
+            Some(ParseError::InvalidClassIntersection { index: 0 }),
+            compile("[a&&[b]c]")
+        );
+        assert_eq!(Some(ParseError::IncompleteGroupName { index: 1 }), compile("a(?<n)"));
+        assert_eq!(
+            Some(ParseError::InvalidGroupName {
+                name: "1st".to_string(),
+                index: 0,
+            }),
+            compile("(?<1st>a)")
+        );
+        assert_eq!(Some(ParseError::IncompleteEscape { index: 1 }), compile("a\\"));
+        assert_eq!(Some(ParseError::UnexpectedToken { index: 1 }), compile("a)"));
+    }
+
+    #[test]
+    fn test_regex_compile_basic_with_limits() {
+        let compile = |pattern: &str| Regex::compile_basic_with_limits(pattern, Limits::default());
+
+        assert!(compile("\\(a\\)\\{2\\}").unwrap().is_match("aa"));
+        assert_eq!(
+            Some(ParseError::InvalidRepetition { index: 1 }),
+            compile("a\\{3,2\\}").err()
+        );
+        assert_eq!(
+            Some(ParseError::TooManyGroups { count: 2, limit: 1 }),
+            Regex::compile_basic_with_limits(
+                "\\(a\\)\\(b\\)",
+                Limits {
+                    max_groups: 1,
+                    ..Limits::default()
+                }
+            )
+            .err()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Incomplete alternation (missing closing bracket) at index 6")]
+    fn test_regex_compile_basic_error_index() {
+        Regex::compile_basic("a\\{2\\}\\(b");
+    }
+
+    #[test]
+    #[should_panic(expected = "Pattern uses unsupported non-capturing group at index 1")]
+    fn test_regex_compile_unsupported_feature() {
+        Regex::compile("a(?:b)");
+    }
+
+    #[test]
+    fn test_regex_compile_with_limits_variable_length_lookbehind() {
+        let compile = |pattern: &str| Regex::compile_with_limits(pattern, Limits::default()).err();
+        let variable = |index| Some(ParseError::VariableLengthLookbehind { index });
+
+        assert_eq!(variable(1), compile("a(?<=b+)"));
+        assert_eq!(variable(0), compile("(?<!ab?)c"));
+        assert_eq!(variable(0), compile("(?<=a|bc)d"));
+        assert_eq!(variable(3), compile("(a)(?<=\\1)"));
+        assert_eq!(variable(1), compile("((?<=x{1,2}))"));
+        assert_eq!(None, compile("(?<=[$€]\\d{2}|a.c)x"));
+        assert_eq!(
+            "Lookbehind at index 1 does not have a fixed length",
+            Regex::compile_with_limits("a(?<=b*)", Limits::default())
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_regex_lookbehind_before_search_start() {
+        let regex = Regex::compile("(?<=[$])\\d+");
+        let spans: Vec<Span> = regex.find_iter("$12 and $3, not 45").collect();
+
+        assert_eq!(vec![Span { start: 1, end: 3 }, Span { start: 9, end: 10 }], spans);
+        let captures = Regex::compile("(?<=a)b").captures_at("ab ab", 4).unwrap();
+        assert_eq!(Some(Span { start: 4, end: 5 }), captures.get(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Backreference \\2 refers to a capture group that does not exist")]
+    fn test_regex_compile_unknown_group_reference() {
+        Regex::compile("(a)\\2");
+    }
+
+    #[test]
+    #[should_panic(expected = "Backreference \\1 refers to a capture group that is not closed before it")]
+    fn test_regex_compile_open_group_reference() {
+        Regex::compile("(\\1)");
+    }
+
+    #[test]
+    fn test_regex_compile_basic() {
+        let regex = Regex::compile_basic("\\(a\\+\\)b");
+        assert_eq!(1, regex.captures_len());
+        assert_eq!(Some("aab"), regex.captures("xaab").unwrap().get_text(0));
+
+        let regex = Regex::compile_basic("(a)");
+        assert_eq!(0, regex.captures_len());
+        assert!(regex.is_match("x(a)"));
+        assert!(!regex.is_match("a"));
+    }
+
+    #[test]
+    fn test_regex_compile_basic_literal_quantifiers() {
+        assert!(Regex::compile_basic("a+?{2}|b").is_match("a+?{2}|b"));
+        assert!(!Regex::compile_basic("a+").is_match("aa"));
+        assert!(Regex::compile_basic("a\\{2\\}").is_full_match("aa"));
+        assert!(Regex::compile_basic("\\(a\\|b\\)c*").is_full_match("bcc"));
+    }
+
+    #[test]
+    fn test_regex_compile_anchored_find_iter() {
+        let regex = Regex::compile_anchored("\\d");
+
+        assert_eq!(Some(Span { start: 0, end: 1 }), regex.find("12a3"));
+        assert_eq!(1, regex.find_iter("12a3").count());
+        assert_eq!(None, regex.shortest_match("a1"));
+    }
+
+    #[test]
+    fn test_regex_compile_with_limits() {
+        let limits = Limits {
+            max_groups: 2,
+            max_depth: 2,
+        };
+
+        assert!(Regex::compile_with_limits("((a)|b)", limits).unwrap().is_match("b"));
+        assert!(Regex::compile_with_limits("(a)(b)", limits).is_ok());
+    }
+
+    #[test]
+    fn test_regex_compile_with_limits_too_many_groups() {
+        let limits = Limits {
+            max_groups: 2,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Some(ParseError::TooManyGroups { count: 3, limit: 2 }),
+            Regex::compile_with_limits("(a)(b)(c)", limits).err()
+        );
+    }
+
+    #[test]
+    fn test_regex_compile_with_limits_too_deeply_nested() {
+        let limits = Limits {
+            max_depth: 2,
+            ..Default::default()
+        };
+        let pattern = format!("{}a{}", "(".repeat(1000), ")".repeat(1000));
+
+        assert_eq!(
+            Some(ParseError::TooDeeplyNested { depth: 3, limit: 2 }),
+            Regex::compile_with_limits("(((a)))", limits).err()
+        );
+        assert_eq!(
+            Some(ParseError::TooDeeplyNested { depth: 1000, limit: 2 }),
+            Regex::compile_with_limits(&pattern, limits).err()
+        );
+    }
+
+    #[test]
+    fn test_regex_compile_fixed() {
+        let regex = Regex::compile_fixed("a.b");
+
+        assert!(regex.is_match("a.b"));
+        assert!(regex.is_match("xa.by"));
+        assert!(!regex.is_match("axb"));
+    }
+
+    #[test]
+    fn test_regex_compile_fixed_metacharacters() {
+        assert!(Regex::compile_fixed("(a|b)+").is_match("(a|b)+"));
+        assert!(!Regex::compile_fixed("(a|b)+").is_match("a"));
+        assert!(Regex::compile_fixed("^[x]$\\d").is_match("1^[x]$\\d2"));
+    }
+
+    #[test]
+    fn test_regex_compile_fixed_find_iter() {
+        let regex = Regex::compile_fixed("a.b");
+        let spans: Vec<_> = regex.find_iter("xa.by a.b").map(|span| (span.start, span.end)).collect();
+
+        assert_eq!(vec![(1, 4), (6, 9)], spans);
+        assert_eq!(Some("a.b"), regex.find("xa.by").map(|span| span.as_str("xa.by")));
+    }
+
+    #[test]
+    fn test_regex_capture_names() {
+        let regex = Regex::compile("(\\d+)-(?<month>\\d+)-(\\d+)");
+
+        assert_eq!(3, regex.captures_len());
+        assert_eq!(vec![None, Some("month".to_string()), None], regex.capture_names());
+    }
+
+    #[test]
+    fn test_regex_capture_names_no_groups() {
+        let regex = Regex::compile("abc");
+
+        assert_eq!(0, regex.captures_len());
+        assert!(regex.capture_names().is_empty());
+    }
+
+    #[test]
+    fn test_regex_debug_tree() {
+        let tree = Regex::compile("^a+$").debug_tree();
+
+        assert_eq!("StartOfLineAnchor\nOneOrMore\n  Literal { char: 'a' }\nEndOfLineAnchor", tree);
+    }
+
+    #[test]
+    fn test_regex_captures() {
+        let captures = Regex::compile("(\\d+)-(\\d+)").captures("12-34").unwrap();
+
+        assert_eq!(3, captures.len());
+        assert_eq!(Some(Span { start: 0, end: 5 }), captures.get(0));
+        assert_eq!(Some(Span { start: 0, end: 2 }), captures.get(1));
+        assert_eq!(Some(Span { start: 3, end: 5 }), captures.get(2));
+        assert_eq!(Some("12-34"), captures.get_text(0));
+        assert_eq!(Some("12"), captures.get_text(1));
+        assert_eq!(Some("34"), captures.get_text(2));
+        assert_eq!(None, captures.get(3));
+    }
+
+    #[test]
+    fn test_regex_captures_offsets() {
+        let captures = Regex::compile("(\\d+)-(\\d+)").captures("ab 12-34 cd").unwrap();
+
+        assert_eq!(Some(Span { start: 3, end: 8 }), captures.get(0));
+        assert_eq!(Some(Span { start: 3, end: 5 }), captures.get(1));
+        assert_eq!(Some(Span { start: 6, end: 8 }), captures.get(2));
+    }
+
+    #[test]
+    fn test_regex_captures_multibyte() {
+        let captures = Regex::compile("(\\w+) (café)").captures("é au café").unwrap();
+
+        assert_eq!(Some("au café"), captures.get_text(0));
+        assert_eq!(Some("au"), captures.get_text(1));
+        assert_eq!(Some("café"), captures.get_text(2));
+    }
+
+    #[test]
+    fn test_regex_captures_group_not_participating() {
+        let captures = Regex::compile("((a)|(b))").captures("b").unwrap();
+        assert_eq!(Some("b"), captures.get_text(1));
+        assert_eq!(None, captures.get_text(2));
+        assert_eq!(Some("b"), captures.get_text(3));
+    }
+
+    #[test]
+    fn test_regex_captures_empty_branch() {
+        let captures = Regex::compile("(|a)b").captures("ab").unwrap();
+        assert_eq!(Some("ab"), captures.get_text(0));
+        assert_eq!(Some("a"), captures.get_text(1));
+
+        let captures = Regex::compile("x(a|)y").captures("xy").unwrap();
+        assert_eq!(Some(Span { start: 1, end: 1 }), captures.get(1));
+
+        let captures = Regex::compile("(|)(a|)\\2").captures("aa").unwrap();
+        assert_eq!(Some(""), captures.get_text(1));
+        assert_eq!(Some("a"), captures.get_text(2));
+    }
+
+    #[test]
+    fn test_regex_captures_backtracking_into_groups() {
+        let texts = |pattern: &str, text: &str| -> Vec<Option<String>> {
+            let captures = Regex::compile(pattern).captures(text).unwrap();
+            (0..captures.len())
+                .map(|id| captures.get_text(id).map(str::to_string))
+                .collect()
+        };
+        let expected = |texts: &[Option<&str>]| -> Vec<Option<String>> {
+            texts.iter().map(|text| text.map(str::to_string)).collect()
+        };
+
+        assert_eq!(
+            expected(&[Some("abcd"), Some("a"), Some("bcd"), Some("")]),
+            texts("(a|ab)(c|bcd)(d*)", "abcd")
+        );
+        assert_eq!(
+            expected(&[Some("hello big"), Some("hello big"), Some("hello"), Some("big")]),
+            texts("((\\w+) (\\w+))", "hello big world")
+        );
+        assert_eq!(expected(&[Some("ac"), Some("a"), Some("a")]), texts("((a)|b)c", "bac"));
+        assert_eq!(expected(&[Some("ac"), Some("a"), None]), texts("(a(b)?)c", "ac"));
+        assert_eq!(expected(&[Some("y"), None]), texts("(x)?y", "y"));
+        assert_eq!(expected(&[Some("aaab"), Some("aaa")]), texts("(?>(a+))b", "aaab"));
+        assert_eq!(expected(&[Some("b"), Some("a")]), texts("(?<=(a))b", "ab"));
+        assert_eq!(expected(&[Some("ééy"), Some("éé"), Some("y")]), texts("(é+)(.)", "xééy"));
+    }
+
+    #[test]
+    fn test_regex_captures_options_of_different_lengths() {
+        let groups = |pattern: &str, text: &str| -> Vec<Option<String>> {
+            let captures = Regex::compile(pattern).captures(text).unwrap();
+            (1..captures.len())
+                .map(|id| captures.get_text(id).map(str::to_string))
+                .collect()
+        };
+        let some = |texts: &[&str]| -> Vec<Option<String>> {
+            texts.iter().map(|text| Some(text.to_string())).collect()
+        };
+
+        // The first option that lets the whole pattern match is kept.
+        assert_eq!(some(&["ab"]), groups("(a|ab)c", "abc"));
+        assert_eq!(some(&["a", "bc"]), groups("(a|ab)(c|bc)", "abc"));
+        assert_eq!(some(&["ab", "c"]), groups("(ab|a)(c|bc)", "abc"));
+        assert_eq!(some(&["ab"]), groups("^(a|ab){2}c$", "aabc"));
+        assert_eq!(some(&["a"]), groups("^(a|ab)*bc$", "aabc"));
+    }
+
+    #[test]
+    fn test_regex_captures_quantified_group_last_iteration() {
+        let captures = Regex::compile("(\\d)+").captures("123").unwrap();
+        assert_eq!(Some("123"), captures.get_text(0));
+        assert_eq!(Some("3"), captures.get_text(1));
+        assert_eq!(Some(Span { start: 2, end: 3 }), captures.get(1));
+
+        let captures = Regex::compile("(\\d)+-\\1").captures("123-3").unwrap();
+        assert_eq!(Some("123-3"), captures.get_text(0));
+        assert!(Regex::compile("^(\\d)+-\\1$").captures("123-1").is_none());
+
+        // A group not taken by the last iteration keeps its capture from an earlier one.
+        let captures = Regex::compile("((a)|b)+c").captures("abc").unwrap();
+        assert_eq!(Some("b"), captures.get_text(1));
+        assert_eq!(Some("a"), captures.get_text(2));
+
+        // Failed later iterations restore the capture of the last successful one.
+        let captures = Regex::compile("(\\w+ )*end").captures("one two end").unwrap();
+        assert_eq!(Some("two "), captures.get_text(1));
+    }
+
+    #[test]
+    fn test_regex_quantified_group_long_line() {
+        let line = "ab".repeat(60_000);
+        let captures = Regex::compile("(ab)+").captures(&line).unwrap();
+
+        assert_eq!(Some(Span { start: 0, end: 120_000 }), captures.get(0));
+        assert_eq!(Some(Span { start: 119_998, end: 120_000 }), captures.get(1));
+        assert!(Regex::compile("^(ab)+$").is_match(&line));
+
+        // Iterations of different lengths are backtracked into, which takes more stack
+        // than a thread has.
+        let line = format!("{}end", "word ".repeat(3000));
+        assert!(Regex::compile("^(\\w+ )*end$").is_match(&line));
+        assert!(!Regex::compile("^(\\w+ )*end$").is_match(&format!("{}x", line)));
+    }
+
+    #[test]
+    fn test_regex_captures_at() {
+        let regex = Regex::compile("(\\d+)px");
+        let captures = regex.captures_at("w=10px h=20px", 6).unwrap();
+
+        assert_eq!(Some(Span { start: 9, end: 13 }), captures.get(0));
+        assert_eq!(Some("20"), captures.get_text(1));
+        assert!(regex.captures_at("w=10px h=20px", 11).is_none());
+    }
+
+    #[test]
+    fn test_regex_captures_iter() {
+        let regex = Regex::compile("(\\w)(\\d)");
+        let all: Vec<Captures> = regex.captures_iter("a1b2").collect();
+
+        assert_eq!(2, all.len());
+        assert_eq!(Some(Span { start: 0, end: 2 }), all[0].get(0));
+        assert_eq!(Some("a"), all[0].get_text(1));
+        assert_eq!(Some("1"), all[0].get_text(2));
+        assert_eq!(Some(Span { start: 2, end: 4 }), all[1].get(0));
+        assert_eq!(Some("b"), all[1].get_text(1));
+        assert_eq!(Some("2"), all[1].get_text(2));
+        assert_eq!(0, regex.captures_iter("ab").count());
+    }
+
+    #[test]
+    fn test_regex_captures_iter_groups_reset() {
+        let groups: Vec<Option<&str>> = Regex::compile("(a)?b")
+            .captures_iter("abb")
+            .map(|captures| captures.get_text(1))
+            .collect();
+
+        assert_eq!(vec![Some("a"), None], groups);
+    }
+
+    #[test]
+    fn test_regex_captures_iter_literal_alternation() {
+        let groups: Vec<Option<&str>> = Regex::compile("(cat|dog)")
+            .captures_iter("dog, cat")
+            .map(|captures| captures.get_text(1))
+            .collect();
+
+        assert_eq!(vec![Some("dog"), Some("cat")], groups);
+    }
+
+    #[test]
+    fn test_regex_captures_no_match() {
+        assert!(Regex::compile("(\\d+)-(\\d+)").captures("12-").is_none());
+    }
+
+    #[test]
+    fn test_regex_compile_fixed_set() {
+        let regex = Regex::compile_fixed_set(&["cat", "c.t", "category"]);
+
+        assert!(regex.is_match("a c.t"));
+        assert!(!regex.is_match("cut"));
+        assert_eq!(Some(Span { start: 2, end: 10 }), regex.find("a category"));
+        assert_eq!(Some(5), regex.shortest_match("a category"));
+        assert_eq!(0, regex.captures_len());
+
+        let text = "cat c.t cat";
+        let matches: Vec<&str> = regex.find_iter(text).map(|span| span.as_str(text)).collect();
+        assert_eq!(vec!["cat", "c.t", "cat"], matches);
+    }
+
+    #[test]
+    fn test_regex_compile_fixed_set_full_match() {
+        let regex = Regex::compile_fixed_set(&["ab", "abc"]);
+
+        assert!(regex.is_full_match("abc"));
+        assert!(regex.is_full_match("ab"));
+        assert!(!regex.is_full_match("abcd"));
+    }
+
+    #[test]
+    fn test_regex_compile_fixed_set_ignore_case() {
+        let regex = Regex::compile_fixed_set(&["cat", "dog"]).ignore_case();
+
+        assert!(regex.is_match("a DOG"));
+        assert_eq!(Some(Span { start: 2, end: 5 }), regex.find("a Cat"));
+    }
+
+    #[test]
+    fn test_regex_compile_fixed_set_empty() {
+        assert!(!Regex::compile_fixed_set(&[]).is_match("abc"));
+        assert!(Regex::compile_fixed_set(&["x", ""]).is_match("abc"));
+    }
+
+    #[test]
+    fn test_regex_compile_fixed_set_engine() {
+        let regex = Regex::compile_fixed_set(&["cat"]).with_engine(Engine::Nfa).unwrap();
+        assert!(regex.is_match("a cat"));
+    }
+
+    #[test]
+    fn test_regex_literal_alternation() {
+        let text = "a bird, a dog and a cat";
+        let regex = Regex::compile("(cat|dog|bird)");
+
+        let matches: Vec<&str> = regex.find_iter(text).map(|span| span.as_str(text)).collect();
+        assert_eq!(vec!["bird", "dog", "cat"], matches);
+
+        let captures = regex.captures("hot dog").unwrap();
+        assert_eq!(Some(Span { start: 4, end: 7 }), captures.get(0));
+        assert_eq!(Some(Span { start: 4, end: 7 }), captures.get(1));
+    }
+
+    #[test]
+    fn test_regex_literal_alternation_first_option_wins() {
+        assert_eq!(Some(Span { start: 0, end: 1 }), Regex::compile("(a|ab)").find("ab"));
+        assert_eq!(Some(Span { start: 0, end: 2 }), Regex::compile("(ab|a)").find("ab"));
+        assert_eq!(Some(Span { start: 1, end: 3 }), Regex::compile("(bc|abcd)").find("abcx"));
+    }
+
+    #[test]
+    fn test_regex_literal_alternation_agrees_with_backtracking() {
+        // The nested group keeps the pattern from being searched as a set of literals.
+        let patterns = [("(ab|a|bcd|)", "((ab|a|bcd|))"), ("(x|yx|xyz)", "((x|yx|xyz))")];
+        let texts = ["", "abcd", "xyzyx", "bcdab", "yxyz", "zzz"];
+
+        for (literals, nested) in patterns {
+            let (literals, nested) = (Regex::compile(literals), Regex::compile(nested));
+            for text in texts {
+                let expected: Vec<Span> = nested.find_iter(text).collect();
+                assert_eq!(expected, literals.find_iter(text).collect::<Vec<_>>(), "{:?}", text);
+                assert_eq!(nested.is_match(text), literals.is_match(text), "{:?}", text);
+                assert_eq!(nested.shortest_match(text), literals.shortest_match(text), "{:?}", text);
+            }
+        }
+    }
+
+    #[test]
+    fn test_regex_literal_alternation_options() {
+        let regex = RegexBuilder::new("(cat|dog)").case_insensitive(true).build().unwrap();
+        assert!(regex.is_match("HOT DOG"));
+
+        let regex = Regex::compile_anchored("(cat|dog)");
+        assert!(regex.is_match("dog days"));
+        assert!(!regex.is_match("hot dog"));
+    }
+
+    #[test]
+    fn test_regex_find() {
+        let text = "abc123def";
+        let span = Regex::compile("\\d+").find(text).unwrap();
+
+        assert_eq!(Span { start: 3, end: 6 }, span);
+        assert_eq!("123", span.as_str(text));
+    }
+
+    #[test]
+    fn test_regex_find_anchored() {
+        assert_eq!(None, Regex::compile("^b").find("ab"));
+        assert_eq!(Some(Span { start: 0, end: 1 }), Regex::compile("^a").find("ab"));
+    }
+
+    #[test]
+    fn test_regex_find_iter_alternation_anchors() {
+        assert_eq!(vec![(0, 1), (3, 4)], find_all("(^a|a$)", "abaa"));
+        assert_eq!(vec![(0, 1)], find_all("(^a|b$)", "aa"));
+    }
+
+    #[test]
+    fn test_regex_find_iter_quantified_alternation_anchors() {
+        // An anchored option is only taken by iterations at the matching position,
+        // the other iterations continue with the remaining options.
+        assert_eq!(vec![(0, 2)], find_all("(a$|b)+", "ba"));
+        assert_eq!(vec![(0, 1), (2, 3)], find_all("(a$|b)+", "bab"));
+        assert_eq!(vec![(1, 2)], find_all("(a$|b)+", "aa"));
+        assert_eq!(vec![(0, 1), (2, 5)], find_all("(a$|b)+", "babba"));
+        assert_eq!(vec![(0, 3)], find_all("(a$|b)+$", "bba"));
+        assert_eq!(vec![(2, 3)], find_all("(a$|b)+$", "bab"));
+
+        assert_eq!(vec![(0, 2), (3, 4)], find_all("(^a|b)+", "abab"));
+        assert_eq!(vec![(2, 3)], find_all("(^a|b)+", "xab"));
+        assert_eq!(Vec::<(usize, usize)>::new(), find_all("(^a|b)+", "xa"));
+        assert_eq!(vec![(0, 3)], find_all("(^a|b)*", "abb"));
+        assert_eq!(vec![(0, 0), (1, 1), (2, 3)], find_all("(^a|b)*", "xab"));
+
+        assert_eq!(vec![(0, 2)], find_all("(b|a$)+c", "bc"));
+        assert_eq!(Vec::<(usize, usize)>::new(), find_all("(b|a$)+c", "bac"));
+        assert_eq!(Vec::<(usize, usize)>::new(), find_all("(a|b$){2}", "ba"));
+        assert_eq!(Vec::<(usize, usize)>::new(), find_all("(^a|b){2}", "aa"));
+        assert_eq!(vec![(0, 2)], find_all("(^a|b){2}", "bb"));
+    }
+
+    #[test]
+    fn test_regex_captures_quantified_alternation_anchors() {
+        let groups = |pattern: &str, text: &str| {
+            let captures = Regex::compile(pattern).captures(text).unwrap();
+            (0..captures.len())
+                .map(|id| captures.get(id).map(|span| (span.start, span.end)))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(vec![Some((0, 2)), Some((1, 2))], groups("(a$|b)+", "ba"));
+        assert_eq!(vec![Some((0, 1)), Some((0, 1))], groups("(a$|b)+", "bab"));
+        assert_eq!(vec![Some((0, 2)), Some((1, 2))], groups("(^a|b)*", "ab"));
+        assert_eq!(vec![Some((0, 0)), None], groups("(^a|b)*", "xab"));
+        assert_eq!(vec![Some((0, 1)), None], groups("x(a$|b)*", "xab"));
+
+        // Groups within an option failing at its anchor do not keep their capture.
+        assert_eq!(vec![Some((0, 2)), Some((0, 1)), None], groups("((a)$|a)b", "ab"));
+        assert_eq!(vec![Some((0, 1)), Some((0, 1)), None], groups("((^a)|b)+", "bab"));
+        assert_eq!(vec![Some((2, 3)), None, None], groups("(a|(b)$)*c", "abc"));
+
+        // An iteration matching only an anchor is empty, which ends the repetition.
+        assert_eq!(vec![Some((0, 1)), Some((0, 0))], groups("(x|^)+a", "a"));
+        assert_eq!(vec![Some((0, 2)), Some((2, 2))], groups("(x|$)+", "xx"));
+    }
+
+    #[test]
+    fn test_regex_multiline_quantified_alternation_anchors() {
+        let spans = |pattern: &str, text: &str| {
+            let regex = RegexBuilder::new(pattern).multiline(true).build().unwrap();
+            regex.find_iter(text).map(|span| (span.start, span.end)).collect::<Vec<_>>()
+        };
+
+        assert_eq!(vec![(0, 2), (4, 5), (6, 7)], spans("(a$|b)+", "ba\nab\nb"));
+        assert_eq!(vec![(3, 5), (7, 8)], spans("(^a|b)+", "xa\nab\nxb"));
+    }
+
+    #[test]
+    fn test_regex_find_multibyte() {
+        let text = "äöü 42";
+        let span = Regex::compile("\\d+").find(text).unwrap();
+
+        assert_eq!("42", span.as_str(text));
+    }
+
+    #[test]
+    fn test_regex_shortest_match() {
+        let regex = Regex::compile("a.*b");
+
+        assert_eq!(Some(Span { start: 1, end: 6 }), regex.find("xaYbZb"));
+        assert_eq!(Some(4), regex.shortest_match("xaYbZb"));
+        assert_eq!(Some(1), Regex::compile("a+").shortest_match("aaa"));
+        assert_eq!(Some(0), Regex::compile("a*").shortest_match("aaa"));
+        assert_eq!(None, Regex::compile("a.*b").shortest_match("xaY"));
+        assert_eq!(Some(3), Regex::compile("(a)\\1+").shortest_match("xaaaa"));
+    }
+
+    #[test]
+    fn test_regex_shortest_match_anchors() {
+        assert_eq!(Some(3), Regex::compile("(ab$|abx)").shortest_match("abx"));
+        assert_eq!(Some(5), Regex::compile("a.*b$").shortest_match("axbyb"));
+        assert_eq!(Some(1), Regex::compile("^a+").shortest_match("aaa"));
+    }
+
+    #[test]
+    fn test_regex_shortest_match_multibyte() {
+        assert_eq!(Some(5), Regex::compile("ü.*ö").shortest_match("üxöyö"));
+    }
+
+    #[test]
+    fn test_regex_find_iter() {
+        assert_eq!(vec![(1, 2), (3, 5)], find_all("\\d+", "a1b22c"));
+        assert_eq!(Vec::<(usize, usize)>::new(), find_all("\\d+", "abc"));
+    }
+
+    #[test]
+    fn test_regex_find_iter_empty_matches() {
+        assert_eq!(vec![(0, 0), (1, 4), (5, 5)], find_all("a?a?a?", "baaab"));
+        assert_eq!(vec![(0, 0), (1, 1), (2, 2)], find_all("x?", "ab"));
+        assert_eq!(vec![(0, 0), (1, 4), (5, 5)], find_all("a*", "baaab"));
+        assert_eq!(vec![(0, 0), (1, 5)], find_all("é*", "xéé"));
+    }
+
+    #[test]
+    fn test_regex_find_iter_previous_match_end_anchor() {
+        assert_eq!(vec![(0, 1), (1, 2)], find_all("\\G\\d", "12a3"));
+        assert_eq!(vec![(0, 1), (1, 2), (3, 4)], find_all("\\d", "12a3"));
+        assert_eq!(Vec::<(usize, usize)>::new(), find_all("\\G\\d", "a12"));
+        assert_eq!(vec![(0, 2), (2, 4)], find_all("\\G\\w\\w", "abcd ef"));
+    }
+
+    #[test]
+    fn test_regex_find_iter_overlapping() {
+        let regex = Regex::compile("aa");
+        let overlapping: Vec<_> = regex
+            .find_iter_overlapping("aaaa")
+            .map(|span| (span.start, span.end))
+            .collect();
+
+        assert_eq!(vec![(0, 2), (1, 3), (2, 4)], overlapping);
+        assert_eq!(vec![(0, 2), (2, 4)], find_all("aa", "aaaa"));
+    }
+
+    #[test]
+    fn test_regex_find_iter_overlapping_count() {
+        let regex = Regex::compile("a+");
+
+        assert_eq!(4, regex.find_iter_overlapping("aaaa").count());
+        assert_eq!(1, regex.find_iter("aaaa").count());
+        assert_eq!(3, regex.find_iter_overlapping("öaöaöa").count());
+    }
+
+    #[test]
+    fn test_regex_find_iter_overlapping_empty_matches() {
+        let regex = Regex::compile("x?");
+
+        assert_eq!(3, regex.find_iter_overlapping("ab").count());
+    }
+
+    #[test]
+    fn test_regex_replacen() {
+        let regex = Regex::compile("\\d+");
+
+        assert_eq!("a1b22c333", regex.replacen("a1b22c333", 0, "#"));
+        assert_eq!("a#b22c333", regex.replacen("a1b22c333", 1, "#"));
+        assert_eq!("a#b#c333", regex.replacen("a1b22c333", 2, "#"));
+        assert_eq!("a#b#c#", regex.replacen("a1b22c333", 10, "#"));
+    }
+
+    #[test]
+    fn test_regex_replacen_no_match() {
+        assert_eq!("abc", Regex::compile("\\d").replacen("abc", 1, "#"));
+    }
+
+    #[test]
+    fn test_regex_replacen_empty_matches() {
+        assert_eq!("-a-b-", Regex::compile("x?").replacen("ab", 3, "-"));
+        assert_eq!("-ab", Regex::compile("x?").replacen("ab", 1, "-"));
+    }
+
+    #[test]
+    fn test_regex_replace_all_with() {
+        let regex = Regex::compile("\\w+");
+        let replaced = regex.replace_all_with("hello, big world!", |captures| {
+            captures.get_text(0).unwrap().to_uppercase()
+        });
+
+        assert_eq!("HELLO, BIG WORLD!", replaced);
+    }
+
+    #[test]
+    fn test_regex_replace_all_with_groups() {
+        let regex = Regex::compile("(\\w+)=(\\d+)");
+        let replaced = regex.replace_all_with("a=1, b=22", |captures| {
+            format!("{}:{}", captures.get_text(2).unwrap(), captures.get_text(1).unwrap())
+        });
+
+        assert_eq!("1:a, 22:b", replaced);
+    }
+
+    #[test]
+    fn test_regex_distinct_group_values() {
+        let regex = Regex::compile("(\\w)=\\d");
+
+        let expected: HashSet<String> = ["a", "b"].map(String::from).into();
+        assert_eq!(expected, regex.distinct_group_values("a=1;b=2;a=3", 1));
+
+        let expected: HashSet<String> = ["a=1", "b=2", "a=3"].map(String::from).into();
+        assert_eq!(expected, regex.distinct_group_values("a=1;b=2;a=3", 0));
+    }
+
+    #[test]
+    fn test_regex_distinct_group_values_not_participating() {
+        let regex = Regex::compile("((x)|y)\\d");
+
+        let expected: HashSet<String> = ["x"].map(String::from).into();
+        assert_eq!(expected, regex.distinct_group_values("y1 x2 y3 x4", 2));
+        assert!(regex.distinct_group_values("y1 y3", 2).is_empty());
+        assert!(regex.distinct_group_values("x1", 3).is_empty());
+    }
+
+    #[test]
+    fn test_regex_replace_all_with_no_match() {
+        let mut calls = 0;
+        let replaced = Regex::compile("\\d").replace_all_with("abc", |_| {
+            calls += 1;
+            String::new()
+        });
+
+        assert_eq!("abc", replaced);
+        assert_eq!(0, calls);
+    }
+
+    #[test]
+    fn test_regex_empty_pattern() {
+        let regex = Regex::compile("");
+
+        assert!(regex.is_match("abc"));
+        assert!(regex.is_match(""));
+        assert!(regex.is_full_match(""));
+        assert!(!regex.is_full_match("abc"));
+        assert_eq!(4, regex.find_iter("abc").count());
+    }
+
+    #[test]
+    fn test_regex_split() {
+        assert_eq!(vec!["a", "b", "c"], Regex::compile("\\d+").split("a1b22c"));
+    }
+
+    #[test]
+    fn test_regex_split_multiple_delimiters() {
+        assert_eq!(vec!["a", "b", "c", "d"], Regex::compile("[,;] ?").split("a, b;c,d"));
+        assert_eq!(vec!["key", "value", "x"], Regex::compile("(=|:)").split("key=value:x"));
+    }
+
+    #[test]
+    fn test_regex_split_no_delimiter() {
+        assert_eq!(vec!["abc"], Regex::compile("\\d").split("abc"));
+        assert_eq!(vec![""], Regex::compile("\\d").split(""));
+    }
+
+    #[test]
+    fn test_regex_split_leading_and_trailing_delimiters() {
+        assert_eq!(vec!["", "a", "b", ""], Regex::compile("\\d").split("1a2b3"));
+        assert_eq!(vec!["", ""], Regex::compile("\\d+").split("123"));
+    }
+
+    #[test]
+    fn test_regex_split_empty_matches() {
+        assert_eq!(vec!["", "a", "b", "c", ""], Regex::compile("x?").split("abc"));
+    }
+
+    #[test]
+    fn test_regex_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        // Required to search several files on different threads with the same regex.
+        assert_send_sync::<Regex>();
+    }
+
+    #[test]
+    fn test_regex_shared_between_threads() {
+        let regex = Arc::new(Regex::compile("(\\w+)@(\\w+)[.]com"));
+
+        let handles: Vec<_> = (0..8)
+            .map(|index| {
+                let regex = Arc::clone(&regex);
+                thread::spawn(move || {
+                    let text = format!("mail user{0}@host{0}.com or x@y.org", index);
+                    (0..100)
+                        .map(|_| {
+                            let user = regex.captures(&text).unwrap().get_text(1).unwrap();
+                            (user.to_string(), regex.find_iter(&text).count())
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        for (index, handle) in handles.into_iter().enumerate() {
+            let results = handle.join().unwrap();
+
+            assert!(results.iter().all(|result| *result == (format!("user{}", index), 1)));
+        }
+    }
+
+    #[test]
+    fn test_regex_builder_defaults() {
+        let regex = RegexBuilder::new("^a.\\w$").build().unwrap();
+
+        assert!(regex.is_match("ab_"));
+        assert!(!regex.is_match("A\né"));
+        assert!(!regex.is_match("x\nab_"));
+    }
+
+    #[test]
+    fn test_regex_builder_flags() {
+        let regex = RegexBuilder::new("a").multiline(true).unicode(true).build().unwrap();
+        let expected = Flags {
+            multiline: true,
+            unicode: true,
+            ..Flags::default()
+        };
+
+        assert_eq!(expected, regex.flags());
+        assert_eq!(Flags::default(), Regex::compile("a").flags());
+        assert!(Regex::compile("a").ignore_case().flags().case_insensitive);
+    }
+
+    #[test]
+    fn test_regex_builder_limits() {
+        let limits = Limits {
+            max_groups: 1,
+            ..Limits::default()
+        };
+
+        assert_eq!(
+            Some(ParseError::TooManyGroups { count: 2, limit: 1 }),
+            RegexBuilder::new("(a)(b)").limits(limits).build().err()
+        );
+    }
+
+    #[test]
+    fn test_regex_builder_case_insensitive() {
+        let regex = RegexBuilder::new("ab[c-d]").case_insensitive(true).build().unwrap();
+
+        assert!(regex.is_match("ABD"));
+        assert!(!RegexBuilder::new("ab").case_insensitive(false).build().unwrap().is_match("AB"));
+    }
+
+    #[test]
+    fn test_regex_builder_multiline() {
+        let regex = RegexBuilder::new("^b\\d$").multiline(true).build().unwrap();
+
+        assert!(regex.is_match("a1\nb2\nc3"));
+        assert_eq!(Some(Span { start: 3, end: 5 }), regex.find("a1\nb2\nc3"));
+        assert!(!regex.is_match("a1 b2"));
+        assert!(!RegexBuilder::new("^b\\d$").build().unwrap().is_match("a1\nb2\nc3"));
+    }
+
+    #[test]
+    fn test_regex_builder_dot_matches_newline() {
+        let regex = RegexBuilder::new("a.b").dot_matches_newline(true).build().unwrap();
+
+        assert!(regex.is_match("a\nb"));
+        assert!(regex.is_match("axb"));
+        assert!(!RegexBuilder::new("a.b").build().unwrap().is_match("a\nb"));
+    }
+
+    #[test]
+    fn test_regex_builder_unicode() {
+        let regex = RegexBuilder::new("^\\w+ [\\d]$").unicode(true).build().unwrap();
+
+        assert!(regex.is_match("café ٣"));
+        assert!(!RegexBuilder::new("^\\w+ [\\d]$").build().unwrap().is_match("café ٣"));
+    }
+
+    #[test]
+    fn test_regex_builder_unicode_case_insensitive() {
+        let regex = RegexBuilder::new("\\w")
+            .unicode(true)
+            .case_insensitive(true)
+            .build()
+            .unwrap();
+
+        assert!(regex.is_full_match("É"));
+    }
+
+    #[test]
+    fn test_regex_builder_ignore_whitespace() {
+        let pattern = "
+            ^ (\\d{4}) - (\\d{2})   # year and month
+            [ ] \\# \\d+ $         # a space and an issue number
+        ";
+        let regex = RegexBuilder::new(pattern).ignore_whitespace(true).build().unwrap();
+        let compact = Regex::compile("^(\\d{4})-(\\d{2})[ ]#\\d+$");
+
+        assert_eq!(compact.debug_tree(), regex.debug_tree());
+        assert!(regex.is_match("2024-05 #12"));
+        assert!(!regex.is_match("2024-05#12"));
+    }
+
+    #[test]
+    fn test_regex_compile_free_spacing_flag() {
+        let regex = Regex::compile("(?x) a+ \\  b  # trailing comment");
+
+        assert_eq!(Regex::compile("a+ b").debug_tree(), regex.debug_tree());
+        assert!(regex.is_match("aa b"));
+        assert!(!regex.is_match("aab"));
+    }
+
+    #[test]
+    fn test_regex_candidates_min_len() {
+        let regex = Regex::compile("a\\d+");
+
+        assert_eq!(vec![0, 1, 2], regex.candidates("abcd", 0).collect::<Vec<_>>());
+        assert_eq!(vec![2], regex.candidates("abcd", 2).collect::<Vec<_>>());
+        assert_eq!(0, regex.candidates("a", 0).count());
+        assert_eq!(vec![0, 1, 2, 3, 4], Regex::compile("x*").candidates("abcd", 0).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_regex_candidates_end_anchored() {
+        let text = "x".repeat(10_000);
+
+        assert_eq!(vec![9_997], Regex::compile("abc$").candidates(&text, 0).collect::<Vec<_>>());
+        assert_eq!(2, Regex::compile("a\\d{2,3}$").candidates(&text, 0).count());
+        assert_eq!(10_000, Regex::compile("a+$").candidates(&text, 0).count());
+        assert_eq!(0, Regex::compile("^abc$").candidates(&text, 0).filter(|c| *c == 0).count());
+    }
+
+    #[test]
+    fn test_regex_candidates_start_anchored() {
+        let text = "x".repeat(10_000);
+
+        assert_eq!(vec![0], Regex::compile("^abc").candidates(&text, 0).collect::<Vec<_>>());
+        assert_eq!(vec![0], Regex::compile_anchored("abc").candidates(&text, 0).collect::<Vec<_>>());
+        assert_eq!(0, Regex::compile("^abc").candidates(&text, 1).count());
+        assert_eq!(0, Regex::compile("^abc$").candidates(&text, 0).count());
+    }
+
+    #[test]
+    fn test_regex_start_anchored_long_line() {
+        let text = format!("{}abc", "ab".repeat(50_000));
+
+        assert!(Regex::compile("^[ab]+c").is_match(&text));
+        assert!(!Regex::compile("^abc").is_match(&text));
+        assert_eq!(0, Regex::compile("^b").find_iter(&text).count());
+
+        let nfa = Regex::compile("^abc").with_engine(Engine::Nfa).unwrap();
+        assert!(!nfa.is_match(&text));
+        assert!(nfa.is_match("abcd"));
+    }
+
+    #[test]
+    fn test_regex_candidates_multibyte() {
+        let regex = Regex::compile("éa$");
+
+        assert_eq!(vec![2], regex.candidates("ééa", 0).collect::<Vec<_>>());
+        assert_eq!(Some(Span { start: 2, end: 5 }), regex.find("ééa"));
+        assert_eq!(None, regex.find("éa é"));
+    }
+
+    #[test]
+    fn test_regex_end_anchored_long_line() {
+        let text = format!("{}abc", "ab".repeat(50_000));
+
+        assert!(Regex::compile("b?abc$").is_match(&text));
+        assert!(!Regex::compile("abd$").is_match(&text));
+        assert!(Regex::compile("(ab|x)c$").is_match(&text));
+    }
+
+    #[test]
+    fn test_regex_literal_run() {
+        let regex = Regex::compile("fox (jumps|leaps) over");
+
+        assert_eq!(
+            Some(Span { start: 4, end: 18 }),
+            regex.find("the fox leaps over the dog")
+        );
+        assert_eq!(None, regex.find("the fox jumps ove"));
+        assert_eq!(Some(Span { start: 2, end: 7 }), Regex::compile("éèa").find("xxéèab"));
+        assert!(Regex::compile("QUICK brown").ignore_case().is_match("the quick BROWN fox"));
+    }
+
+    #[test]
+    fn test_regex_lookahead_captures() {
+        let regex = Regex::compile("(\\w+)(?=(\\d))");
+        let captures = regex.captures("ab1").unwrap();
+
+        assert_eq!(Some("ab"), captures.get_text(1));
+        assert_eq!(Some(Span { start: 2, end: 3 }), captures.get(2));
+        assert_eq!(Some(Span { start: 0, end: 2 }), captures.get(0));
+        assert!(Regex::compile("(?=(a))\\1b").is_match("ab"));
+        assert!(!Regex::compile("x(?!(a))").is_match("xa"));
+    }
+
+    #[test]
+    fn test_regex_lookahead_find_iter() {
+        let spans: Vec<Span> = Regex::compile("\\w+(?=,)").find_iter("a,bc,d").collect();
+
+        assert_eq!(vec![Span { start: 0, end: 1 }, Span { start: 2, end: 4 }], spans);
+    }
+
+    /// Returns the regex compiled once for each engine.
+    fn compile_engines(pattern: &str) -> (Regex, Regex) {
+        let backtrack = Regex::compile(pattern);
+        let nfa = backtrack.clone().with_engine(Engine::Nfa).unwrap();
+
+        (backtrack, nfa)
+    }
+
+    #[test]
+    fn test_regex_engines_agree_on_fuzz_seeds() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/seeds/matches_regex_crate");
+        let mut count = 0;
+
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let seed = String::from_utf8(std::fs::read(entry.unwrap().path()).unwrap()).unwrap();
+            let (pattern, text) = seed.split_once('\0').unwrap();
+            let (backtrack, nfa) = compile_engines(pattern);
+
+            assert_eq!(
+                backtrack.is_match(text),
+                nfa.is_match(text),
+                "Pattern '{}' on text '{}'",
+                pattern,
+                text
+            );
+            count += 1;
+        }
+
+        assert!(count > 0);
+    }
+
+    #[test]
+    fn test_regex_engines_agree() {
+        let patterns = [
+            "^\\d+ apples?$",
+            "(c.t|d.g) and (f..h|b..d)",
+            "[a-z&&[^aeiou]]{3}",
+            "x{2,}(ab|cde)",
+            "\\Gab",
+            "^$",
+            "(|a)b*",
+            "é+.",
+            "(a$|b)+",
+            "(^a|b)*c",
+            "(b|a$)+c",
+            "(x|^)+a",
+        ];
+        let texts = [
+            "", "12 apples", "1 apple", "cat and bird", "strength", "xxxcde", "abab", "bébéx",
+            "ba", "bac", "xa", "abc",
+        ];
+
+        for pattern in patterns {
+            let (backtrack, nfa) = compile_engines(pattern);
+
+            for text in texts {
+                assert_eq!(
+                    backtrack.is_match(text),
+                    nfa.is_match(text),
+                    "Pattern '{}' on text '{}'",
+                    pattern,
+                    text
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_regex_nfa_engine_options() {
+        let regex = RegexBuilder::new("^B.c$")
+            .case_insensitive(true)
+            .multiline(true)
+            .engine(Engine::Nfa)
+            .build()
+            .unwrap();
+
+        assert!(regex.is_match("x\nbac\ny"));
+        assert!(!regex.is_match("x\nba\nc"));
+        assert!(Regex::compile("ab").with_engine(Engine::Nfa).unwrap().ignore_case().is_match("AB"));
+        assert!(!Regex::compile_anchored("b").with_engine(Engine::Nfa).unwrap().is_match("ab"));
+    }
+
+    #[test]
+    fn test_regex_nfa_engine_unsupported() {
+        let error = Regex::compile("(a)\\1").with_engine(Engine::Nfa).unwrap_err();
+
+        assert_eq!("The NFA engine does not support backreferences", error.to_string());
+        assert!(Regex::compile("(a)\\1").with_engine(Engine::Backtrack).is_ok());
+    }
+}
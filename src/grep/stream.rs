@@ -0,0 +1,175 @@
+use std::borrow::Cow;
+
+use super::regex::{decode_bytes, Regex, Span};
+
+/// Matches a [`Regex`] against text arriving in chunks, e.g. from a socket.
+///
+/// Matches never span multiple lines, so the incomplete last line of each chunk is
+/// buffered until the rest of it arrives. Spans are byte offsets within the whole stream.
+/// Lines that are not valid UTF-8 are decoded like [`Regex::is_match_bytes`] does.
+pub struct StreamMatcher<'r> {
+    regex: &'r Regex,
+
+    /// The incomplete line at the end of the data fed so far.
+    tail: Vec<u8>,
+
+    /// Byte offset of the tail within the stream.
+    tail_offset: usize,
+}
+
+impl<'r> StreamMatcher<'r> {
+    pub fn new(regex: &'r Regex) -> StreamMatcher<'r> {
+        StreamMatcher {
+            regex,
+            tail: vec![],
+            tail_offset: 0,
+        }
+    }
+
+    /// Feeds the next chunk of the stream, returning the matches within all lines
+    /// completed by it.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<Span> {
+        let mut spans = vec![];
+        let mut remainder = chunk;
+
+        while let Some(index) = remainder.iter().position(|b| *b == b'\n') {
+            self.tail.extend_from_slice(&remainder[..index]);
+            remainder = &remainder[index + 1..];
+
+            self.match_tail(&mut spans);
+
+            // Step over the consumed line including its '\n'.
+            self.tail_offset += self.tail.len() + 1;
+            self.tail.clear();
+        }
+
+        self.tail.extend_from_slice(remainder);
+        spans
+    }
+
+    /// Ends the stream, returning the matches within its last line
+    /// if that was not terminated by a newline.
+    pub fn finish(self) -> Vec<Span> {
+        let mut spans = vec![];
+
+        if !self.tail.is_empty() {
+            self.match_tail(&mut spans);
+        }
+
+        spans
+    }
+
+    fn match_tail(&self, spans: &mut Vec<Span>) {
+        let decoded = decode_bytes(&self.tail);
+        let line = decoded.strip_suffix('\r').unwrap_or(&decoded);
+
+        // Offsets within the decoded line only differ from the byte offsets if it was
+        // not valid UTF-8.
+        let byte_offset = |offset| match decoded {
+            Cow::Borrowed(_) => offset,
+            Cow::Owned(_) => byte_offset(&self.tail, offset),
+        };
+
+        spans.extend(self.regex.find_iter(line).map(|span| Span {
+            start: self.tail_offset + byte_offset(span.start),
+            end: self.tail_offset + byte_offset(span.end),
+        }));
+    }
+}
+
+/// Maps an offset within the bytes decoded by [`decode_bytes`] back to the offset
+/// within the bytes. Each invalid byte was decoded to a char longer than the byte.
+fn byte_offset(bytes: &[u8], offset: usize) -> usize {
+    let mut decoded_offset = 0;
+    let mut index = 0;
+
+    for chunk in bytes.utf8_chunks() {
+        let valid_len = chunk.valid().len();
+        if offset <= decoded_offset + valid_len {
+            return index + offset - decoded_offset;
+        }
+
+        decoded_offset += valid_len;
+        index += valid_len;
+
+        for byte in chunk.invalid() {
+            if offset <= decoded_offset {
+                return index;
+            }
+
+            decoded_offset += char::from(*byte).len_utf8();
+            index += 1;
+        }
+    }
+
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(spans: Vec<Span>) -> Vec<(usize, usize)> {
+        spans.iter().map(|span| (span.start, span.end)).collect()
+    }
+
+    #[test]
+    fn test_stream_matcher_match_across_chunks() {
+        let regex = Regex::compile("\\d+");
+        let mut matcher = StreamMatcher::new(&regex);
+
+        assert!(matcher.feed(b"ab 12").is_empty());
+        assert_eq!(vec![(3, 7)], spans(matcher.feed(b"34 cd\n")));
+        assert!(matcher.finish().is_empty());
+    }
+
+    #[test]
+    fn test_stream_matcher_multiple_lines_per_chunk() {
+        let regex = Regex::compile("[a-z]+");
+        let mut matcher = StreamMatcher::new(&regex);
+
+        assert_eq!(vec![(0, 3), (4, 7)], spans(matcher.feed(b"dog\ncat\nfo")));
+        assert_eq!(vec![(8, 11)], spans(matcher.feed(b"x\n")));
+    }
+
+    #[test]
+    fn test_stream_matcher_unterminated_last_line() {
+        let regex = Regex::compile("end$");
+        let mut matcher = StreamMatcher::new(&regex);
+
+        assert!(matcher.feed(b"the\r\nen").is_empty());
+        assert!(matcher.feed(b"d").is_empty());
+        assert_eq!(vec![(5, 8)], spans(matcher.finish()));
+    }
+
+    #[test]
+    fn test_stream_matcher_invalid_utf8() {
+        let regex = Regex::compile("a+");
+        let mut matcher = StreamMatcher::new(&regex);
+
+        assert_eq!(vec![(1, 2), (3, 4)], spans(matcher.feed(b"\xFFa\na\n")));
+        assert_eq!(
+            vec![(6, 8), (10, 11)],
+            spans(matcher.feed(b"\xC3aa\xFF\xFEa\r\n"))
+        );
+    }
+
+    #[test]
+    fn test_stream_matcher_invalid_utf8_matched_byte() {
+        // The invalid byte is matched as the char with the same value.
+        let regex = Regex::compile("x\u{FF}y");
+        let mut matcher = StreamMatcher::new(&regex);
+
+        assert_eq!(vec![(1, 4)], spans(matcher.feed(b"-x\xFFy\n")));
+    }
+
+    #[test]
+    fn test_stream_matcher_utf8_split_across_chunks() {
+        let regex = Regex::compile("ü");
+        let mut matcher = StreamMatcher::new(&regex);
+        let bytes = "xü\n".as_bytes();
+
+        assert!(matcher.feed(&bytes[..2]).is_empty());
+        assert_eq!(vec![(1, 3)], spans(matcher.feed(&bytes[2..])));
+    }
+}
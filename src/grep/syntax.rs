@@ -1,23 +1,33 @@
 use std::collections::VecDeque;
+use std::fmt;
 
-use crate::grep::tokens::Token;
+use crate::grep::charset::CharSet;
+use crate::grep::error::ParseError;
+use crate::grep::patterns;
+use crate::grep::tokens::{tokenize_pattern, Token};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Syntax {
     /// Matches a single specified character.
     Literal { char: char },
 
+    /// Matches the specified text, a run of consecutive literals merged by [`merge_literals`].
+    LiteralRun { text: String },
+
     /// Matches a single digit. Equivalent to \[0-9\]
     Digit,
 
     /// Matches a single word character. Equivalent to \[a-zA-Z0-9_\].
     Word,
 
-    /// Matches any single character.
+    /// Matches any single character except newline.
     Wildcard,
 
-    /// Matches any one of the specified characters.
-    CharacterClass { chars: Vec<char>, is_negated: bool },
+    /// Matches any one of the specified characters, ranges or shorthand classes.
+    CharacterClass { set: CharSet },
+
+    /// Matches the contained single char syntax, or any case variant of the char.
+    CaseInsensitive { syntax: Box<Syntax> },
 
     /// Matches the start of a line.
     StartOfLineAnchor,
@@ -25,33 +35,266 @@ pub enum Syntax {
     /// Matches the end of a line.
     EndOfLineAnchor,
 
+    /// Matches the start of any line within a multiline text.
+    StartOfAnyLineAnchor,
+
+    /// Matches the end of any line within a multiline text.
+    EndOfAnyLineAnchor,
+
+    /// Matches where the search started, which is where the previous match ended (`\G`).
+    PreviousMatchEnd,
+
     /// Matches the contained syntax one or more times.
     OneOrMore { syntax: Box<Syntax> },
 
     /// Matches the contained syntax zero or more times.
+    ZeroOrMore { syntax: Box<Syntax> },
+
+    /// Matches the contained syntax zero or one time.
     ZeroOrOne { syntax: Box<Syntax> },
 
+    /// Matches the contained syntax at least `min` and at most `max` times,
+    /// without an upper limit if `max` is `None`.
+    Repeat {
+        syntax: Box<Syntax>,
+        min: usize,
+        max: Option<usize>,
+    },
+
     /// Matches either of the contained syntax options.
-    CaptureGroup { options: Vec<Vec<Syntax>>, id: u32 },
+    CaptureGroup {
+        options: Vec<Vec<Syntax>>,
+        id: u32,
+        name: Option<String>,
+    },
+
+    /// Matches the first successful option and never backtracks into it afterwards.
+    AtomicGroup { options: Vec<Vec<Syntax>> },
 
-    /// Artificial syntax to finalize capture groups.
-    CaptureGroupEnd { text: String, id: u32 },
+    /// Matches without consuming any text if either of the contained syntax options
+    /// matches at this position (`(?=`), or if none of them does when negated (`(?!`).
+    Lookahead {
+        options: Vec<Vec<Syntax>>,
+        is_negated: bool,
+    },
+
+    /// Matches without consuming any text if either of the contained syntax options
+    /// matches the `len` chars before this position (`(?<=`), or if none of them does
+    /// when negated (`(?<!`). All options match exactly `len` chars.
+    Lookbehind {
+        options: Vec<Vec<Syntax>>,
+        is_negated: bool,
+        len: usize,
+    },
 
     /// References an already matched capture group by id.
     BackReference { id: u32 },
 }
 
-fn into_character_class(tokens: &[Token], is_negated: bool) -> Syntax {
-    Syntax::CharacterClass {
-        chars: tokens
-            .iter()
-            .map(|t| match t {
-                Token::Literal(c) => *c,
-                other => panic!("Invalid token '{}' in character class", other),
-            })
-            .collect(),
-        is_negated: is_negated,
+/// Shorthand character classes that may appear inside a character class.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClassShorthand {
+    /// Matches a single digit (`\d`).
+    Digit,
+
+    /// Matches any character that is not a digit (`\D`).
+    NonDigit,
+
+    /// Matches a single word character (`\w`).
+    Word,
+
+    /// Matches any character that is not a word character (`\W`).
+    NonWord,
+
+    /// Matches a single whitespace character (`\s`).
+    Whitespace,
+
+    /// Matches any character that is not a whitespace character (`\S`).
+    NonWhitespace,
+}
+
+impl fmt::Display for ClassShorthand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let escapee = match self {
+            ClassShorthand::Digit => 'd',
+            ClassShorthand::NonDigit => 'D',
+            ClassShorthand::Word => 'w',
+            ClassShorthand::NonWord => 'W',
+            ClassShorthand::Whitespace => 's',
+            ClassShorthand::NonWhitespace => 'S',
+        };
+
+        write!(f, "\\{}", escapee)
+    }
+}
+
+fn into_class_shorthand(token: &Token) -> Option<ClassShorthand> {
+    match token {
+        Token::Literal('d') => Some(ClassShorthand::Digit),
+        Token::Literal('D') => Some(ClassShorthand::NonDigit),
+        Token::Literal('w') => Some(ClassShorthand::Word),
+        Token::Literal('W') => Some(ClassShorthand::NonWord),
+        Token::Literal('s') => Some(ClassShorthand::Whitespace),
+        Token::Literal('S') => Some(ClassShorthand::NonWhitespace),
+        _ => None,
+    }
+}
+
+/// Maps the escapee of a control character escape sequence (e.g. `\t`) to the control character.
+fn into_control_char(token: &Token) -> Option<char> {
+    match token {
+        Token::Literal('n') => Some('\n'),
+        Token::Literal('t') => Some('\t'),
+        Token::Literal('r') => Some('\r'),
+        Token::Literal('f') => Some('\x0C'),
+        Token::Literal('v') => Some('\x0B'),
+        Token::Literal('0') => Some('\0'),
+        _ => None,
+    }
+}
+
+/// Maps a control character to the escapee of its escape sequence, the inverse of
+/// `into_control_char`.
+pub fn control_char_escapee(char: char) -> Option<char> {
+    match char {
+        '\n' => Some('n'),
+        '\t' => Some('t'),
+        '\r' => Some('r'),
+        '\x0C' => Some('f'),
+        '\x0B' => Some('v'),
+        '\0' => Some('0'),
+        _ => None,
+    }
+}
+
+enum ClassAtom {
+    Char(char),
+    Shorthand(ClassShorthand),
+}
+
+/// Parses a single character or escape sequence at the start of a character class,
+/// returning it together with the number of consumed tokens.
+fn parse_class_atom(tokens: &[Token]) -> (ClassAtom, usize) {
+    let token = tokens.first().expect("Character class must not be empty");
+
+    if let Token::Backslash = token {
+        let Some(escapee) = tokens.get(1) else {
+            panic!("Incomplete escape sequence in character class");
+        };
+
+        if let Some(shorthand) = into_class_shorthand(escapee) {
+            return (ClassAtom::Shorthand(shorthand), 2);
+        }
+
+        if let Some(control_char) = into_control_char(escapee) {
+            return (ClassAtom::Char(control_char), 2);
+        }
+
+        // Any other escaped token stands for itself.
+        return (ClassAtom::Char(escapee.to_char()), 2);
+    }
+
+    // Within a character class all other tokens lose their special meaning.
+    (ClassAtom::Char(token.to_char()), 1)
+}
+
+/// Returns the index of the first `&&[` within the tokens of a character class, which
+/// starts an intersection with a nested class.
+fn find_class_intersection(tokens: &[Token]) -> Option<usize> {
+    let mut index = 0;
+
+    while index < tokens.len() {
+        match &tokens[index..] {
+            [Token::Backslash, ..] => index += 2,
+            [Token::Literal('&'), Token::Literal('&'), Token::OpenSquareBracket, ..] => {
+                return Some(index)
+            }
+            _ => index += 1,
+        }
+    }
+
+    None
+}
+
+/// Parses the tokens between the brackets of a character class into a char set.
+/// Each `&&[...]` following the members restricts them to those of the nested class.
+/// Malformed members are reported with the `index` of the class within the pattern.
+fn into_char_set(tokens: &[Token], is_negated: bool, index: usize) -> Result<CharSet, ParseError> {
+    let members_end = find_class_intersection(tokens).unwrap_or(tokens.len());
+
+    let mut chars = vec![];
+    let mut ranges = vec![];
+    let mut shorthands = vec![];
+    let mut remainder = &tokens[..members_end];
+
+    while !remainder.is_empty() {
+        let (atom, len) = parse_class_atom(remainder);
+        remainder = &remainder[len..];
+
+        let lower = match atom {
+            ClassAtom::Shorthand(shorthand) => {
+                shorthands.push(shorthand);
+                continue;
+            }
+            ClassAtom::Char(c) => c,
+        };
+
+        // A '-' only denotes a range if it is surrounded by characters,
+        // otherwise it is matched literally (e.g. `[a-]` or `[\w-]`).
+        if remainder.len() > 1 && remainder[0] == Token::Literal('-') {
+            if let (ClassAtom::Char(upper), upper_len) = parse_class_atom(&remainder[1..]) {
+                if lower > upper {
+                    return Err(ParseError::InvalidClassRange {
+                        lower,
+                        upper,
+                        index,
+                    });
+                }
+
+                ranges.push((lower, upper));
+                remainder = &remainder[1 + upper_len..];
+                continue;
+            }
+        }
+
+        chars.push(lower);
+    }
+
+    let mut set = CharSet::new(chars, ranges, shorthands, is_negated);
+    let mut remainder = &tokens[members_end..];
+
+    while !remainder.is_empty() {
+        let nested = match remainder {
+            [Token::Literal('&'), Token::Literal('&'), nested @ ..] => nested,
+            _ => return Err(ParseError::InvalidClassIntersection { index }),
+        };
+
+        let Some(end) = find_closing_bracket(nested) else {
+            return Err(ParseError::MissingClosingBracket {
+                construct: "character class intersection",
+                index,
+            });
+        };
+
+        let nested_set = match &nested[1..end] {
+            [Token::Caret, members @ ..] => into_char_set(members, true, index)?,
+            members => into_char_set(members, false, index)?,
+        };
+        set = set.with_intersection(nested_set);
+        remainder = &nested[end + 1..];
     }
+
+    Ok(set)
+}
+
+fn into_character_class(
+    tokens: &[Token],
+    is_negated: bool,
+    index: usize,
+) -> Result<Syntax, ParseError> {
+    Ok(Syntax::CharacterClass {
+        set: into_char_set(tokens, is_negated, index)?,
+    })
 }
 
 #[derive(PartialEq)]
@@ -77,71 +320,85 @@ fn is_closing_bracket(token: &Token) -> Option<BracketKind> {
 }
 
 fn find_closing_bracket(pattern: &[Token]) -> Option<usize> {
-    let first = pattern.get(0).expect("Pattern must not be empty");
+    let first = pattern.first().expect("Pattern must not be empty");
     let Some(kind) = is_opening_bracket(first) else {
         panic!("First token must be an opening bracket");
     };
 
-    let mut index = 1;
-    let mut brackets = VecDeque::from([]);
+    let mut brackets = VecDeque::from([kind]);
+    let mut is_escaped = false;
 
-    for token in pattern[1..].iter() {
-        if let Some(open_kind) = is_opening_bracket(token) {
-            brackets.push_back(open_kind);
+    for (index, token) in pattern.iter().enumerate().skip(1) {
+        if is_escaped {
+            // Escaped tokens never open or close brackets.
+            is_escaped = false;
+            continue;
         }
 
+        if let Token::Backslash = token {
+            is_escaped = true;
+            continue;
+        }
+
+        // Within a character class, only the closing square bracket is special.
+        let is_in_character_class = brackets.back() == Some(&BracketKind::SquareBracket);
+
         if let Some(close_kind) = is_closing_bracket(token) {
-            let last_open_bracket = brackets.pop_back();
+            if is_in_character_class && close_kind != BracketKind::SquareBracket {
+                continue;
+            }
 
-            if let Some(open_kind) = last_open_bracket {
-                if open_kind != close_kind {
-                    // Open/closed bracket types do not match, fail search;
-                    return None;
-                }
-            } else if close_kind == kind {
-                return Some(index);
-            } else {
+            let open_kind = brackets.pop_back()?;
+            if open_kind != close_kind {
                 // Open/closed bracket types do not match, fail search;
                 return None;
             }
-        }
 
-        index += 1;
+            if brackets.is_empty() {
+                return Some(index);
+            }
+        } else if let Some(open_kind) = is_opening_bracket(token) {
+            // Within a character class, only an unescaped `&&[` opens a nested class.
+            let is_intersection = open_kind == BracketKind::SquareBracket
+                && index >= 2
+                && pattern[index - 2..index] == [Token::Literal('&'), Token::Literal('&')]
+                && (index < 3 || pattern[index - 3] != Token::Backslash);
+
+            if !is_in_character_class || is_intersection {
+                brackets.push_back(open_kind);
+            }
+        }
     }
 
-    return None;
+    None
 }
 
 fn find_alternations(pattern: &[Token]) -> Vec<Vec<Token>> {
     let mut options: Vec<Vec<Token>> = vec![];
     let mut remainder = pattern;
-    let mut open_count = 0;
     let mut current = vec![];
 
-    while let Some(token) = remainder.get(0) {
+    while let Some(token) = remainder.first() {
         if let Token::Bar = token {
-            if open_count == 0 {
-                // Only split options on | when brackets are currently balanced
-                // (aka we are "top-level").
+            // Only a top-level | splits options, bracketed and escaped tokens
+            // are skipped as a whole below.
+            options.push(current.to_vec());
+            current = vec![];
+            remainder = &remainder[1..];
+            continue;
+        }
 
-                options.push(current.to_vec());
-                current = vec![];
-            } else {
-                current.push(token.clone());
-            }
+        let len = if let Token::Backslash = token {
+            remainder.len().min(2)
+        } else if is_opening_bracket(token).is_some() {
+            // Unbalanced brackets are reported when parsing the option.
+            find_closing_bracket(remainder).map_or(1, |end| end + 1)
         } else {
-            if let Some(_) = is_opening_bracket(token) {
-                open_count += 1;
-            }
-
-            if let Some(_) = is_closing_bracket(token) {
-                open_count -= 1;
-            }
-
-            current.push(token.clone());
-        }
+            1
+        };
 
-        remainder = &remainder[1..];
+        current.extend_from_slice(&remainder[..len]);
+        remainder = &remainder[len..];
     }
 
     // Push the last option.
@@ -150,203 +407,1196 @@ fn find_alternations(pattern: &[Token]) -> Vec<Vec<Token>> {
     options
 }
 
-fn parse_pattern_core(pattern: &[Token], capture_group_id: &mut u32) -> Vec<Syntax> {
-    let mut syntax: Vec<Syntax> = vec![];
-    let mut remainder = pattern;
-
-    if remainder.starts_with(&[Token::Caret]) {
-        syntax.push(Syntax::StartOfLineAnchor);
-        remainder = &remainder[1..];
-    }
+/// Splits the indices of the tokens of a group like [`find_alternations`] splits the
+/// tokens into options, skipping the bars between them.
+fn option_indices<'i>(options: &[Vec<Token>], indices: &'i [usize]) -> Vec<&'i [usize]> {
+    let mut option_start = 0;
 
-    while remainder.len() > 0 {
-        let prev_len = remainder.len();
+    options
+        .iter()
+        .map(|option| {
+            let option_indices = &indices[option_start..option_start + option.len()];
+            option_start += option.len() + 1;
+            option_indices
+        })
+        .collect()
+}
 
-        if remainder.starts_with(&[Token::OpenSquareBracket]) {
-            let Some(end) = find_closing_bracket(remainder)
-            else {
-                panic!("Incomplete character class (missing closing bracket)");
-            };
+/// Parses the alternation options of a group, where `indices` holds the index of the
+/// char of each token of the group contents within the pattern.
+fn parse_options(
+    group: &[Token],
+    indices: &[usize],
+    capture_group_id: &mut u32,
+) -> Result<Vec<Vec<Syntax>>, ParseError> {
+    let options = find_alternations(group);
 
-            let character_class = &remainder[1..end];
-            if character_class.starts_with(&[Token::Caret]) {
-                let negated_character_class = &character_class[1..];
+    options
+        .iter()
+        .zip(option_indices(&options, indices))
+        .map(|(option, indices)| parse_pattern_core(option, indices, capture_group_id))
+        .collect()
+}
 
-                syntax.push(into_character_class(negated_character_class, true));
-                remainder = &remainder[end + 1..];
-            } else {
-                syntax.push(into_character_class(character_class, false));
-                remainder = &remainder[end + 1..];
-            }
-        } else if remainder.starts_with(&[Token::OpenBracket]) {
-            let Some(end) = find_closing_bracket(remainder) else {
-                panic!("Incomplete alternation (missing closing bracket)");
-            };
+/// Parses the optional name at the start of a capture group, `?<name>` or `?P<name>`,
+/// returning the name together with the number of consumed tokens. Malformed names are
+/// reported with the `index` of the group within the pattern.
+fn parse_group_name(group: &[Token], index: usize) -> Result<(Option<String>, usize), ParseError> {
+    let prefix_len = if group.starts_with(&[Token::QuestionMark, Token::Literal('<')]) {
+        2
+    } else if group.starts_with(&[
+        Token::QuestionMark,
+        Token::Literal('P'),
+        Token::Literal('<'),
+    ]) {
+        3
+    } else {
+        return Ok((None, 0));
+    };
 
-            *capture_group_id += 1;
-            let id = *capture_group_id;
-            let options = find_alternations(&remainder[1..end])
-                .iter()
-                .map(|o| parse_pattern_core(o, capture_group_id))
-                .collect();
-
-            syntax.push(Syntax::CaptureGroup {
-                options: options,
-                id: id,
-            });
-            remainder = &remainder[end + 1..];
-        } else if remainder.starts_with(&[Token::Backslash, Token::Backslash]) {
-            syntax.push(Syntax::Literal { char: '\\' });
-            remainder = &remainder[2..];
-        } else if remainder.starts_with(&[Token::Backslash, Token::Literal('d')]) {
-            syntax.push(Syntax::Digit);
-            remainder = &remainder[2..];
-        } else if remainder.starts_with(&[Token::Backslash, Token::Literal('w')]) {
-            syntax.push(Syntax::Word);
-            remainder = &remainder[2..];
-        } else if remainder.starts_with(&[Token::Backslash]) {
-            let Some(escapee) = remainder.get(1) else {
-                panic!("Incomplete escape sequence");
-            };
+    let Some(name_len) = group[prefix_len..]
+        .iter()
+        .position(|t| *t == Token::Literal('>'))
+    else {
+        return Err(ParseError::IncompleteGroupName { index });
+    };
 
-            if let Token::Literal(l) = escapee {
-                if let Some(d) = char::to_digit(*l, 10) {
-                    syntax.push(Syntax::BackReference { id: d });
-                    remainder = &remainder[2..];
-                } else {
-                    panic!("Unrecognized escape sequence '\\{}'", l);
-                }
-            } else {
-                panic!("Unrecognized token type following backslash");
-            }
-        } else if remainder.starts_with(&[Token::Dot]) {
-            syntax.push(Syntax::Wildcard);
-            remainder = &remainder[1..];
-        } else if remainder.starts_with(&[Token::Dollar]) {
-            syntax.push(Syntax::EndOfLineAnchor);
-            remainder = &remainder[1..];
-        } else if remainder.starts_with(&[Token::Plus]) {
-            let contained_syntax = syntax
-                .pop()
-                .expect("The one or more modifier can only appear after another token");
-            syntax.push(Syntax::OneOrMore {
-                syntax: Box::from(contained_syntax),
-            });
-            remainder = &remainder[1..];
-        } else if remainder.starts_with(&[Token::QuestionMark]) {
-            let contained_syntax = syntax
-                .pop()
-                .expect("The zero or more modifier can only appear after another token");
-            syntax.push(Syntax::ZeroOrOne {
-                syntax: Box::from(contained_syntax),
-            });
-            remainder = &remainder[1..];
-        } else if let Some(Token::Literal(c)) = remainder.get(0) {
-            syntax.push(Syntax::Literal { char: *c });
-            remainder = &remainder[1..];
-        } else {
-            panic!("Malformed pattern, cannot parse token");
-        }
+    let name: String = group[prefix_len..prefix_len + name_len]
+        .iter()
+        .map(Token::to_char)
+        .collect();
 
-        // Sanity check to ensure that progress is made.
-        assert!(
-            remainder.len() < prev_len,
-            "Must consume at least one pattern char"
-        )
+    let is_valid = name.chars().next().is_some_and(|c| !c.is_ascii_digit())
+        && name.chars().all(patterns::is_word);
+    if !is_valid {
+        return Err(ParseError::InvalidGroupName { name, index });
     }
 
-    syntax
+    Ok((Some(name), prefix_len + name_len + 1))
 }
 
-pub fn parse_pattern(pattern: &[Token]) -> Vec<Syntax> {
-    let mut capture_group_id = 0;
-    parse_pattern_core(pattern, &mut capture_group_id)
+/// Returns whether the tokens start a negated lookahead `(?!` or a lookahead `(?=`,
+/// or `None` if they start neither.
+fn parse_lookahead_prefix(tokens: &[Token]) -> Option<bool> {
+    match tokens {
+        [Token::OpenBracket, Token::QuestionMark, Token::Literal('!'), ..] => Some(true),
+        [Token::OpenBracket, Token::QuestionMark, Token::Literal('='), ..] => Some(false),
+        _ => None,
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    fn assert_single<T: std::fmt::Debug + PartialEq>(items: Vec<T>, expected: T) {
-        assert_eq!(
-            1,
-            items.len(),
-            "Collection must contain exactly one element"
-        );
-        assert_eq!(expected, items[0]);
+/// Returns whether the tokens start a negated lookbehind `(?<!` or a lookbehind `(?<=`,
+/// or `None` if they start neither.
+fn parse_lookbehind_prefix(tokens: &[Token]) -> Option<bool> {
+    match tokens {
+        [Token::OpenBracket, Token::QuestionMark, Token::Literal('<'), Token::Literal('!'), ..] => {
+            Some(true)
+        }
+        [Token::OpenBracket, Token::QuestionMark, Token::Literal('<'), Token::Literal('='), ..] => {
+            Some(false)
+        }
+        _ => None,
     }
+}
 
-    #[test]
-    fn test_parse_pattern_literal() {
-        assert_single(
-            parse_pattern(&[Token::Literal('a')]),
-            Syntax::Literal { char: 'a' },
-        );
+/// Parses the bounds of a repetition quantifier at the start of the tokens, `{n}`,
+/// `{n,}`, `{n,m}` or `{,m}`, returning the bounds together with the number of consumed
+/// tokens. A missing lower bound is 0 and a missing upper bound is unlimited, so `{,}`
+/// is the same as `{0,}`. Returns `None` if the tokens do not form a quantifier, in which
+/// case the opening brace is matched literally.
+fn parse_repetition(tokens: &[Token]) -> Option<(usize, Option<usize>, usize)> {
+    if tokens.first() != Some(&Token::OpenBrace) {
+        return None;
     }
 
-    #[test]
-    fn test_parse_pattern_digit() {
-        assert_single(
-            parse_pattern(&[Token::Backslash, Token::Literal('d')]),
-            Syntax::Digit,
-        );
-    }
+    let end = tokens.iter().position(|t| *t == Token::Literal('}'))?;
+    let contents: String = tokens[1..end].iter().map(Token::to_char).collect();
 
-    #[test]
-    fn test_parse_pattern_word() {
-        assert_single(
-            parse_pattern(&[Token::Backslash, Token::Literal('w')]),
-            Syntax::Word,
-        );
-    }
+    let parse_bound = |bound: &str| -> Option<Option<usize>> {
+        if bound.is_empty() {
+            Some(None)
+        } else if bound.chars().all(|c| c.is_ascii_digit()) {
+            Some(Some(bound.parse().ok()?))
+        } else {
+            None
+        }
+    };
 
-    #[test]
-    fn test_parse_pattern_character_class() {
-        assert_single(
-            parse_pattern(&[
-                Token::OpenSquareBracket,
-                Token::Literal('a'),
-                Token::Literal('b'),
-                Token::Literal('c'),
-                Token::CloseSquareBracket,
-            ]),
-            Syntax::CharacterClass {
-                chars: vec!['a', 'b', 'c'],
-                is_negated: false,
-            },
-        )
-    }
+    let (min, max) = match contents.split_once(',') {
+        Some((min, max)) => (parse_bound(min)?.unwrap_or(0), parse_bound(max)?),
+        None => {
+            // A single count is required, `{}` is not a quantifier.
+            let count = parse_bound(&contents)??;
+            (count, Some(count))
+        }
+    };
 
-    #[test]
-    fn test_parse_pattern_negated_character_class() {
-        assert_single(
-            parse_pattern(&[
-                Token::OpenSquareBracket,
-                Token::Caret,
-                Token::Literal('a'),
-                Token::Literal('b'),
-                Token::Literal('c'),
-                Token::CloseSquareBracket,
-            ]),
-            Syntax::CharacterClass {
-                chars: vec!['a', 'b', 'c'],
-                is_negated: true,
-            },
-        )
-    }
+    Some((min, max, end + 1))
+}
 
-    #[test]
-    fn test_parse_pattern_start_of_line_anchor() {
-        assert_single(parse_pattern(&[Token::Caret]), Syntax::StartOfLineAnchor);
-    }
+/// Returns how deeply the groups of the pattern are nested, 0 if there are no groups.
+/// Works on the tokens, so the depth is known before recursing into the groups to parse them.
+pub fn group_depth(pattern: &[Token]) -> usize {
+    let mut depth: usize = 0;
+    let mut max_depth = 0;
+    let mut is_escaped = false;
+    let mut is_in_character_class = false;
 
-    #[test]
-    fn test_parse_pattern_end_of_line_anchor() {
-        assert_single(parse_pattern(&[Token::Dollar]), Syntax::EndOfLineAnchor);
+    for token in pattern {
+        if is_escaped {
+            is_escaped = false;
+            continue;
+        }
+
+        match token {
+            Token::Backslash => is_escaped = true,
+            Token::OpenSquareBracket => is_in_character_class = true,
+            Token::CloseSquareBracket => is_in_character_class = false,
+            Token::OpenBracket if !is_in_character_class => {
+                depth += 1;
+                max_depth = max_depth.max(depth);
+            }
+            Token::CloseBracket if !is_in_character_class => depth = depth.saturating_sub(1),
+            _ => {}
+        }
     }
 
-    #[test]
-    fn test_parse_pattern_one_or_more_modifier() {
+    max_depth
+}
+
+/// Returns the names of all capture groups ordered by their id,
+/// with `None` for groups without a name.
+pub fn capture_names(syntax: &[Syntax]) -> Vec<Option<String>> {
+    fn collect(syntax: &[Syntax], names: &mut Vec<(u32, Option<String>)>) {
+        for item in syntax {
+            match item {
+                Syntax::CaptureGroup { options, id, name } => {
+                    names.push((*id, name.clone()));
+                    options.iter().for_each(|o| collect(o, names));
+                }
+                Syntax::AtomicGroup { options }
+                | Syntax::Lookahead { options, .. }
+                | Syntax::Lookbehind { options, .. } => {
+                    options.iter().for_each(|o| collect(o, names));
+                }
+                Syntax::OneOrMore { syntax }
+                | Syntax::ZeroOrMore { syntax }
+                | Syntax::ZeroOrOne { syntax }
+                | Syntax::Repeat { syntax, .. } => {
+                    collect(std::slice::from_ref(syntax), names);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut names = vec![];
+    collect(syntax, &mut names);
+    names.sort_by_key(|(id, _)| *id);
+
+    names.into_iter().map(|(_, name)| name).collect()
+}
+
+/// Returns true if any of the syntax items is a backreference, which needs the texts
+/// captured by the groups while matching.
+pub fn has_back_references(syntax: &[Syntax]) -> bool {
+    syntax.iter().any(|item| match item {
+        Syntax::BackReference { .. } => true,
+        Syntax::CaptureGroup { options, .. }
+        | Syntax::AtomicGroup { options }
+        | Syntax::Lookahead { options, .. }
+        | Syntax::Lookbehind { options, .. } => options.iter().any(|o| has_back_references(o)),
+        Syntax::OneOrMore { syntax }
+        | Syntax::ZeroOrMore { syntax }
+        | Syntax::ZeroOrOne { syntax }
+        | Syntax::Repeat { syntax, .. } => has_back_references(std::slice::from_ref(syntax)),
+        _ => false,
+    })
+}
+
+/// Returns true if any of the syntax items is a capture group, including those nested
+/// within other items.
+pub fn has_capture_groups(syntax: &[Syntax]) -> bool {
+    syntax.iter().any(|item| match item {
+        Syntax::CaptureGroup { .. } => true,
+        Syntax::AtomicGroup { options }
+        | Syntax::Lookahead { options, .. }
+        | Syntax::Lookbehind { options, .. } => options.iter().any(|o| has_capture_groups(o)),
+        Syntax::OneOrMore { syntax }
+        | Syntax::ZeroOrMore { syntax }
+        | Syntax::ZeroOrOne { syntax }
+        | Syntax::Repeat { syntax, .. } => has_capture_groups(std::slice::from_ref(syntax)),
+        _ => false,
+    })
+}
+
+/// Returns the minimum number of chars matched by the syntax items.
+/// Backreferences may match the empty text, so they count as 0.
+pub fn min_len(syntax: &[Syntax]) -> usize {
+    syntax.iter().map(|item| match item {
+        Syntax::Literal { .. }
+        | Syntax::Digit
+        | Syntax::Word
+        | Syntax::Wildcard
+        | Syntax::CharacterClass { .. }
+        | Syntax::CaseInsensitive { .. } => 1,
+        Syntax::LiteralRun { text } => text.chars().count(),
+        Syntax::OneOrMore { syntax } => min_len(std::slice::from_ref(syntax)),
+        Syntax::Repeat { syntax, min, .. } => min * min_len(std::slice::from_ref(syntax)),
+        Syntax::CaptureGroup { options, .. } | Syntax::AtomicGroup { options } => {
+            options.iter().map(|o| min_len(o)).min().unwrap_or(0)
+        }
+        _ => 0,
+    })
+    .sum()
+}
+
+/// Returns the maximum number of chars matched by the syntax items, or `None` if
+/// there is no limit or it is unknown, like for backreferences.
+pub fn max_len(syntax: &[Syntax]) -> Option<usize> {
+    syntax.iter().map(|item| match item {
+        Syntax::Literal { .. }
+        | Syntax::Digit
+        | Syntax::Word
+        | Syntax::Wildcard
+        | Syntax::CharacterClass { .. }
+        | Syntax::CaseInsensitive { .. } => Some(1),
+        Syntax::LiteralRun { text } => Some(text.chars().count()),
+        Syntax::ZeroOrOne { syntax } => max_len(std::slice::from_ref(syntax)),
+        Syntax::Repeat { syntax, max, .. } => {
+            max.and_then(|max| max.checked_mul(max_len(std::slice::from_ref(syntax))?))
+        }
+        Syntax::CaptureGroup { options, .. } | Syntax::AtomicGroup { options } => options
+            .iter()
+            .map(|o| max_len(o))
+            .try_fold(0, |max, len| Some(max.max(len?))),
+        Syntax::OneOrMore { .. } | Syntax::ZeroOrMore { .. } | Syntax::BackReference { .. } => None,
+        _ => Some(0),
+    })
+    .try_fold(0, |sum: usize, len| sum.checked_add(len?))
+}
+
+/// Returns true if the tokens start with a comment `(?#...)`, which produces no syntax.
+/// Brackets within the comment are skipped like those of a group, so they have to be
+/// balanced.
+fn is_comment_start(tokens: &[Token]) -> bool {
+    tokens.starts_with(&[Token::OpenBracket, Token::QuestionMark, Token::Literal('#')])
+}
+
+/// Checks that the pattern does not use a group construct beyond named groups `(?<name>`
+/// and `(?P<name>`, comments `(?#...)`, atomic groups `(?>`, lookaheads and fixed-length lookbehinds. Other
+/// constructs starting with `(?`, like conditionals, are valid Perl-compatible syntax that
+/// would otherwise be misparsed, so they are reported with the index of their opening
+/// bracket, as are lookbehinds that may match texts of different lengths. `indices` holds
+/// the index of the char of each token within the pattern.
+pub fn check_supported(tokens: &[Token], indices: &[usize]) -> Result<(), ParseError> {
+    let mut index = 0;
+
+    while index < tokens.len() {
+        match &tokens[index] {
+            Token::Backslash => index += 1,
+            Token::OpenSquareBracket => {
+                // Brackets within a class are literals, a class without end is
+                // reported when parsing.
+                let Some(end) = find_closing_bracket(&tokens[index..]) else {
+                    return Ok(());
+                };
+
+                index += end;
+            }
+            Token::OpenBracket if is_comment_start(&tokens[index..]) => {
+                // Comments are discarded, whatever they contain. A comment without end
+                // is reported when parsing.
+                let Some(end) = find_closing_bracket(&tokens[index..]) else {
+                    return Ok(());
+                };
+
+                index += end;
+            }
+            Token::OpenBracket if tokens.get(index + 1) == Some(&Token::QuestionMark) => {
+                if parse_lookbehind_prefix(&tokens[index..]).is_some() {
+                    let Some(end) = find_closing_bracket(&tokens[index..]) else {
+                        return Ok(());
+                    };
+
+                    // Every option has to match texts of the same length.
+                    let options = find_alternations(&tokens[index + 4..index + end]);
+                    let mut lens = options
+                        .iter()
+                        .zip(option_indices(&options, &indices[index + 4..index + end]))
+                        .map(|(option, indices)| {
+                            let option = parse_tokens(option, indices)?;
+                            Ok((Some(min_len(&option)), max_len(&option)))
+                        })
+                        .collect::<Result<Vec<_>, ParseError>>()?;
+                    lens.dedup();
+
+                    if lens.len() != 1 || lens[0].0 != lens[0].1 {
+                        return Err(ParseError::VariableLengthLookbehind {
+                            index: indices[index],
+                        });
+                    }
+                }
+
+                let feature = match &tokens[index + 2..] {
+                    [Token::Literal('<' | '>'), ..] => None,
+                    [Token::Literal('P'), Token::Literal('<'), ..] => None,
+                    [Token::Literal('=' | '!'), ..] => None,
+                    [Token::Literal(':'), ..] => Some("non-capturing group"),
+                    [Token::OpenBracket, ..] => Some("conditional"),
+                    _ => Some("inline flag group"),
+                };
+
+                if let Some(feature) = feature {
+                    return Err(ParseError::UnsupportedFeature {
+                        feature,
+                        index: indices[index],
+                    });
+                }
+            }
+            _ => {}
+        }
+
+        index += 1;
+    }
+
+    Ok(())
+}
+
+/// Checks that every backreference refers to a capture group closed before it.
+/// References to groups that do not exist, contain the reference or follow it
+/// could never match.
+pub fn check_back_references(syntax: &[Syntax]) -> Result<(), ParseError> {
+    fn check(syntax: &[Syntax], closed: &mut Vec<u32>, group_count: u32) -> Result<(), ParseError> {
+        for item in syntax {
+            match item {
+                Syntax::BackReference { id } if *id > group_count => {
+                    return Err(ParseError::UnknownGroupReference { id: *id });
+                }
+                Syntax::BackReference { id } if !closed.contains(id) => {
+                    return Err(ParseError::OpenGroupReference { id: *id });
+                }
+                Syntax::CaptureGroup { options, id, .. } => {
+                    for option in options {
+                        check(option, closed, group_count)?;
+                    }
+                    closed.push(*id);
+                }
+                Syntax::AtomicGroup { options }
+                | Syntax::Lookahead { options, .. }
+                | Syntax::Lookbehind { options, .. } => {
+                    for option in options {
+                        check(option, closed, group_count)?;
+                    }
+                }
+                Syntax::OneOrMore { syntax }
+                | Syntax::ZeroOrMore { syntax }
+                | Syntax::ZeroOrOne { syntax }
+                | Syntax::Repeat { syntax, .. } => {
+                    check(std::slice::from_ref(syntax), closed, group_count)?;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    let group_count = capture_names(syntax).len() as u32;
+    check(syntax, &mut vec![], group_count)
+}
+
+/// Merges runs of consecutive literals into a single [`Syntax::LiteralRun`], which is
+/// matched at once instead of char by char. Quantified literals are not merged, as the
+/// quantifier only applies to the last char, and neither are literals of different
+/// alternation options.
+pub fn merge_literals(syntax: Vec<Syntax>) -> Vec<Syntax> {
+    let mut merged = Vec::with_capacity(syntax.len());
+    let mut run = String::new();
+
+    let flush = |run: &mut String, merged: &mut Vec<Syntax>| match run.chars().count() {
+        0 => {}
+        1 => merged.push(Syntax::Literal {
+            char: run.remove(0),
+        }),
+        _ => merged.push(Syntax::LiteralRun {
+            text: std::mem::take(run),
+        }),
+    };
+
+    for item in syntax {
+        match item {
+            Syntax::Literal { char } => run.push(char),
+            Syntax::LiteralRun { text } => run.push_str(&text),
+            Syntax::CaptureGroup { options, id, name } => {
+                flush(&mut run, &mut merged);
+                merged.push(Syntax::CaptureGroup {
+                    options: options.into_iter().map(merge_literals).collect(),
+                    id,
+                    name,
+                });
+            }
+            Syntax::AtomicGroup { options } => {
+                flush(&mut run, &mut merged);
+                merged.push(Syntax::AtomicGroup {
+                    options: options.into_iter().map(merge_literals).collect(),
+                });
+            }
+            Syntax::Lookahead {
+                options,
+                is_negated,
+            } => {
+                flush(&mut run, &mut merged);
+                merged.push(Syntax::Lookahead {
+                    options: options.into_iter().map(merge_literals).collect(),
+                    is_negated,
+                });
+            }
+            Syntax::Lookbehind {
+                options,
+                is_negated,
+                len,
+            } => {
+                flush(&mut run, &mut merged);
+                merged.push(Syntax::Lookbehind {
+                    options: options.into_iter().map(merge_literals).collect(),
+                    is_negated,
+                    len,
+                });
+            }
+            other => {
+                flush(&mut run, &mut merged);
+                merged.push(other);
+            }
+        }
+    }
+
+    flush(&mut run, &mut merged);
+    merged
+}
+
+/// Applies `f` to every syntax item that does not contain other items, descending into
+/// groups and quantifiers. Literal runs are split up before and merged again afterwards,
+/// so `f` sees every literal on its own.
+fn map_items(syntax: Vec<Syntax>, f: &impl Fn(Syntax) -> Syntax) -> Vec<Syntax> {
+    fn map_item(item: Syntax, f: &impl Fn(Syntax) -> Syntax) -> Syntax {
+        match item {
+            Syntax::OneOrMore { syntax } => Syntax::OneOrMore {
+                syntax: Box::new(map_item(*syntax, f)),
+            },
+            Syntax::ZeroOrMore { syntax } => Syntax::ZeroOrMore {
+                syntax: Box::new(map_item(*syntax, f)),
+            },
+            Syntax::ZeroOrOne { syntax } => Syntax::ZeroOrOne {
+                syntax: Box::new(map_item(*syntax, f)),
+            },
+            Syntax::Repeat { syntax, min, max } => Syntax::Repeat {
+                syntax: Box::new(map_item(*syntax, f)),
+                min,
+                max,
+            },
+            Syntax::CaptureGroup { options, id, name } => Syntax::CaptureGroup {
+                options: options.into_iter().map(|o| map_items(o, f)).collect(),
+                id,
+                name,
+            },
+            Syntax::AtomicGroup { options } => Syntax::AtomicGroup {
+                options: options.into_iter().map(|o| map_items(o, f)).collect(),
+            },
+            Syntax::Lookahead {
+                options,
+                is_negated,
+            } => Syntax::Lookahead {
+                options: options.into_iter().map(|o| map_items(o, f)).collect(),
+                is_negated,
+            },
+            Syntax::Lookbehind {
+                options,
+                is_negated,
+                len,
+            } => Syntax::Lookbehind {
+                options: options.into_iter().map(|o| map_items(o, f)).collect(),
+                is_negated,
+                len,
+            },
+            other => f(other),
+        }
+    }
+
+    let items = syntax.into_iter().flat_map(|item| match item {
+        Syntax::LiteralRun { text } => text.chars().map(|char| Syntax::Literal { char }).collect(),
+        other => vec![other],
+    });
+
+    merge_literals(items.map(|item| map_item(item, f)).collect())
+}
+
+/// Makes the syntax items match letters regardless of their case.
+/// Backreferences still require the exact text captured by their group.
+pub fn ignore_case(syntax: Vec<Syntax>) -> Vec<Syntax> {
+    map_items(syntax, &|item| match item {
+        Syntax::Literal { .. } | Syntax::CharacterClass { .. } => Syntax::CaseInsensitive {
+            syntax: Box::new(item),
+        },
+        other => other,
+    })
+}
+
+/// Makes `^` and `$` match at the start and end of every line within the text,
+/// instead of only at the start and end of the text.
+pub fn multiline(syntax: Vec<Syntax>) -> Vec<Syntax> {
+    map_items(syntax, &|item| match item {
+        Syntax::StartOfLineAnchor => Syntax::StartOfAnyLineAnchor,
+        Syntax::EndOfLineAnchor => Syntax::EndOfAnyLineAnchor,
+        other => other,
+    })
+}
+
+/// Makes `.` match newlines as well.
+pub fn dot_matches_newline(syntax: Vec<Syntax>) -> Vec<Syntax> {
+    map_items(syntax, &|item| match item {
+        // A negated empty class matches every char.
+        Syntax::Wildcard => Syntax::CharacterClass {
+            set: CharSet::new(vec![], vec![], vec![], true),
+        },
+        other => other,
+    })
+}
+
+/// Makes `\d`, `\w` and the shorthand classes within character classes match with their
+/// Unicode instead of their ASCII definitions.
+pub fn unicode(syntax: Vec<Syntax>) -> Vec<Syntax> {
+    let shorthand_class = |shorthand| Syntax::CharacterClass {
+        set: CharSet::new(vec![], vec![], vec![shorthand], false).with_unicode_shorthands(),
+    };
+
+    map_items(syntax, &|item| match item {
+        Syntax::Digit => shorthand_class(ClassShorthand::Digit),
+        Syntax::Word => shorthand_class(ClassShorthand::Word),
+        Syntax::CharacterClass { set } => Syntax::CharacterClass {
+            set: set.with_unicode_shorthands(),
+        },
+        other => other,
+    })
+}
+
+/// Renders the syntax items as an indented tree, one item per line. Groups list each
+/// of their options as a nested `Option`, quantifiers nest the quantified item and all
+/// other items are rendered with their `Debug` representation.
+pub fn debug_tree(syntax: &[Syntax]) -> String {
+    fn render(syntax: &[Syntax], depth: usize, lines: &mut Vec<String>) {
+        for item in syntax {
+            let indent = "  ".repeat(depth);
+
+            match item {
+                Syntax::CaptureGroup { options, id, name } => {
+                    match name {
+                        Some(name) => lines.push(format!("{}CaptureGroup {} <{}>", indent, id, name)),
+                        None => lines.push(format!("{}CaptureGroup {}", indent, id)),
+                    }
+                    render_options(options, depth + 1, lines);
+                }
+                Syntax::AtomicGroup { options } => {
+                    lines.push(format!("{}AtomicGroup", indent));
+                    render_options(options, depth + 1, lines);
+                }
+                Syntax::Lookahead {
+                    options,
+                    is_negated,
+                } => {
+                    match is_negated {
+                        true => lines.push(format!("{}NegativeLookahead", indent)),
+                        false => lines.push(format!("{}Lookahead", indent)),
+                    }
+                    render_options(options, depth + 1, lines);
+                }
+                Syntax::Lookbehind {
+                    options,
+                    is_negated,
+                    len,
+                } => {
+                    match is_negated {
+                        true => lines.push(format!("{}NegativeLookbehind {}", indent, len)),
+                        false => lines.push(format!("{}Lookbehind {}", indent, len)),
+                    }
+                    render_options(options, depth + 1, lines);
+                }
+                Syntax::OneOrMore { syntax } => {
+                    lines.push(format!("{}OneOrMore", indent));
+                    render(std::slice::from_ref(syntax), depth + 1, lines);
+                }
+                Syntax::ZeroOrMore { syntax } => {
+                    lines.push(format!("{}ZeroOrMore", indent));
+                    render(std::slice::from_ref(syntax), depth + 1, lines);
+                }
+                Syntax::ZeroOrOne { syntax } => {
+                    lines.push(format!("{}ZeroOrOne", indent));
+                    render(std::slice::from_ref(syntax), depth + 1, lines);
+                }
+                Syntax::Repeat { syntax, min, max } => {
+                    match max {
+                        Some(max) => lines.push(format!("{}Repeat {{{},{}}}", indent, min, max)),
+                        None => lines.push(format!("{}Repeat {{{},}}", indent, min)),
+                    }
+                    render(std::slice::from_ref(syntax), depth + 1, lines);
+                }
+                other => lines.push(format!("{}{:?}", indent, other)),
+            }
+        }
+    }
+
+    fn render_options(options: &[Vec<Syntax>], depth: usize, lines: &mut Vec<String>) {
+        for option in options {
+            lines.push(format!("{}Option", "  ".repeat(depth)));
+            render(option, depth + 1, lines);
+        }
+    }
+
+    let mut lines = vec![];
+    render(syntax, 0, &mut lines);
+
+    lines.join("\n")
+}
+
+/// Renders the syntax items as a pattern, the inverse of parsing. Parsing the rendered
+/// pattern again yields the same syntax items.
+pub fn to_pattern(syntax: &[Syntax]) -> String {
+    syntax.iter().map(Syntax::to_string).collect()
+}
+
+impl fmt::Display for Syntax {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn write_options(f: &mut fmt::Formatter, options: &[Vec<Syntax>]) -> fmt::Result {
+            let options: Vec<String> = options.iter().map(|o| to_pattern(o)).collect();
+            write!(f, "{})", options.join("|"))
+        }
+
+        match self {
+            Syntax::Literal { char: '\\' } => write!(f, "\\\\"),
+            Syntax::Literal { char } => match control_char_escapee(*char) {
+                Some(escapee) => write!(f, "\\{}", escapee),
+                // Special chars cannot be escaped outside of a class, so they are
+                // rendered as a class matching only the char itself. A brace is only
+                // special if it starts a quantifier, which it did not when parsed.
+                None if *char != '{'
+                    && tokenize_pattern(&char.to_string()) != [Token::Literal(*char)] =>
+                {
+                    write!(f, "[\\{}]", char)
+                }
+                None => write!(f, "{}", char),
+            },
+            Syntax::LiteralRun { text } => text
+                .chars()
+                .try_for_each(|char| write!(f, "{}", Syntax::Literal { char })),
+            Syntax::Digit => write!(f, "\\d"),
+            Syntax::Word => write!(f, "\\w"),
+            Syntax::Wildcard => write!(f, "."),
+            Syntax::CharacterClass { set } => write!(f, "{}", set),
+            // Case insensitivity is an option of the compiled regex, not part of the pattern.
+            Syntax::CaseInsensitive { syntax } => write!(f, "{}", syntax),
+            Syntax::StartOfLineAnchor | Syntax::StartOfAnyLineAnchor => write!(f, "^"),
+            Syntax::EndOfLineAnchor | Syntax::EndOfAnyLineAnchor => write!(f, "$"),
+            Syntax::PreviousMatchEnd => write!(f, "\\G"),
+            Syntax::OneOrMore { syntax } => write!(f, "{}+", syntax),
+            Syntax::ZeroOrMore { syntax } => write!(f, "{}*", syntax),
+            Syntax::ZeroOrOne { syntax } => write!(f, "{}?", syntax),
+            Syntax::Repeat { syntax, min, max } => match max {
+                Some(max) if max == min => write!(f, "{}{{{}}}", syntax, min),
+                Some(max) => write!(f, "{}{{{},{}}}", syntax, min, max),
+                None => write!(f, "{}{{{},}}", syntax, min),
+            },
+            Syntax::CaptureGroup { options, name, .. } => {
+                match name {
+                    Some(name) => write!(f, "(?<{}>", name)?,
+                    None => write!(f, "(")?,
+                }
+                write_options(f, options)
+            }
+            Syntax::AtomicGroup { options } => {
+                write!(f, "(?>")?;
+                write_options(f, options)
+            }
+            Syntax::Lookahead {
+                options,
+                is_negated,
+            } => {
+                write!(f, "(?{}", if *is_negated { '!' } else { '=' })?;
+                write_options(f, options)
+            }
+            Syntax::Lookbehind {
+                options,
+                is_negated,
+                ..
+            } => {
+                write!(f, "(?<{}", if *is_negated { '!' } else { '=' })?;
+                write_options(f, options)
+            }
+            Syntax::BackReference { id } => write!(f, "\\{}", id),
+        }
+    }
+}
+
+/// Parses the tokens into syntax items.
+///
+/// Capture groups are numbered by the position of their opening bracket, from left
+/// to right, starting at 1. `capture_group_id` holds the last id handed out and is
+/// shared with nested calls, so groups inside alternation options are numbered after
+/// their enclosing group and before any group following it, e.g. `((a)(b)|(c))(d)`
+/// numbers the groups 1 to 5 in the order they are opened.
+///
+/// `indices` holds the index of the char of each token within the pattern, which is
+/// reported for malformed syntax.
+fn parse_pattern_core(
+    pattern: &[Token],
+    indices: &[usize],
+    capture_group_id: &mut u32,
+) -> Result<Vec<Syntax>, ParseError> {
+    let mut syntax: Vec<Syntax> = vec![];
+    let mut remainder = pattern;
+
+    if remainder.starts_with(&[Token::Caret]) {
+        syntax.push(Syntax::StartOfLineAnchor);
+        remainder = &remainder[1..];
+    }
+
+    while !remainder.is_empty() {
+        let prev_len = remainder.len();
+        let remainder_indices = &indices[pattern.len() - remainder.len()..];
+        let index = remainder_indices[0];
+
+        if remainder.starts_with(&[Token::OpenSquareBracket]) {
+            let Some(end) = find_closing_bracket(remainder) else {
+                return Err(ParseError::MissingClosingBracket {
+                    construct: "character class",
+                    index,
+                });
+            };
+
+            let character_class = &remainder[1..end];
+            if character_class.starts_with(&[Token::Caret]) {
+                let negated_character_class = &character_class[1..];
+
+                syntax.push(into_character_class(negated_character_class, true, index)?);
+                remainder = &remainder[end + 1..];
+            } else {
+                syntax.push(into_character_class(character_class, false, index)?);
+                remainder = &remainder[end + 1..];
+            }
+        } else if is_comment_start(remainder) {
+            let Some(end) = find_closing_bracket(remainder) else {
+                return Err(ParseError::MissingClosingBracket {
+                    construct: "comment",
+                    index,
+                });
+            };
+
+            remainder = &remainder[end + 1..];
+        } else if remainder.starts_with(&[
+            Token::OpenBracket,
+            Token::QuestionMark,
+            Token::Literal('>'),
+        ]) {
+            let Some(end) = find_closing_bracket(remainder) else {
+                return Err(ParseError::MissingClosingBracket {
+                    construct: "atomic group",
+                    index,
+                });
+            };
+
+            let options = parse_options(
+                &remainder[3..end],
+                &remainder_indices[3..end],
+                capture_group_id,
+            )?;
+
+            syntax.push(Syntax::AtomicGroup { options });
+            remainder = &remainder[end + 1..];
+        } else if let Some(is_negated) = parse_lookahead_prefix(remainder) {
+            let Some(end) = find_closing_bracket(remainder) else {
+                return Err(ParseError::MissingClosingBracket {
+                    construct: "lookahead",
+                    index,
+                });
+            };
+
+            let options = parse_options(
+                &remainder[3..end],
+                &remainder_indices[3..end],
+                capture_group_id,
+            )?;
+
+            syntax.push(Syntax::Lookahead {
+                options,
+                is_negated,
+            });
+            remainder = &remainder[end + 1..];
+        } else if let Some(is_negated) = parse_lookbehind_prefix(remainder) {
+            let Some(end) = find_closing_bracket(remainder) else {
+                return Err(ParseError::MissingClosingBracket {
+                    construct: "lookbehind",
+                    index,
+                });
+            };
+
+            // The length is only fixed for patterns passing `check_supported`,
+            // otherwise only the shortest texts are matched.
+            let options = parse_options(
+                &remainder[4..end],
+                &remainder_indices[4..end],
+                capture_group_id,
+            )?;
+            let len = options.iter().map(|o| min_len(o)).min().unwrap_or(0);
+
+            syntax.push(Syntax::Lookbehind {
+                options,
+                is_negated,
+                len,
+            });
+            remainder = &remainder[end + 1..];
+        } else if remainder.starts_with(&[Token::OpenBracket]) {
+            let Some(end) = find_closing_bracket(remainder) else {
+                return Err(ParseError::MissingClosingBracket {
+                    construct: "alternation",
+                    index,
+                });
+            };
+
+            let (name, name_len) = parse_group_name(&remainder[1..end], index)?;
+
+            *capture_group_id += 1;
+            let id = *capture_group_id;
+            let options = parse_options(
+                &remainder[1 + name_len..end],
+                &remainder_indices[1 + name_len..end],
+                capture_group_id,
+            )?;
+
+            syntax.push(Syntax::CaptureGroup { options, id, name });
+            remainder = &remainder[end + 1..];
+        } else if remainder.starts_with(&[Token::Backslash, Token::Backslash]) {
+            syntax.push(Syntax::Literal { char: '\\' });
+            remainder = &remainder[2..];
+        } else if remainder.starts_with(&[Token::Backslash, Token::Literal('G')]) {
+            syntax.push(Syntax::PreviousMatchEnd);
+            remainder = &remainder[2..];
+        } else if remainder.starts_with(&[Token::Backslash, Token::Literal('d')]) {
+            syntax.push(Syntax::Digit);
+            remainder = &remainder[2..];
+        } else if remainder.starts_with(&[Token::Backslash, Token::Literal('w')]) {
+            syntax.push(Syntax::Word);
+            remainder = &remainder[2..];
+        } else if remainder.starts_with(&[Token::Backslash]) {
+            let Some(escapee) = remainder.get(1) else {
+                return Err(ParseError::IncompleteEscape { index });
+            };
+
+            if let Some(control_char) = into_control_char(escapee) {
+                syntax.push(Syntax::Literal { char: control_char });
+                remainder = &remainder[2..];
+            } else if let Some(d) = char::to_digit(escapee.to_char(), 10) {
+                syntax.push(Syntax::BackReference { id: d });
+                remainder = &remainder[2..];
+            } else {
+                return Err(ParseError::UnrecognizedEscape {
+                    escapee: escapee.to_char(),
+                    index,
+                });
+            }
+        } else if remainder.starts_with(&[Token::Dot]) {
+            syntax.push(Syntax::Wildcard);
+            remainder = &remainder[1..];
+        } else if remainder.starts_with(&[Token::Dollar]) {
+            syntax.push(Syntax::EndOfLineAnchor);
+            remainder = &remainder[1..];
+        } else if remainder.starts_with(&[Token::Plus]) {
+            let Some(contained_syntax) = syntax.pop() else {
+                return Err(ParseError::MissingQuantifiedItem {
+                    quantifier: "one or more",
+                    index,
+                });
+            };
+            syntax.push(Syntax::OneOrMore {
+                syntax: Box::from(contained_syntax),
+            });
+            remainder = &remainder[1..];
+        } else if remainder.starts_with(&[Token::Star]) {
+            let Some(contained_syntax) = syntax.pop() else {
+                return Err(ParseError::MissingQuantifiedItem {
+                    quantifier: "zero or more",
+                    index,
+                });
+            };
+            syntax.push(Syntax::ZeroOrMore {
+                syntax: Box::from(contained_syntax),
+            });
+            remainder = &remainder[1..];
+        } else if remainder.starts_with(&[Token::QuestionMark]) {
+            let Some(contained_syntax) = syntax.pop() else {
+                return Err(ParseError::MissingQuantifiedItem {
+                    quantifier: "zero or one",
+                    index,
+                });
+            };
+            syntax.push(Syntax::ZeroOrOne {
+                syntax: Box::from(contained_syntax),
+            });
+            remainder = &remainder[1..];
+        } else if let (Some(_), Some((min, max, len))) =
+            (syntax.last(), parse_repetition(remainder))
+        {
+            if max.is_some_and(|max| min > max) {
+                return Err(ParseError::InvalidRepetition { index });
+            }
+
+            let contained_syntax = syntax.pop().expect("Repetition must follow another token");
+            syntax.push(Syntax::Repeat {
+                syntax: Box::from(contained_syntax),
+                min,
+                max,
+            });
+            remainder = &remainder[len..];
+        } else if let Some(token @ (Token::Literal(_) | Token::OpenBrace)) = remainder.first() {
+            syntax.push(Syntax::Literal {
+                char: token.to_char(),
+            });
+            remainder = &remainder[1..];
+        } else {
+            return Err(ParseError::UnexpectedToken { index });
+        }
+
+        // Sanity check to ensure that progress is made.
+        assert!(
+            remainder.len() < prev_len,
+            "Must consume at least one pattern char"
+        )
+    }
+
+    Ok(syntax)
+}
+
+/// Parses the tokens into syntax items, panicking on malformed syntax. The index of a
+/// token within the tokens is reported as its index within the pattern.
+pub fn parse_pattern(pattern: &[Token]) -> Vec<Syntax> {
+    let indices: Vec<usize> = (0..pattern.len()).collect();
+
+    parse_tokens(pattern, &indices).unwrap_or_else(|error| panic!("{}", error))
+}
+
+/// Parses the tokens into syntax items, where `indices` holds the index of the char of
+/// each token within the pattern, failing with that index for malformed syntax.
+pub fn parse_tokens(pattern: &[Token], indices: &[usize]) -> Result<Vec<Syntax>, ParseError> {
+    let mut capture_group_id = 0;
+    parse_pattern_core(pattern, indices, &mut capture_group_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grep::tokens::{split_indices, tokenize_pattern, tokenize_pattern_spanned};
+
+    fn assert_single<T: std::fmt::Debug + PartialEq>(items: Vec<T>, expected: T) {
+        assert_eq!(
+            1,
+            items.len(),
+            "Collection must contain exactly one element"
+        );
+        assert_eq!(expected, items[0]);
+    }
+
+    #[test]
+    fn test_parse_pattern_literal() {
+        assert_single(
+            parse_pattern(&[Token::Literal('a')]),
+            Syntax::Literal { char: 'a' },
+        );
+    }
+
+    #[test]
+    fn test_parse_pattern_digit() {
+        assert_single(
+            parse_pattern(&[Token::Backslash, Token::Literal('d')]),
+            Syntax::Digit,
+        );
+    }
+
+    #[test]
+    fn test_parse_pattern_word() {
+        assert_single(
+            parse_pattern(&[Token::Backslash, Token::Literal('w')]),
+            Syntax::Word,
+        );
+    }
+
+    #[test]
+    fn test_parse_pattern_character_class() {
+        assert_single(
+            parse_pattern(&[
+                Token::OpenSquareBracket,
+                Token::Literal('a'),
+                Token::Literal('b'),
+                Token::Literal('c'),
+                Token::CloseSquareBracket,
+            ]),
+            Syntax::CharacterClass {
+                set: CharSet::new(vec!['a', 'b', 'c'], vec![], vec![], false),
+            },
+        )
+    }
+
+    #[test]
+    fn test_parse_pattern_negated_character_class() {
+        assert_single(
+            parse_pattern(&[
+                Token::OpenSquareBracket,
+                Token::Caret,
+                Token::Literal('a'),
+                Token::Literal('b'),
+                Token::Literal('c'),
+                Token::CloseSquareBracket,
+            ]),
+            Syntax::CharacterClass {
+                set: CharSet::new(vec!['a', 'b', 'c'], vec![], vec![], true),
+            },
+        )
+    }
+
+    #[test]
+    fn test_parse_pattern_character_class_escape_sequences() {
+        assert_single(
+            parse_pattern(&[
+                Token::OpenSquareBracket,
+                Token::Backslash,
+                Token::Literal('d'),
+                Token::Backslash,
+                Token::Literal('W'),
+                Token::Backslash,
+                Token::Literal('s'),
+                Token::Dot,
+                Token::Backslash,
+                Token::Backslash,
+                Token::CloseSquareBracket,
+            ]),
+            Syntax::CharacterClass {
+                set: CharSet::new(
+                    vec!['.', '\\'],
+                    vec![],
+                    vec![
+                        ClassShorthand::Digit,
+                        ClassShorthand::NonWord,
+                        ClassShorthand::Whitespace,
+                    ],
+                    false,
+                ),
+            },
+        )
+    }
+
+    #[test]
+    fn test_parse_pattern_character_class_deduplicates() {
+        assert_eq!(
+            parse_pattern(&tokenize_pattern("[aabbcc]")),
+            parse_pattern(&tokenize_pattern("[abc]"))
+        );
+        assert_eq!(
+            parse_pattern(&tokenize_pattern("[a-fbc-h]")),
+            parse_pattern(&tokenize_pattern("[a-h]"))
+        );
+    }
+
+    #[test]
+    fn test_parse_pattern_character_class_intersection() {
+        let vowels = CharSet::new(vec!['a', 'e', 'i', 'o', 'u'], vec![], vec![], true);
+        let consonants = CharSet::new(vec![], vec![('a', 'z')], vec![], false).with_intersection(vowels);
+
+        assert_single(
+            parse_pattern(&tokenize_pattern("[a-z&&[^aeiou]]")),
+            Syntax::CharacterClass { set: consonants },
+        );
+    }
+
+    #[test]
+    fn test_parse_pattern_character_class_without_intersection() {
+        let class = |chars: Vec<char>| Syntax::CharacterClass {
+            set: CharSet::new(chars, vec![], vec![], false),
+        };
+
+        assert_single(parse_pattern(&tokenize_pattern("[&&]")), class(vec!['&']));
+        assert_single(
+            parse_pattern(&tokenize_pattern("[\\&&[x]")),
+            class(vec!['&', '[', 'x']),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid character class intersection, expected '&&['")]
+    fn test_parse_pattern_character_class_intersection_trailing_members() {
+        parse_pattern(&tokenize_pattern("[a-z&&[^a]b]"));
+    }
+
+    #[test]
+    fn test_parse_pattern_character_class_ranges() {
+        assert_single(
+            parse_pattern(&tokenize_pattern("[a-zA-Z_-]")),
+            Syntax::CharacterClass {
+                set: CharSet::new(vec!['_', '-'], vec![('a', 'z'), ('A', 'Z')], vec![], false),
+            },
+        )
+    }
+
+    #[test]
+    fn test_parse_pattern_character_class_range_with_shorthand() {
+        assert_single(
+            parse_pattern(&tokenize_pattern("[a-\\d]")),
+            Syntax::CharacterClass {
+                set: CharSet::new(vec!['a', '-'], vec![], vec![ClassShorthand::Digit], false),
+            },
+        )
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid character class range")]
+    fn test_parse_pattern_character_class_invalid_range() {
+        parse_pattern(&tokenize_pattern("[z-a]"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Incomplete character class (missing closing bracket) at index 3")]
+    fn test_parse_pattern_error_index() {
+        parse_pattern(&tokenize_pattern("abc[de"));
+    }
+
+    #[test]
+    #[should_panic(expected = "one or more modifier can only appear after another token at index 5")]
+    fn test_parse_pattern_error_index_in_alternation_option() {
+        parse_pattern(&tokenize_pattern("x(ab|+c)"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Unrecognized escape sequence '\\q' at index 9")]
+    fn test_parse_pattern_error_index_in_named_group() {
+        parse_pattern(&tokenize_pattern("(?<n>a|(b\\q))"));
+    }
+
+    #[test]
+    fn test_parse_pattern_start_of_line_anchor() {
+        assert_single(parse_pattern(&[Token::Caret]), Syntax::StartOfLineAnchor);
+    }
+
+    #[test]
+    fn test_parse_pattern_end_of_line_anchor() {
+        assert_single(parse_pattern(&[Token::Dollar]), Syntax::EndOfLineAnchor);
+    }
+
+    #[test]
+    fn test_parse_pattern_previous_match_end_anchor() {
+        assert_eq!(
+            vec![Syntax::PreviousMatchEnd, Syntax::Digit],
+            parse_pattern(&tokenize_pattern("\\G\\d"))
+        );
+    }
+
+    #[test]
+    fn test_parse_pattern_one_or_more_modifier() {
         assert_single(
             parse_pattern(&[Token::Literal('a'), Token::Plus]),
             Syntax::OneOrMore {
@@ -365,6 +1615,71 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_parse_pattern_star_modifier() {
+        assert_single(
+            parse_pattern(&[Token::Literal('a'), Token::Star]),
+            Syntax::ZeroOrMore {
+                syntax: Box::new(Syntax::Literal { char: 'a' }),
+            },
+        )
+    }
+
+    fn repeat(char: char, min: usize, max: Option<usize>) -> Syntax {
+        Syntax::Repeat {
+            syntax: Box::new(Syntax::Literal { char }),
+            min,
+            max,
+        }
+    }
+
+    #[test]
+    fn test_parse_pattern_repetition() {
+        assert_single(parse_pattern(&tokenize_pattern("a{3}")), repeat('a', 3, Some(3)));
+        assert_single(parse_pattern(&tokenize_pattern("a{2,}")), repeat('a', 2, None));
+        assert_single(parse_pattern(&tokenize_pattern("a{2,4}")), repeat('a', 2, Some(4)));
+    }
+
+    #[test]
+    fn test_parse_pattern_repetition_missing_lower_bound() {
+        assert_single(parse_pattern(&tokenize_pattern("a{,2}")), repeat('a', 0, Some(2)));
+    }
+
+    #[test]
+    fn test_parse_pattern_repetition_missing_both_bounds() {
+        assert_single(parse_pattern(&tokenize_pattern("a{,}")), repeat('a', 0, None));
+    }
+
+    #[test]
+    fn test_parse_pattern_repetition_not_a_quantifier() {
+        let literals = |text: &str| -> Vec<Syntax> {
+            text.chars().map(|char| Syntax::Literal { char }).collect()
+        };
+
+        assert_eq!(literals("a{}"), parse_pattern(&tokenize_pattern("a{}")));
+        assert_eq!(literals("a{x}"), parse_pattern(&tokenize_pattern("a{x}")));
+        assert_eq!(literals("a{1"), parse_pattern(&tokenize_pattern("a{1")));
+        assert_eq!(literals("{1}"), parse_pattern(&tokenize_pattern("{1}")));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid repetition, minimum exceeds maximum at index 1")]
+    fn test_parse_pattern_repetition_min_exceeds_max() {
+        parse_pattern(&tokenize_pattern("a{3,2}"));
+    }
+
+    #[test]
+    fn test_parse_pattern_quantified_character_class() {
+        assert_single(
+            parse_pattern(&tokenize_pattern("[^0-9]+")),
+            Syntax::OneOrMore {
+                syntax: Box::new(Syntax::CharacterClass {
+                    set: CharSet::new(vec![], vec![('0', '9')], vec![], true),
+                }),
+            },
+        )
+    }
+
     #[test]
     fn test_parse_pattern_wildcard() {
         assert_single(parse_pattern(&[Token::Dot]), Syntax::Wildcard);
@@ -388,10 +1703,25 @@ mod tests {
                     vec![Syntax::Literal { char: 'b' }],
                 ],
                 id: 1,
+                name: None,
             },
         );
     }
 
+    #[test]
+    fn test_parse_pattern_alternation_empty_branches() {
+        let group = |options: Vec<Vec<Syntax>>| Syntax::CaptureGroup {
+            options,
+            id: 1,
+            name: None,
+        };
+        let a = || vec![Syntax::Literal { char: 'a' }];
+
+        assert_single(parse_pattern(&tokenize_pattern("(a|)")), group(vec![a(), vec![]]));
+        assert_single(parse_pattern(&tokenize_pattern("(|a)")), group(vec![vec![], a()]));
+        assert_single(parse_pattern(&tokenize_pattern("(|)")), group(vec![vec![], vec![]]));
+    }
+
     #[test]
     fn test_parse_pattern_capture_group_ids() {
         let items = parse_pattern(&[
@@ -404,21 +1734,519 @@ mod tests {
         ]);
 
         assert_eq!(
-            items.get(0).unwrap(),
+            items.first().unwrap(),
             &Syntax::CaptureGroup {
                 options: vec![vec![Syntax::Literal { char: 'a' }]],
-                id: 1
+                id: 1,
+                name: None,
             }
         );
         assert_eq!(
             items.get(1).unwrap(),
             &Syntax::CaptureGroup {
                 options: vec![vec![Syntax::Literal { char: 'b' }]],
-                id: 2
+                id: 2,
+                name: None,
             }
         );
     }
 
+    #[test]
+    fn test_parse_pattern_nested_capture_group_ids() {
+        let group = |id: u32, char: char| Syntax::CaptureGroup {
+            options: vec![vec![Syntax::Literal { char }]],
+            id,
+            name: None,
+        };
+
+        assert_eq!(
+            parse_pattern(&tokenize_pattern("((a)(b)|(c))(d)")),
+            vec![
+                Syntax::CaptureGroup {
+                    options: vec![vec![group(2, 'a'), group(3, 'b')], vec![group(4, 'c')]],
+                    id: 1,
+                    name: None,
+                },
+                group(5, 'd'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_pattern_deeply_nested_capture_group_ids() {
+        assert_single(
+            parse_pattern(&tokenize_pattern("(((a)|b)|(c))")),
+            Syntax::CaptureGroup {
+                options: vec![
+                    vec![Syntax::CaptureGroup {
+                        options: vec![
+                            vec![Syntax::CaptureGroup {
+                                options: vec![vec![Syntax::Literal { char: 'a' }]],
+                                id: 3,
+                                name: None,
+                            }],
+                            vec![Syntax::Literal { char: 'b' }],
+                        ],
+                        id: 2,
+                        name: None,
+                    }],
+                    vec![Syntax::CaptureGroup {
+                        options: vec![vec![Syntax::Literal { char: 'c' }]],
+                        id: 4,
+                        name: None,
+                    }],
+                ],
+                id: 1,
+                name: None,
+            },
+        );
+    }
+
+    #[test]
+    fn test_parse_pattern_comment() {
+        let parse = |pattern: &str| parse_pattern(&tokenize_pattern(pattern));
+
+        assert_eq!(parse("ab"), parse("a(?#foo)b"));
+        assert_eq!(parse("ab"), parse("a(?#f(o)o)b"));
+        assert_eq!(parse("(a|b)"), parse("(a(?#x|y)|b)"));
+        assert_eq!(parse("(c)"), parse("(?#(a)(b))(c)"));
+        assert_eq!(Vec::<Syntax>::new(), parse("(?#)"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Incomplete comment (missing closing bracket) at index 1")]
+    fn test_parse_pattern_comment_incomplete() {
+        parse_pattern(&tokenize_pattern("a(?#b(c)"));
+    }
+
+    #[test]
+    fn test_parse_pattern_atomic_group() {
+        assert_eq!(
+            parse_pattern(&tokenize_pattern("(?>a+|(b))(c)")),
+            vec![
+                Syntax::AtomicGroup {
+                    options: vec![
+                        vec![Syntax::OneOrMore {
+                            syntax: Box::new(Syntax::Literal { char: 'a' }),
+                        }],
+                        vec![Syntax::CaptureGroup {
+                            options: vec![vec![Syntax::Literal { char: 'b' }]],
+                            id: 1,
+                            name: None,
+                        }],
+                    ],
+                },
+                Syntax::CaptureGroup {
+                    options: vec![vec![Syntax::Literal { char: 'c' }]],
+                    id: 2,
+                    name: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_pattern_lookahead() {
+        assert_eq!(
+            parse_pattern(&tokenize_pattern("a(?=b|(c))(?!d)")),
+            vec![
+                Syntax::Literal { char: 'a' },
+                Syntax::Lookahead {
+                    options: vec![
+                        vec![Syntax::Literal { char: 'b' }],
+                        vec![Syntax::CaptureGroup {
+                            options: vec![vec![Syntax::Literal { char: 'c' }]],
+                            id: 1,
+                            name: None,
+                        }],
+                    ],
+                    is_negated: false,
+                },
+                Syntax::Lookahead {
+                    options: vec![vec![Syntax::Literal { char: 'd' }]],
+                    is_negated: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_pattern_lookbehind() {
+        assert_eq!(
+            parse_pattern(&tokenize_pattern("(?<=ab|\\d{2})(?<!c)")),
+            vec![
+                Syntax::Lookbehind {
+                    options: vec![
+                        vec![Syntax::Literal { char: 'a' }, Syntax::Literal { char: 'b' }],
+                        vec![Syntax::Repeat {
+                            syntax: Box::new(Syntax::Digit),
+                            min: 2,
+                            max: Some(2),
+                        }],
+                    ],
+                    is_negated: false,
+                    len: 2,
+                },
+                Syntax::Lookbehind {
+                    options: vec![vec![Syntax::Literal { char: 'c' }]],
+                    is_negated: true,
+                    len: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_pattern_control_characters() {
+        assert_eq!(
+            parse_pattern(&tokenize_pattern("\\n\\t\\r\\f\\v\\0")),
+            vec![
+                Syntax::Literal { char: '\n' },
+                Syntax::Literal { char: '\t' },
+                Syntax::Literal { char: '\r' },
+                Syntax::Literal { char: '\x0C' },
+                Syntax::Literal { char: '\x0B' },
+                Syntax::Literal { char: '\0' },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_pattern_character_class_control_characters() {
+        assert_single(
+            parse_pattern(&tokenize_pattern("[\\t\\n]")),
+            Syntax::CharacterClass {
+                set: CharSet::new(vec!['\t', '\n'], vec![], vec![], false),
+            },
+        )
+    }
+
+    #[test]
+    fn test_parse_pattern_character_class_bar() {
+        assert_single(
+            parse_pattern(&tokenize_pattern("[a|b]")),
+            Syntax::CharacterClass {
+                set: CharSet::new(vec!['a', '|', 'b'], vec![], vec![], false),
+            },
+        )
+    }
+
+    #[test]
+    fn test_find_alternations_skips_character_classes() {
+        assert_eq!(
+            find_alternations(&tokenize_pattern("a[|]|b")),
+            vec![tokenize_pattern("a[|]"), tokenize_pattern("b")]
+        );
+        assert_eq!(
+            find_alternations(&tokenize_pattern("[(]|[)]")),
+            vec![tokenize_pattern("[(]"), tokenize_pattern("[)]")]
+        );
+    }
+
+    #[test]
+    fn test_find_alternations_skips_nested_groups() {
+        assert_eq!(
+            find_alternations(&tokenize_pattern("(a|b)|c")),
+            vec![tokenize_pattern("(a|b)"), tokenize_pattern("c")]
+        );
+    }
+
+    #[test]
+    fn test_parse_pattern_named_capture_group() {
+        assert_eq!(
+            parse_pattern(&tokenize_pattern("(?<year>\\d)(?P<month>\\d)")),
+            vec![
+                Syntax::CaptureGroup {
+                    options: vec![vec![Syntax::Digit]],
+                    id: 1,
+                    name: Some("year".to_string()),
+                },
+                Syntax::CaptureGroup {
+                    options: vec![vec![Syntax::Digit]],
+                    id: 2,
+                    name: Some("month".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid capture group name")]
+    fn test_parse_pattern_invalid_capture_group_name() {
+        parse_pattern(&tokenize_pattern("(?<1st>a)"));
+    }
+
+    #[test]
+    fn test_capture_names() {
+        let syntax = parse_pattern(&tokenize_pattern("(a)((?<inner>b)|c)+(?>(d))"));
+
+        assert_eq!(
+            capture_names(&syntax),
+            vec![None, None, Some("inner".to_string()), None]
+        );
+    }
+
+    #[test]
+    fn test_has_back_references() {
+        let has_back_references =
+            |pattern| has_back_references(&parse_pattern(&tokenize_pattern(pattern)));
+
+        assert!(!has_back_references("abc"));
+        assert!(!has_back_references("(a)(b|c)+"));
+        assert!(has_back_references("(a)\\1"));
+        assert!(has_back_references("(a)(b|(?=c)\\1)"));
+        assert!(has_back_references("(a)(x\\1)*"));
+    }
+
+    #[test]
+    fn test_group_depth() {
+        assert_eq!(0, group_depth(&tokenize_pattern("abc")));
+        assert_eq!(1, group_depth(&tokenize_pattern("(a)(b)")));
+        assert_eq!(3, group_depth(&tokenize_pattern("((a)|(b(c)))")));
+        assert_eq!(1, group_depth(&tokenize_pattern("(a[(]\\()")));
+    }
+
+    #[test]
+    fn test_ignore_case() {
+        let syntax = ignore_case(parse_pattern(&tokenize_pattern("(a|\\d)+")));
+        let literal = Syntax::CaseInsensitive {
+            syntax: Box::new(Syntax::Literal { char: 'a' }),
+        };
+
+        assert_single(
+            syntax,
+            Syntax::OneOrMore {
+                syntax: Box::new(Syntax::CaptureGroup {
+                    options: vec![vec![literal], vec![Syntax::Digit]],
+                    id: 1,
+                    name: None,
+                }),
+            },
+        );
+    }
+
+    #[test]
+    fn test_to_pattern() {
+        let pattern = "^(?<x>a|b\\d)+[^0-9\\w]*\\1$";
+        assert_eq!(pattern, to_pattern(&parse_pattern(&tokenize_pattern(pattern))));
+
+        let syntax = vec![
+            Syntax::Literal { char: '.' },
+            Syntax::Literal { char: '\\' },
+            Syntax::Literal { char: '\t' },
+        ];
+        assert_eq!("[\\.]\\\\\\t", to_pattern(&syntax));
+    }
+
+    #[test]
+    fn test_to_pattern_round_trip() {
+        let patterns = [
+            "abc",
+            "^\\d+ apples?$",
+            "\\Gca*t.",
+            "[a-z_\\-]+[^\\]\\\\]",
+            "[\\d\\s\\W.]",
+            "((c.t|d.g) and (f..h|b..d)), \\2 with \\3, \\1",
+            "(?P<word>\\w+) (?>ab|a)c",
+            "(a|)(|)x{2}y{,3}z{1,}",
+            "\\w+(?=ing|ed)(?!s)",
+            "[a-z&&[^aeiou]]+[\\w&&[^\\d_]&&[^x]]",
+            "(?<=[$€]|ab)\\d(?<!(0))",
+            "\\n\\r\\0",
+        ];
+
+        for pattern in patterns {
+            let syntax = parse_pattern(&tokenize_pattern(pattern));
+            let rendered = to_pattern(&syntax);
+
+            assert_eq!(
+                syntax,
+                parse_pattern(&tokenize_pattern(&rendered)),
+                "Pattern '{}' rendered as '{}'",
+                pattern,
+                rendered
+            );
+        }
+    }
+
+    #[test]
+    fn test_merge_literals() {
+        let merged = |pattern: &str| merge_literals(parse_pattern(&tokenize_pattern(pattern)));
+        let run = |text: &str| Syntax::LiteralRun {
+            text: text.to_string(),
+        };
+
+        assert_eq!(vec![run("abc"), Syntax::Digit, run("de")], merged("abc\\dde"));
+        assert_eq!(vec![Syntax::Literal { char: 'a' }, Syntax::Digit], merged("a\\d"));
+        assert_eq!(
+            vec![
+                run("ab"),
+                Syntax::OneOrMore {
+                    syntax: Box::new(Syntax::Literal { char: 'c' }),
+                },
+                Syntax::Literal { char: 'd' },
+            ],
+            merged("abc+d")
+        );
+        assert_eq!(
+            vec![
+                Syntax::Literal { char: 'x' },
+                Syntax::CaptureGroup {
+                    options: vec![vec![run("ab")], vec![Syntax::Literal { char: 'c' }]],
+                    id: 1,
+                    name: None,
+                },
+                run("yz"),
+            ],
+            merged("x(ab|c)yz")
+        );
+    }
+
+    #[test]
+    fn test_merge_literals_round_trip() {
+        let merged = merge_literals(parse_pattern(&tokenize_pattern("a.b\\t[.]{2}é")));
+
+        assert_eq!("a.b\\t[.]{2}é", to_pattern(&merged));
+    }
+
+    #[test]
+    fn test_map_items_splits_literal_runs() {
+        let syntax = ignore_case(merge_literals(parse_pattern(&tokenize_pattern("ab"))));
+
+        assert_eq!(2, syntax.len());
+        assert!(syntax.iter().all(|item| matches!(item, Syntax::CaseInsensitive { .. })));
+        assert_eq!(
+            vec![Syntax::LiteralRun { text: "ab".to_string() }, Syntax::EndOfAnyLineAnchor],
+            multiline(merge_literals(parse_pattern(&tokenize_pattern("ab$"))))
+        );
+    }
+
+    #[test]
+    fn test_min_len() {
+        let min = |pattern: &str| min_len(&parse_pattern(&tokenize_pattern(pattern)));
+
+        assert_eq!(3, min("a\\d."));
+        assert_eq!(1, min("^a+b*c?$"));
+        assert_eq!(4, min("x{2,}(ab|cde)"));
+        assert_eq!(1, min("(a)\\1"));
+        assert_eq!(0, min(""));
+    }
+
+    #[test]
+    fn test_max_len() {
+        let max = |pattern: &str| max_len(&parse_pattern(&tokenize_pattern(pattern)));
+
+        assert_eq!(Some(3), max("^a\\d.$"));
+        assert_eq!(Some(5), max("ab?(c|def)"));
+        assert_eq!(Some(7), max("x{,3}[ab]{4}"));
+        assert_eq!(None, max("ab+"));
+        assert_eq!(None, max("a*"));
+        assert_eq!(None, max("x{2,}"));
+        assert_eq!(None, max("(a)\\1"));
+    }
+
+    #[test]
+    fn test_check_supported() {
+        let check = |pattern: &str| {
+            let (tokens, indices) = split_indices(&tokenize_pattern_spanned(pattern));
+            check_supported(&tokens, &indices)
+        };
+
+        assert_eq!(Ok(()), check("(?<year>\\d+)-(?P<day>\\d+)"));
+        assert_eq!(Ok(()), check("(?>a+)b"));
+        assert_eq!(Ok(()), check("a(?=b)(?!c)"));
+        assert_eq!(Ok(()), check("(?<=[$])\\d+(?<!0{3})"));
+        assert_eq!(Ok(()), check("[(?=]\\(?=a"));
+        assert_eq!(Ok(()), check("a?(b)?"));
+        assert_eq!(Ok(()), check("(?#note)a"));
+        assert_eq!(Ok(()), check("a(?#see (?:x) and (?(1)y))b"));
+    }
+
+    #[test]
+    fn test_check_supported_unsupported_features() {
+        let check = |pattern: &str| {
+            let (tokens, indices) = split_indices(&tokenize_pattern_spanned(pattern));
+            check_supported(&tokens, &indices)
+        };
+        let unsupported = |feature, index| Err(ParseError::UnsupportedFeature { feature, index });
+
+        assert_eq!(unsupported("non-capturing group", 0), check("(?:a|b)"));
+        assert_eq!(unsupported("conditional", 3), check("(a)(?(1)b|c)"));
+        assert_eq!(unsupported("inline flag group", 0), check("(?i)a"));
+    }
+
+    #[test]
+    fn test_check_back_references() {
+        let check = |pattern: &str| check_back_references(&parse_pattern(&tokenize_pattern(pattern)));
+
+        assert_eq!(Ok(()), check("(a)\\1"));
+        assert_eq!(Ok(()), check("((a)\\2)\\1"));
+        assert_eq!(Ok(()), check("((a)|\\2)"));
+        assert_eq!(Ok(()), check("(a)(b)?\\2+"));
+    }
+
+    #[test]
+    fn test_check_back_references_unknown_group() {
+        let check = |pattern: &str| check_back_references(&parse_pattern(&tokenize_pattern(pattern)));
+
+        assert_eq!(Err(ParseError::UnknownGroupReference { id: 2 }), check("(a)\\2"));
+        assert_eq!(Err(ParseError::UnknownGroupReference { id: 1 }), check("a\\1"));
+    }
+
+    #[test]
+    fn test_check_back_references_open_group() {
+        let check = |pattern: &str| check_back_references(&parse_pattern(&tokenize_pattern(pattern)));
+
+        assert_eq!(Err(ParseError::OpenGroupReference { id: 1 }), check("(\\1)"));
+        assert_eq!(Err(ParseError::OpenGroupReference { id: 1 }), check("(a\\1?)"));
+        assert_eq!(Err(ParseError::OpenGroupReference { id: 2 }), check("(a)\\2(b)"));
+    }
+
+    #[test]
+    fn test_debug_tree() {
+        let syntax = parse_pattern(&tokenize_pattern("(a|b)+c"));
+        let expected = [
+            "OneOrMore",
+            "  CaptureGroup 1",
+            "    Option",
+            "      Literal { char: 'a' }",
+            "    Option",
+            "      Literal { char: 'b' }",
+            "Literal { char: 'c' }",
+        ];
+
+        assert_eq!(expected.join("\n"), debug_tree(&syntax));
+    }
+
+    #[test]
+    fn test_debug_tree_named_and_atomic_groups() {
+        let syntax = parse_pattern(&tokenize_pattern("(?<x>\\d)(?>a?)"));
+        let expected = [
+            "CaptureGroup 1 <x>",
+            "  Option",
+            "    Digit",
+            "AtomicGroup",
+            "  Option",
+            "    ZeroOrOne",
+            "      Literal { char: 'a' }",
+        ];
+
+        assert_eq!(expected.join("\n"), debug_tree(&syntax));
+    }
+
+    #[test]
+    fn test_debug_tree_repetition() {
+        let syntax = parse_pattern(&tokenize_pattern("a{,2}b{1,}"));
+        let expected = [
+            "Repeat {0,2}",
+            "  Literal { char: 'a' }",
+            "Repeat {1,}",
+            "  Literal { char: 'b' }",
+        ];
+
+        assert_eq!(expected.join("\n"), debug_tree(&syntax));
+    }
+
     #[test]
     fn test_parse_pattern_backreference() {
         assert_single(
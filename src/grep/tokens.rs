@@ -11,50 +11,236 @@ pub enum Token {
     Caret,
     Dollar,
     Plus,
+    Star,
+    OpenBrace,
     QuestionMark,
     Dot,
     Bar
 }
 
+/// A token together with the index of the char of the pattern it starts at. Quoting,
+/// free-spacing and escapes in basic syntax turn chars into tokens unevenly, so the
+/// index of a token within the tokens is not the index of its char within the pattern.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spanned<T> {
+    pub token: T,
+    pub index: usize,
+}
+
+/// Tokenizes a pattern in extended syntax (ERE), where each char becomes one token.
+/// Chars quoted by `\Q...\E` become literal tokens, without their special meaning.
+/// A `\Q` without an `\E` quotes the rest of the pattern. A leading `(?x)` tokenizes
+/// the rest of the pattern in free-spacing mode, see [`tokenize_pattern_free_spacing`].
 pub fn tokenize_pattern(pattern: &str) -> Vec<Token> {
-    pattern
-        .chars()
-        .map(|c| match c {
-            '\\' => Token::Backslash,
-            '(' => Token::OpenBracket,
-            ')' => Token::CloseBracket,
-            '[' => Token::OpenSquareBracket,
-            ']' => Token::CloseSquareBracket,
-            '^' => Token::Caret,
-            '$' => Token::Dollar,
-            '+' => Token::Plus,
-            '?' => Token::QuestionMark,
-            '.' => Token::Dot,
-            '|' => Token::Bar,
-            other => Token::Literal(other),
-        })
-        .collect()
+    into_tokens(tokenize_pattern_spanned(pattern))
 }
 
-impl fmt::Display for Token {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+/// Tokenizes a pattern like [`tokenize_pattern`], keeping the index of each token's char.
+pub fn tokenize_pattern_spanned(pattern: &str) -> Vec<Spanned<Token>> {
+    match pattern.strip_prefix("(?x)") {
+        Some(rest) => tokenize(rest, "(?x)".len(), true),
+        None => tokenize(pattern, 0, false),
+    }
+}
+
+/// Tokenizes a pattern in extended syntax (ERE) in free-spacing mode, which drops
+/// whitespace and comments from `#` to the end of the line, so long patterns can be
+/// spread over several lines. Whitespace and `#` within character classes, quoted by
+/// `\Q...\E` or escaped with a backslash remain literals.
+pub fn tokenize_pattern_free_spacing(pattern: &str) -> Vec<Spanned<Token>> {
+    tokenize(pattern, 0, true)
+}
+
+/// Tokenizes the pattern, `offset` being the index of its first char within the whole
+/// pattern.
+fn tokenize(pattern: &str, offset: usize, is_free_spacing: bool) -> Vec<Spanned<Token>> {
+    let mut tokens: Vec<Spanned<Token>> = vec![];
+    let mut chars = pattern.chars().enumerate().map(|(i, c)| (offset + i, c)).peekable();
+
+    // Like when parsing, only an unescaped `&&[` nests a class within a class.
+    let mut class_depth = 0;
+
+    while let Some((index, c)) = chars.next() {
+        if is_free_spacing && class_depth == 0 {
+            if c.is_whitespace() {
+                continue;
+            }
+
+            if c == '#' {
+                chars.by_ref().find(|(_, c)| *c == '\n');
+                continue;
+            }
+        }
+
+        if c != '\\' {
+            let token = tokenize_char(c);
+            match token {
+                Token::OpenSquareBracket
+                    if class_depth == 0 || ends_with_intersection(&tokens) =>
+                {
+                    class_depth += 1
+                }
+                Token::CloseSquareBracket if class_depth > 0 => class_depth -= 1,
+                _ => {}
+            }
+
+            tokens.push(Spanned { token, index });
+            continue;
+        }
+
+        // The escaped char is taken along, so an escaped backslash cannot start a quote.
+        match chars.next() {
+            Some((_, 'Q')) => {
+                while let Some((quoted_index, quoted)) = chars.next() {
+                    if quoted == '\\' && matches!(chars.peek(), Some((_, 'E'))) {
+                        chars.next();
+                        break;
+                    }
+
+                    tokens.push(Spanned {
+                        token: Token::Literal(quoted),
+                        index: quoted_index,
+                    });
+                }
+            }
+            Some((_, escapee))
+                if is_free_spacing && (escapee.is_whitespace() || escapee == '#') =>
+            {
+                tokens.push(Spanned {
+                    token: Token::Literal(escapee),
+                    index,
+                })
+            }
+            Some((escapee_index, escapee)) => tokens.extend([
+                Spanned {
+                    token: Token::Backslash,
+                    index,
+                },
+                Spanned {
+                    token: tokenize_char(escapee),
+                    index: escapee_index,
+                },
+            ]),
+            None => tokens.push(Spanned {
+                token: Token::Backslash,
+                index,
+            }),
+        }
+    }
+
+    tokens
+}
+
+/// Returns true if the tokens end with the `&&` of a class intersection.
+fn ends_with_intersection(tokens: &[Spanned<Token>]) -> bool {
+    tokens.len() >= 2
+        && tokens[tokens.len() - 2..]
+            .iter()
+            .all(|spanned| spanned.token == Token::Literal('&'))
+}
+
+/// Tokenizes a pattern in basic syntax (BRE), where `(`, `)`, `{`, `}`, `+`, `?` and `|`
+/// match themselves and only have their special meaning when escaped with a backslash.
+/// An escaped metacharacter becomes a single token at the index of its backslash.
+pub fn tokenize_pattern_basic(pattern: &str) -> Vec<Spanned<Token>> {
+    let mut tokens = vec![];
+    let mut chars = pattern.chars().enumerate();
+
+    while let Some((index, c)) = chars.next() {
+        let token = match c {
+            '\\' => match chars.next() {
+                Some((_, '(')) => Token::OpenBracket,
+                Some((_, ')')) => Token::CloseBracket,
+                Some((_, '{')) => Token::OpenBrace,
+                Some((_, '}')) => Token::Literal('}'),
+                Some((_, '+')) => Token::Plus,
+                Some((_, '?')) => Token::QuestionMark,
+                Some((_, '|')) => Token::Bar,
+                Some((escapee_index, escapee)) => {
+                    // All other escape sequences are the same as in extended syntax.
+                    tokens.push(Spanned {
+                        token: Token::Backslash,
+                        index,
+                    });
+                    tokens.push(Spanned {
+                        token: tokenize_char(escapee),
+                        index: escapee_index,
+                    });
+                    continue;
+                }
+                None => Token::Backslash,
+            },
+            '(' | ')' | '{' | '}' | '+' | '?' | '|' => Token::Literal(c),
+            other => tokenize_char(other),
+        };
+
+        tokens.push(Spanned { token, index });
+    }
+
+    tokens
+}
+
+/// Splits the tokens from the indices of their chars within the pattern.
+pub fn split_indices(tokens: &[Spanned<Token>]) -> (Vec<Token>, Vec<usize>) {
+    tokens
+        .iter()
+        .map(|spanned| (spanned.token.clone(), spanned.index))
+        .unzip()
+}
+
+/// Drops the indices of the tokens.
+pub fn into_tokens(tokens: Vec<Spanned<Token>>) -> Vec<Token> {
+    tokens.into_iter().map(|spanned| spanned.token).collect()
+}
+
+/// Returns the token of a single char in extended syntax.
+fn tokenize_char(c: char) -> Token {
+    match c {
+        '\\' => Token::Backslash,
+        '(' => Token::OpenBracket,
+        ')' => Token::CloseBracket,
+        '[' => Token::OpenSquareBracket,
+        ']' => Token::CloseSquareBracket,
+        '^' => Token::Caret,
+        '$' => Token::Dollar,
+        '+' => Token::Plus,
+        '*' => Token::Star,
+        '{' => Token::OpenBrace,
+        '?' => Token::QuestionMark,
+        '.' => Token::Dot,
+        '|' => Token::Bar,
+        other => Token::Literal(other),
+    }
+}
+
+impl Token {
+    /// Returns the pattern character this token was created from.
+    pub fn to_char(&self) -> char {
         match *self {
-            Token::Backslash => write!(f, "\\"),
-            Token::OpenBracket => write!(f, "("),
-            Token::CloseBracket => write!(f, ")"),
-            Token::OpenSquareBracket => write!(f, "["),
-            Token::CloseSquareBracket => write!(f, "]"),
-            Token::Caret => write!(f, "^"),
-            Token::Dollar => write!(f, "$"),
-            Token::Plus => write!(f, "+"),
-            Token::QuestionMark => write!(f, "?"),
-            Token::Dot => write!(f, "."),
-            Token::Bar => write!(f, "|"),
-            Token::Literal(c) => write!(f, "{}", c)
+            Token::Backslash => '\\',
+            Token::OpenBracket => '(',
+            Token::CloseBracket => ')',
+            Token::OpenSquareBracket => '[',
+            Token::CloseSquareBracket => ']',
+            Token::Caret => '^',
+            Token::Dollar => '$',
+            Token::Plus => '+',
+            Token::Star => '*',
+            Token::OpenBrace => '{',
+            Token::QuestionMark => '?',
+            Token::Dot => '.',
+            Token::Bar => '|',
+            Token::Literal(c) => c,
         }
     }
 }
 
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,6 +285,16 @@ mod tests {
         assert_eq!(tokenize_pattern("+"), [Token::Plus]);
     }
 
+    #[test]
+    fn test_tokenize_pattern_star() {
+        assert_eq!(tokenize_pattern("*"), [Token::Star]);
+    }
+
+    #[test]
+    fn test_tokenize_pattern_open_brace() {
+        assert_eq!(tokenize_pattern("{}"), [Token::OpenBrace, Token::Literal('}')]);
+    }
+
     #[test]
     fn test_tokenize_pattern_question_mark() {
         assert_eq!(tokenize_pattern("?"), [Token::QuestionMark]);
@@ -132,4 +328,123 @@ mod tests {
             ]
         )
     }
+
+    #[test]
+    fn test_token_to_char_round_trip() {
+        let pattern = "[^a-z]\\d(x|y)+?.*$";
+        let round_trip: String = tokenize_pattern(pattern).iter().map(Token::to_char).collect();
+
+        assert_eq!(pattern, round_trip);
+    }
+
+    #[test]
+    fn test_tokenize_pattern_quoted() {
+        assert_eq!(
+            tokenize_pattern("\\Qa.(\\E+"),
+            [
+                Token::Literal('a'),
+                Token::Literal('.'),
+                Token::Literal('('),
+                Token::Plus
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_pattern_quoted_backslash() {
+        assert_eq!(
+            tokenize_pattern("\\Q\\d\\E"),
+            [Token::Literal('\\'), Token::Literal('d')]
+        );
+        assert_eq!(
+            tokenize_pattern("\\\\Q"),
+            [Token::Backslash, Token::Backslash, Token::Literal('Q')]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_pattern_quoted_unterminated() {
+        assert_eq!(
+            tokenize_pattern("a\\Q$|"),
+            [Token::Literal('a'), Token::Literal('$'), Token::Literal('|')]
+        );
+        assert_eq!(tokenize_pattern("\\Q"), []);
+    }
+
+    #[test]
+    fn test_tokenize_pattern_free_spacing() {
+        assert_eq!(
+            into_tokens(tokenize_pattern_free_spacing(" a b\n\tc ")),
+            tokenize_pattern("abc")
+        );
+        assert_eq!(
+            into_tokens(tokenize_pattern_free_spacing("a # comment (\n b # another")),
+            tokenize_pattern("ab")
+        );
+        assert_eq!(tokenize_pattern("(?x) a  b"), tokenize_pattern("ab"));
+    }
+
+    #[test]
+    fn test_tokenize_pattern_free_spacing_literal_whitespace() {
+        assert_eq!(
+            into_tokens(tokenize_pattern_free_spacing("[ #] \\  \\# \\Q #\\E")),
+            tokenize_pattern("[ #]")
+                .into_iter()
+                .chain(" # #".chars().map(Token::Literal))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            into_tokens(tokenize_pattern_free_spacing("[a&&[ b]] c")),
+            tokenize_pattern("[a&&[ b]]c")
+        );
+    }
+
+    #[test]
+    fn test_tokenize_pattern_spanned_indices() {
+        let indices = |tokens: Vec<Spanned<Token>>| {
+            tokens.iter().map(|spanned| spanned.index).collect::<Vec<_>>()
+        };
+
+        assert_eq!(vec![0, 1, 2], indices(tokenize_pattern_spanned("a\\d")));
+        assert_eq!(vec![2, 3, 6], indices(tokenize_pattern_spanned("\\Qab\\E(")));
+        assert_eq!(vec![6, 9, 12], indices(tokenize_pattern_spanned("(?x)  a  b  (")));
+        assert_eq!(vec![1, 2, 8], indices(tokenize_pattern_free_spacing(" a\\ # b\n(")));
+        assert_eq!(vec![0, 2, 3, 4], indices(tokenize_pattern_basic("\\(a\\d")));
+    }
+
+    #[test]
+    fn test_tokenize_pattern_basic_literal_metacharacters() {
+        assert_eq!(
+            into_tokens(tokenize_pattern_basic("(a|b)+?{}")),
+            "(a|b)+?{}".chars().map(Token::Literal).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_tokenize_pattern_basic_escaped_metacharacters() {
+        assert_eq!(
+            into_tokens(tokenize_pattern_basic("\\(a\\|b\\)\\+\\?\\{2\\}")),
+            [
+                Token::OpenBracket,
+                Token::Literal('a'),
+                Token::Bar,
+                Token::Literal('b'),
+                Token::CloseBracket,
+                Token::Plus,
+                Token::QuestionMark,
+                Token::OpenBrace,
+                Token::Literal('2'),
+                Token::Literal('}'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_pattern_basic_shared_syntax() {
+        assert_eq!(
+            into_tokens(tokenize_pattern_basic("^[a]\\d.*$\\\\")),
+            tokenize_pattern("^[a]\\d.*$\\\\")
+        );
+        assert_eq!(into_tokens(tokenize_pattern_basic("\\")), [Token::Backslash]);
+    }
 }
@@ -0,0 +1,6 @@
+mod grep;
+
+pub use grep::{
+    anchors, decode_bytes, find_pattern, match_pattern, Captures, CapturesIter, Engine, FindIter,
+    Flags, Limits, MatchResult, ParseError, Regex, RegexBuilder, Span, StreamMatcher,
+};
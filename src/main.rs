@@ -1,94 +1,2796 @@
+use std::collections::VecDeque;
 use std::env;
 use std::fs::File;
-use std::io::{self, BufRead};
+use std::io::{self, BufRead, Write};
+use std::iter;
+use std::path::Path;
 use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
 
-mod grep;
+use codecrafters_grep::{anchors, decode_bytes, Engine, Limits, Regex, Span};
+use flate2::bufread::MultiGzDecoder;
+use serde::Serialize;
 
-use grep::match_pattern;
+/// The file name standing for standard input, as a data operand and for `-f`. Without
+/// any data operand, standard input is searched as this file.
+const STDIN_FILE: &str = "-";
 
-fn grep_stdin(pattern: &str) -> i32 {
-    let mut input_line = String::new();
+/// The name printed for standard input in prefixes and messages, like GNU grep does.
+const STDIN_NAME: &str = "(standard input)";
 
-    io::stdin().read_line(&mut input_line).unwrap();
+/// The bytes every gzip compressed file starts with.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
 
-    // Uncomment this block to pass the first stage
-    if match_pattern(&input_line, &pattern) {
-        process::exit(0);
+/// Options controlling which files are searched and how results are printed.
+#[derive(Default)]
+struct Options {
+    /// Prefix each matching line with the name of its file.
+    prefix: bool,
+
+    /// Print nothing and exit successfully at the first selected line (`-q`).
+    /// Takes precedence over all options printing lines, counts or file names.
+    quiet: bool,
+
+    /// Print only the names of files containing at least one match (`-l`).
+    files_with_matches: bool,
+
+    /// Print only the names of files containing no match at all (`-L`).
+    files_without_match: bool,
+
+    /// Select the lines not matching the pattern instead (`-v`).
+    invert_match: bool,
+
+    /// Print only the number of selected lines per file (`-c`).
+    count: bool,
+
+    /// Print only the number of matches per file, counting every match
+    /// within a line (`--count-matches`).
+    count_matches: bool,
+
+    /// With `-c` or `--count-matches`, also print the sum of the counts of all
+    /// files after the per-file counts (`--total`).
+    total: bool,
+
+    /// Stop reading a file after this many selected lines (`-m`).
+    max_count: Option<usize>,
+
+    /// Print only the matched parts of each line, one per output line (`-o`).
+    only_matching: bool,
+
+    /// With `-o`, print the text of this capture group instead of the whole match (`--only`).
+    only_group: Option<usize>,
+
+    /// Print every match as a JSON object on its own line, see [`JsonMatch`] (`--json`).
+    json: bool,
+
+    /// Prefix each output line with the 1-based number of its line within the file,
+    /// also every match printed with `-o` (`-n`).
+    line_number: bool,
+
+    /// Prefix each output line with the byte offset of the line within the file,
+    /// or with `-o` of the match within the line (`-b`).
+    byte_offset: bool,
+
+    /// Separate the prefixes from the line by a tab, so that lines start at a tab stop
+    /// and line up (`-T`).
+    initial_tab: bool,
+
+    /// Separate input and output lines by NUL instead of newline (`-z`).
+    null_data: bool,
+
+    /// Select only lines matched by the pattern as a whole (`-x`).
+    line_regexp: bool,
+
+    /// Select only matches forming whole words (`-w`).
+    word_regexp: bool,
+
+    /// Print this many lines of context after each selected line (`-A`).
+    after_context: usize,
+
+    /// Print this many lines of context before each selected line (`-B`).
+    before_context: usize,
+
+    /// Separates groups of lines printed with context, `--` if not given
+    /// (`--group-separator`).
+    group_separator: Option<String>,
+
+    /// Do not separate groups of lines printed with context (`--no-group-separator`).
+    no_group_separator: bool,
+
+    /// Do not report files that cannot be searched (`-s`).
+    suppress_errors: bool,
+
+    /// Skip lines longer than this many bytes, reporting each of them
+    /// (`--max-line-length`).
+    max_line_length: Option<usize>,
+
+    /// Search the decompressed content of gzip compressed files, like zgrep does
+    /// (`-Z`).
+    decompress: bool,
+
+    /// Search this many files at the same time, one by one if at most 1 (`--threads`).
+    threads: usize,
+}
+
+impl Options {
+    /// Returns the byte separating lines of input.
+    fn line_separator(&self) -> u8 {
+        if self.null_data {
+            b'\0'
+        } else {
+            b'\n'
+        }
+    }
+
+    /// Checks that the options can be combined, returning the message to report otherwise.
+    fn validate(&self) -> Result<(), String> {
+        if self.invert_match && self.only_matching {
+            // Inverted lines contain no matches, so there would never be anything to print.
+            return Err("Options '-v' and '-o' cannot be combined".to_string());
+        }
+
+        if self.invert_match && self.json {
+            return Err("Options '-v' and '--json' cannot be combined".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if lines of context are printed around selected lines.
+    fn has_context(&self) -> bool {
+        let is_printing_lines = !(self.quiet
+            || self.only_matching
+            || self.json
+            || self.count
+            || self.count_matches
+            || self.files_with_matches
+            || self.files_without_match);
+
+        is_printing_lines && (self.after_context > 0 || self.before_context > 0)
+    }
+
+    /// Returns true if the number of selected lines or matches is printed per file.
+    fn is_counted(&self) -> bool {
+        (self.count || self.count_matches)
+            && !(self.quiet || self.files_with_matches || self.files_without_match)
+    }
+
+    /// Returns the line separating groups of lines printed with context, if any.
+    fn group_separator(&self) -> Option<&str> {
+        if self.no_group_separator {
+            None
+        } else {
+            Some(self.group_separator.as_deref().unwrap_or("--"))
+        }
+    }
+}
+
+/// Removes a trailing line ending, either `\n` or `\r\n`, from the line.
+fn trim_line_ending(line: &mut Vec<u8>) {
+    if line.ends_with(b"\n") {
+        line.pop();
+    }
+
+    if line.ends_with(b"\r") {
+        line.pop();
+    }
+}
+
+/// Reads the lines of the file as bytes, split at the given separator byte, as they
+/// need not be valid UTF-8. Each line comes with the number of bytes it takes up in
+/// the file, including the separator.
+/// Lines longer than `max_len` bytes come as `None`, their bytes beyond the limit
+/// are dropped while reading, so such a line is never held in memory as a whole.
+fn read_lines(
+    filename: &str,
+    separator: u8,
+    max_len: Option<usize>,
+    decompress: bool,
+) -> io::Result<impl Iterator<Item = io::Result<(Option<Vec<u8>>, usize)>>> {
+    let mut reader = open_input(filename, decompress)?;
+    let max_len = max_len.unwrap_or(usize::MAX);
+
+    let lines = iter::from_fn(move || {
+        let mut line = vec![];
+        let mut file_len = 0;
+
+        loop {
+            let buffer = match reader.fill_buf() {
+                Ok(buffer) => buffer,
+                Err(error) => return Some(Err(error)),
+            };
+            if buffer.is_empty() {
+                break;
+            }
+
+            let end = buffer.iter().position(|b| *b == separator);
+            let chunk = &buffer[..end.unwrap_or(buffer.len())];
+
+            // One byte beyond the limit is kept to tell that the line is too long.
+            let kept = chunk.len().min(max_len.saturating_add(1) - line.len());
+            line.extend_from_slice(&chunk[..kept]);
+
+            let consumed = chunk.len() + usize::from(end.is_some());
+            reader.consume(consumed);
+            file_len += consumed;
+
+            if end.is_some() {
+                break;
+            }
+        }
+
+        if file_len == 0 {
+            return None;
+        }
+
+        if line.len() > max_len {
+            return Some(Ok((None, file_len)));
+        }
+
+        // Splitting only strips the '\n', so a trailing '\r' from "\r\n" is removed here.
+        if separator == b'\n' {
+            trim_line_ending(&mut line);
+        }
+
+        Some(Ok((Some(line), file_len)))
+    });
+
+    Ok(lines)
+}
+
+/// Opens the file for reading, or standard input for `-`. With `decompress`, input
+/// starting like a gzip compressed file is decompressed, any other input is read as
+/// it is.
+fn open_input(filename: &str, decompress: bool) -> io::Result<Box<dyn BufRead>> {
+    let mut reader: Box<dyn BufRead> = if filename == STDIN_FILE {
+        Box::new(io::stdin().lock())
     } else {
-        process::exit(1);
+        Box::new(io::BufReader::new(File::open(filename)?))
+    };
+
+    if decompress && reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+        // Concatenated gzip members are decompressed one after another, like gzip does.
+        reader = Box::new(io::BufReader::new(MultiGzDecoder::new(reader)));
     }
+
+    Ok(reader)
 }
 
-fn read_lines(filename: &str) -> io::Result<io::Lines<io::BufReader<File>>> {
-    let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+/// Returns the name of the file as printed in prefixes and messages.
+fn display_name(filename: &str) -> &str {
+    if filename == STDIN_FILE {
+        STDIN_NAME
+    } else {
+        filename
+    }
 }
 
-fn grep_files(pattern: &str, files: &[String], prefix: bool) {
-    let mut match_count = 0;
+/// Reads the patterns from the file, or from standard input for `-`, one per line.
+/// An empty line is an empty pattern, which matches every line.
+fn read_patterns(filename: &str) -> io::Result<Vec<String>> {
+    let content = io::read_to_string(open_input(filename, false)?)?;
+    Ok(content.lines().map(String::from).collect())
+}
 
-    for file in files {
-        if let Ok(lines) = read_lines(file) {
-            for line in lines.map_while(Result::ok) {
-                if match_pattern(&line, pattern) {
-                    match_count += 1;
+/// Collects all files within the given directories, recursively.
+/// Operands naming a file instead of a directory are included as they are, as is `-`
+/// for standard input. Files larger than `max_size` bytes are skipped.
+/// Directory entries are visited sorted by name, so the order is the same on all platforms.
+fn find_files(operands: &[String], max_size: Option<u64>) -> Vec<String> {
+    let mut files = vec![];
 
-                    if match_count > 1 {
-                        println!("");
-                    }
+    for operand in operands {
+        if operand == STDIN_FILE {
+            files.push(operand.clone());
+            continue;
+        }
 
-                    if prefix {
-                        print!("{0}:{1}", file, line);
-                    } else {
-                        print!("{}", line);
+        let walker = walkdir::WalkDir::new(operand).sort_by_file_name();
+        for file in walker.into_iter().filter_map(|e| e.ok()) {
+            // The size is only read if limited, which saves a system call per file.
+            let is_too_large = max_size.is_some_and(|max| {
+                file.metadata().is_ok_and(|metadata| metadata.len() > max)
+            });
+
+            if file.file_type().is_file() && !is_too_large {
+                let path = file.path().display().to_string();
+                files.push(path);
+            }
+        }
+    }
+
+    files
+}
+
+/// Returns true if the match is selected by `-w`, i.e. it forms a whole word or `-w`
+/// is not given.
+fn is_word_match(line: &str, span: &Span, options: &Options) -> bool {
+    !options.word_regexp || anchors::matches_whole_word(line, span.start, span.end)
+}
+
+/// Returns the spans of the matches within a selected line as printed by `-o`, which
+/// are the spans of the `--only` group instead if given. With `-x`, the whole line is
+/// the only match, whether a group is given or not.
+fn match_spans<'a>(
+    regex: &'a Regex,
+    line: &'a str,
+    options: &'a Options,
+) -> Box<dyn Iterator<Item = Span> + 'a> {
+    if options.line_regexp {
+        return Box::new(iter::once(Span {
+            start: 0,
+            end: line.len(),
+        }));
+    }
+
+    let Some(group) = options.only_group else {
+        let spans = regex
+            .find_iter(line)
+            .filter(|span| is_word_match(line, span, options));
+        return Box::new(spans);
+    };
+
+    // The groups are taken from the same search that found the match.
+    let spans = regex
+        .captures_iter(line)
+        .filter(|captures| {
+            let span = captures.get(0).expect("Group 0 is the whole match");
+            is_word_match(line, &span, options)
+        })
+        // A group not participating in the match has no span.
+        .filter_map(move |captures| captures.get(group));
+
+    Box::new(spans)
+}
+
+/// Returns true if the line matches the pattern as required by the options,
+/// disregarding `-v`.
+fn is_line_match(regex: &Regex, line: &str, options: &Options) -> bool {
+    if options.line_regexp {
+        regex.is_full_match(line)
+    } else if options.word_regexp {
+        regex.find_iter(line).any(|span| is_word_match(line, &span, options))
+    } else {
+        regex.is_match(line)
+    }
+}
+
+/// Writes a single line of output, prefixed as configured by the options.
+/// Lines of context are prefixed with `-` instead of `:`, like GNU grep does.
+/// Every line is terminated by the line separator, so output of several files or
+/// of several calls can be concatenated.
+fn write_output(
+    out: &mut impl Write,
+    file: &str,
+    line_number: Option<usize>,
+    byte_offset: Option<usize>,
+    text: &[u8],
+    options: &Options,
+    is_context: bool,
+) -> io::Result<()> {
+    let prefix_separator = if is_context { '-' } else { ':' };
+
+    if options.prefix {
+        write!(out, "{}{}", file, prefix_separator)?;
+    }
+
+    if let Some(number) = line_number {
+        write!(out, "{}{}", number, prefix_separator)?;
+    }
+
+    if let Some(offset) = byte_offset {
+        write!(out, "{}{}", offset, prefix_separator)?;
+    }
+
+    let has_prefix = options.prefix || line_number.is_some() || byte_offset.is_some();
+    if options.initial_tab && has_prefix {
+        write!(out, "\t")?;
+    }
+
+    out.write_all(text)?;
+    out.write_all(&[options.line_separator()])
+}
+
+/// A match as printed by `--json`. Offsets are in bytes within the line.
+#[derive(Serialize)]
+struct JsonMatch<'a> {
+    path: &'a str,
+    line_number: usize,
+    line: &'a str,
+    start: usize,
+    end: usize,
+}
+
+/// Writes the match within the line as a JSON object on its own line.
+fn write_json(
+    out: &mut impl Write,
+    file: &str,
+    line_number: usize,
+    line: &str,
+    span: Span,
+) -> io::Result<()> {
+    let json_match = JsonMatch {
+        path: file,
+        line_number,
+        line,
+        start: span.start,
+        end: span.end,
+    };
+
+    serde_json::to_writer(&mut *out, &json_match)?;
+    writeln!(out)
+}
+
+/// Writes the line separating two groups of lines printed with context.
+fn write_group_separator(
+    out: &mut impl Write,
+    separator: &str,
+    options: &Options,
+) -> io::Result<()> {
+    write!(out, "{}", separator)?;
+    out.write_all(&[options.line_separator()])
+}
+
+/// Returns the description of an error opening a file, worded like GNU grep does.
+fn file_error_message(error: &io::Error) -> String {
+    match error.kind() {
+        io::ErrorKind::NotFound => "No such file or directory".to_string(),
+        io::ErrorKind::PermissionDenied => "Permission denied".to_string(),
+        _ => error.to_string(),
+    }
+}
+
+/// The outcome of searching a single file, see [`grep_file`].
+#[derive(Default)]
+struct FileSummary {
+    /// Number of selected lines, at most one with `-q`, `-l` or `-L`.
+    match_count: usize,
+
+    /// Number of selected lines with `-c` or of matches with `--count-matches`.
+    count: usize,
+
+    /// The file is listed by `-l` or `-L`.
+    is_listed: bool,
+
+    /// Groups of lines were printed with context.
+    has_groups: bool,
+
+    /// The file could not be read.
+    has_error: bool,
+}
+
+/// Searches all given files for the pattern, writing the results to `out` and
+/// messages about files that cannot be searched to `err`. With more than one thread,
+/// the files are searched at the same time, but printed in the given order.
+/// Returns the exit code of the search, 2 if any file could not be read, or the
+/// error writing to `out` or `err`.
+fn grep_files(
+    regex: &Regex,
+    files: &[String],
+    options: &Options,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> io::Result<i32> {
+    let mut match_count = 0;
+    let mut total_count = 0;
+    let mut file_count = 0;
+    let mut is_group_printed = false;
+    let mut has_error = false;
+
+    let mut searched = search_in_parallel(regex, files, options).into_iter();
+
+    for filename in files {
+        let summary = match searched.next() {
+            Some((summary, file_out, file_err)) => {
+                // The threads cannot know about groups printed for earlier files.
+                if summary.has_groups && is_group_printed {
+                    if let Some(separator) = options.group_separator() {
+                        write_group_separator(out, separator, options)?;
                     }
                 }
+
+                out.write_all(&file_out)?;
+                err.write_all(&file_err)?;
+                summary
             }
-        } else {
-            process::exit(-2);
+            None => grep_file(regex, filename, options, is_group_printed, out, err)?,
+        };
+
+        if options.quiet && summary.match_count > 0 {
+            // Like GNU grep, the first selected line decides the exit code,
+            // even if an earlier file could not be read.
+            return Ok(0);
         }
+
+        match_count += summary.match_count;
+        total_count += summary.count;
+        file_count += usize::from(summary.is_listed);
+        is_group_printed |= summary.has_groups;
+        has_error |= summary.has_error;
+    }
+
+    if options.is_counted() && options.total {
+        writeln!(out, "total:{}", total_count)?;
     }
 
-    if match_count > 0 {
-        process::exit(0);
+    let is_success = if options.files_with_matches || options.files_without_match {
+        file_count > 0
+    } else {
+        match_count > 0
+    };
+
+    Ok(if has_error {
+        2
+    } else if is_success {
+        0
     } else {
-        process::exit(1);
+        1
+    })
+}
+
+/// Searches the files on `options.threads` threads, each taking the next file nobody
+/// searched yet. Returns the summary and the output and messages of every file in
+/// the order of the files, or nothing if the files are to be searched one by one.
+fn search_in_parallel(
+    regex: &Regex,
+    files: &[String],
+    options: &Options,
+) -> Vec<(FileSummary, Vec<u8>, Vec<u8>)> {
+    if options.threads <= 1 {
+        return vec![];
     }
+
+    let next_index = AtomicUsize::new(0);
+    let searched = Mutex::new(Vec::with_capacity(files.len()));
+
+    thread::scope(|scope| {
+        for _ in 0..options.threads.min(files.len()) {
+            scope.spawn(|| loop {
+                let index = next_index.fetch_add(1, Ordering::Relaxed);
+                let Some(filename) = files.get(index) else {
+                    break;
+                };
+
+                let (mut file_out, mut file_err) = (vec![], vec![]);
+                let summary = grep_file(
+                    regex,
+                    filename,
+                    options,
+                    false,
+                    &mut file_out,
+                    &mut file_err,
+                )
+                .expect("Writing to memory does not fail");
+
+                searched
+                    .lock()
+                    .unwrap()
+                    .push((index, (summary, file_out, file_err)));
+            });
+        }
+    });
+
+    let mut searched = searched.into_inner().unwrap();
+    searched.sort_by_key(|(index, _)| *index);
+    searched.into_iter().map(|(_, search)| search).collect()
 }
 
-// Usage: echo <input_text> | your_program.sh -E <pattern>
-fn main() {
-    let Some(pattern_flag_index) = env::args().position(|arg| arg == "-E") else {
-        println!("Pattern argument '-E' is required");
-        process::exit(1);
+/// Searches a single file for the pattern, writing the results to `out` and messages
+/// about the file to `err`. The first group of lines printed with context is separated
+/// from the groups before it if `is_group_printed` is true. Files that cannot be read
+/// are reported to `err`, only errors writing to `out` or `err` are returned.
+fn grep_file(
+    regex: &Regex,
+    filename: &str,
+    options: &Options,
+    mut is_group_printed: bool,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> io::Result<FileSummary> {
+    let file = display_name(filename);
+
+    if filename != STDIN_FILE && Path::new(filename).is_dir() {
+        // Directories are only searched with -r, skip them like GNU grep does.
+        if !options.suppress_errors {
+            writeln!(err, "grep: {}: Is a directory", file)?;
+        }
+        return Ok(FileSummary::default());
+    }
+
+    // An unreadable file is reported, the search of the others continues.
+    let separator = options.line_separator();
+    let max_len = options.max_line_length;
+    let lines = match read_lines(filename, separator, max_len, options.decompress) {
+        Ok(lines) => lines,
+        Err(error) => {
+            if !options.suppress_errors {
+                writeln!(err, "grep: {}: {}", file, file_error_message(&error))?;
+            }
+            return Ok(FileSummary {
+                has_error: true,
+                ..Default::default()
+            });
+        }
+    };
+
+    let mut file_match_count = 0;
+    let mut file_total_match_count = 0;
+    let mut has_groups = false;
+    let mut has_error = false;
+
+    // Unselected lines that may still be printed as context before the next selected line.
+    let mut before_context = VecDeque::new();
+    let mut after_context_left = 0;
+    let mut last_printed_index = None;
+    let mut next_line_offset = 0;
+
+    for (index, line) in lines.enumerate() {
+        // A file failing to be read is reported like one failing to be opened, what
+        // was found up to there is still printed.
+        let (line, file_len) = match line {
+            Ok(line) => line,
+            Err(error) => {
+                if !options.suppress_errors {
+                    writeln!(err, "grep: {}: {}", file, file_error_message(&error))?;
+                }
+                has_error = true;
+                break;
+            }
+        };
+
+        // Without -o, -b prints the byte offset of the line within the file.
+        let line_offset = next_line_offset;
+        next_line_offset += file_len;
+        let line_byte_offset = options.byte_offset.then_some(line_offset);
+        let line_number = options.line_number.then_some(index + 1);
+
+        let Some(bytes) = line else {
+            if !options.suppress_errors {
+                let max = options.max_line_length.unwrap_or_default();
+                let message = format!("line longer than {} bytes skipped", max);
+                writeln!(err, "grep: {}:{}: {}", file, index + 1, message)?;
+            }
+            continue;
+        };
+
+        // Lines are matched like `Regex::is_match_bytes` does, so invalid UTF-8 is not
+        // lost, but whole lines are printed as the bytes they are in the file.
+        let line = decode_bytes(&bytes);
+
+        let is_max_count_reached = options.max_count.is_some_and(|max| file_match_count >= max);
+        if is_max_count_reached && after_context_left == 0 {
+            break;
+        }
+
+        // Once the max count is reached, only the trailing context is printed.
+        let is_selected =
+            !is_max_count_reached && is_line_match(regex, &line, options) != options.invert_match;
+
+        if is_selected && options.quiet {
+            // Nothing is printed, so the first selected line is all that matters.
+            return Ok(FileSummary {
+                match_count: 1,
+                ..Default::default()
+            });
+        }
+
+        if !is_selected {
+            if !options.has_context() {
+                continue;
+            }
+
+            if after_context_left > 0 {
+                after_context_left -= 1;
+                last_printed_index = Some(index);
+                let offset = line_byte_offset;
+                write_output(out, file, line_number, offset, &bytes, options, true)?;
+            } else if options.before_context > 0 {
+                before_context.push_back((index, line_byte_offset, bytes));
+                if before_context.len() > options.before_context {
+                    before_context.pop_front();
+                }
+            }
+
+            continue;
+        }
+
+        file_match_count += 1;
+
+        if options.files_with_matches || options.files_without_match {
+            // The file name is all that is printed, no need to look any further.
+            break;
+        }
+
+        if options.count {
+            continue;
+        }
+
+        if options.count_matches {
+            // Inverted and whole line matches are counted once per line.
+            file_total_match_count += if options.invert_match || options.line_regexp {
+                1
+            } else {
+                regex
+                    .find_iter(&line)
+                    .filter(|span| is_word_match(&line, span, options))
+                    .count()
+            };
+            continue;
+        }
+
+        if options.has_context() {
+            let first_index = before_context.front().map_or(index, |(i, _, _)| *i);
+            let is_adjacent = last_printed_index.is_some_and(|last| last + 1 == first_index);
+
+            if is_group_printed && !is_adjacent {
+                if let Some(separator) = options.group_separator() {
+                    write_group_separator(out, separator, options)?;
+                }
+            }
+
+            for (index, offset, bytes) in before_context.drain(..) {
+                let number = options.line_number.then_some(index + 1);
+                write_output(out, file, number, offset, &bytes, options, true)?;
+            }
+
+            after_context_left = options.after_context;
+            last_printed_index = Some(index);
+            is_group_printed = true;
+            has_groups = true;
+        }
+
+        if options.json {
+            for span in match_spans(regex, &line, options) {
+                write_json(out, file, index + 1, &line, span)?;
+            }
+        } else if options.only_matching {
+            // Empty matches are not printed, they would only produce blank lines.
+            let spans = match_spans(regex, &line, options).filter(|span| !span.is_empty());
+
+            for span in spans {
+                let byte_offset = options.byte_offset.then_some(span.start);
+                let text = span.as_str(&line).as_bytes();
+                write_output(out, file, line_number, byte_offset, text, options, false)?;
+            }
+        } else {
+            let offset = line_byte_offset;
+            write_output(out, file, line_number, offset, &bytes, options, false)?;
+        }
+    }
+
+    let count = if options.count {
+        file_match_count
+    } else {
+        file_total_match_count
     };
 
-    let pattern = env::args().nth(pattern_flag_index + 1).unwrap();
+    if options.is_counted() {
+        if options.prefix {
+            writeln!(out, "{0}:{1}", file, count)?;
+        } else {
+            writeln!(out, "{}", count)?;
+        }
+    }
 
-    let arg_count = env::args().len();
-    let recursive_flag = match env::args().find(|arg| arg == "-r") {
-        Some(_) => true,
-        None => false,
+    let is_listed = if options.files_without_match {
+        file_match_count == 0
+    } else {
+        options.files_with_matches && file_match_count > 0
     };
 
-    if arg_count < 4 {
-        grep_stdin(&pattern);
-    } else if recursive_flag {
-        let mut files = vec![];
-        let directory = env::args().nth(4).unwrap();
+    if is_listed && !options.quiet {
+        writeln!(out, "{}", file)?;
+    }
 
-        let walker = walkdir::WalkDir::new(directory);
-        for file in walker.into_iter().filter_map(|e| e.ok()) {
-            if file.file_type().is_file() {
-                let path = file.path().display().to_string();
-                files.push(path);
+    Ok(FileSummary {
+        match_count: file_match_count,
+        count,
+        is_listed,
+        has_groups,
+        has_error,
+    })
+}
+
+const HELP: &str = r"Usage: grep [OPTION]... -E PATTERN [FILE]...
+       grep [OPTION]... -F -f PATTERN_FILE [FILE]...
+Search for PATTERN in each FILE, or in standard input without FILE.
+A FILE or PATTERN_FILE of - is standard input.
+
+Pattern selection:
+  -E PATTERN                 search for PATTERN
+  -f PATTERN_FILE            with -F, search for any of the strings in PATTERN_FILE
+  -G, --basic-regexp         PATTERN is a basic regular expression
+  -F, --fixed-strings        PATTERN is a string matching itself
+  -P, --perl-regexp          PATTERN is checked for unsupported Perl syntax
+      --regexp-type=TYPE     PATTERN is 'basic' or 'extended'
+  -i, --ignore-case          ignore case distinctions
+  -w, --word-regexp          match only whole words
+  -x, --line-regexp          match only whole lines
+  -z, --null-data            lines are separated by NUL instead of newline
+  -Z, --decompress           search the content of gzip compressed files
+
+Output control:
+  -v                         select non-matching lines
+  -m, --max-count NUM        stop after NUM selected lines per file
+  -n, --line-number          print the line number of each line or, with -o, match
+  -b                         print the byte offset of each line or, with -o, match
+  -o                         print only the matched parts of lines
+      --only=NUM             with -o, print capture group NUM instead
+      --json                 print every match as a JSON object per line
+  -c                         print only the number of selected lines per file
+      --count-matches        print only the number of matches per file
+      --total                with counts, also print their sum
+  -l                         print only the names of files with matches
+  -L                         print only the names of files without matches
+  -q, --quiet, --silent      print nothing, exit at the first selected line
+  -s, --no-messages          do not report unreadable files
+  -T, --initial-tab          align lines after their prefixes with a tab
+  -r                         search directories recursively
+      --max-line-length=NUM  skip lines longer than NUM bytes
+      --max-filesize=NUM     with -r, skip files larger than NUM bytes
+      --threads=NUM          search NUM files at the same time
+
+Context control:
+  -A NUM                     print NUM lines of trailing context
+  -B NUM                     print NUM lines of leading context
+  -C NUM                     print NUM lines of context before and after
+      --group-separator=SEP  separate groups of context with SEP instead of --
+      --no-group-separator   do not separate groups of context
+
+Pattern syntax:
+  a  .  \\                   a literal char, any char, a backslash
+  \d  \w                     a digit, a word char
+  [abc]  [^a-z]  [\d\s]      classes, negated classes, shorthands in classes
+  [a-z&&[^aeiou]]            class intersection
+  ^  $  \G                   start and end of the text, end of the previous match
+  *  +  ?                    zero or more, one or more, zero or one, all greedy
+  {n}  {n,}  {,m}  {n,m}     repetitions, greedy
+  (a|b)  (?<name>a|b)        capture groups, also named as (?P<name>a|b)
+  \1 ... \9                  backreferences to capture groups
+  (?>a)                      atomic group
+  (?=a)  (?!a)               lookaheads
+  (?<=a)  (?<!a)             lookbehinds of a fixed length
+  \Q...\E                    literal text
+  (?#...)                    comment
+  (?x)                       at the start, ignore whitespace and # comments
+
+Miscellaneous:
+      --help                 print this help and exit
+      --version              print the version and exit
+
+Exit status is 0 if a line is selected, 1 if none is and 2 if an error occurred.
+";
+
+/// The flags taking a value, which is either the next argument, the rest of a bundle
+/// of short flags like `-m5`, or given after `=` for long flags like `--max-count=5`.
+const VALUE_FLAGS: &[&str] = &[
+    "-E",
+    "-f",
+    "-m",
+    "--max-count",
+    "-A",
+    "-B",
+    "-C",
+    "--only",
+    "--group-separator",
+    "--regexp-type",
+    "--max-line-length",
+    "--max-filesize",
+    "--threads",
+    "--engine",
+];
+
+/// The flags without a value.
+const SWITCHES: &[&str] = &[
+    "-G",
+    "--basic-regexp",
+    "-F",
+    "--fixed-strings",
+    "-P",
+    "--perl-regexp",
+    "-i",
+    "--ignore-case",
+    "-w",
+    "--word-regexp",
+    "-x",
+    "--line-regexp",
+    "-z",
+    "--null-data",
+    "-v",
+    "-n",
+    "--line-number",
+    "-b",
+    "-o",
+    "-c",
+    "--count-matches",
+    "--total",
+    "--json",
+    "-l",
+    "-L",
+    "-q",
+    "--quiet",
+    "--silent",
+    "-s",
+    "--no-messages",
+    "-Z",
+    "--decompress",
+    "-T",
+    "--initial-tab",
+    "-r",
+    "--no-group-separator",
+    "--help",
+    "--version",
+];
+
+/// The command line arguments, split into flags and operands regardless of their order.
+#[derive(Debug, Default, PartialEq)]
+struct Args {
+    /// The flags without a value, in the order given.
+    switches: Vec<String>,
+
+    /// The flags with their values, in the order given.
+    values: Vec<(String, String)>,
+
+    /// The files and directories to search.
+    operands: Vec<String>,
+}
+
+impl Args {
+    /// Parses the arguments following the program name. Short flags may be bundled like
+    /// `-ri`, and all arguments after `--` are operands. Returns the message to report
+    /// for unknown flags and missing values.
+    fn parse(args: &[String]) -> Result<Args, String> {
+        let mut parsed = Args::default();
+        let mut args = args.iter();
+        let mut is_operands_only = false;
+
+        while let Some(arg) = args.next() {
+            if is_operands_only || arg == "-" || !arg.starts_with('-') {
+                parsed.operands.push(arg.clone());
+            } else if arg == "--" {
+                is_operands_only = true;
+            } else if arg.starts_with("--") {
+                let (flag, value) = match arg.split_once('=') {
+                    Some((flag, value)) => (flag, Some(value.to_string())),
+                    None => (arg.as_str(), None),
+                };
+
+                if VALUE_FLAGS.contains(&flag) {
+                    let value = value.or_else(|| args.next().cloned());
+                    parsed.values.push((flag.to_string(), value.ok_or_else(|| missing(flag))?));
+                } else if SWITCHES.contains(&flag) && value.is_none() {
+                    parsed.switches.push(flag.to_string());
+                } else if SWITCHES.contains(&flag) {
+                    return Err(format!("Flag '{}' does not take a value", flag));
+                } else {
+                    return Err(format!("Unknown flag '{}'", flag));
+                }
+            } else {
+                for (index, char) in arg.char_indices().skip(1) {
+                    let flag = format!("-{}", char);
+
+                    if VALUE_FLAGS.contains(&flag.as_str()) {
+                        // The rest of the bundle is the value, if there is any.
+                        let rest = &arg[index + char.len_utf8()..];
+                        let value = match rest {
+                            "" => args.next().cloned().ok_or_else(|| missing(&flag))?,
+                            rest => rest.to_string(),
+                        };
+
+                        parsed.values.push((flag, value));
+                        break;
+                    } else if SWITCHES.contains(&flag.as_str()) {
+                        parsed.switches.push(flag);
+                    } else {
+                        return Err(format!("Unknown flag '{}'", flag));
+                    }
+                }
             }
         }
 
-        grep_files(&pattern, &files, true);
+        Ok(parsed)
+    }
+
+    /// Returns true if any of the flags, usually a short flag and its long form, is given.
+    fn has_flag(&self, flags: &[&str]) -> bool {
+        self.switches.iter().any(|switch| flags.contains(&switch.as_str()))
+    }
+
+    /// Returns the value of the flags given last, as a later flag overrides earlier ones.
+    fn value(&self, flags: &[&str]) -> Option<&str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(flag, _)| flags.contains(&flag.as_str()))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the value of the flags parsed as a number, reporting it as `name` if invalid.
+    fn number(&self, flags: &[&str], name: &str) -> Result<Option<usize>, String> {
+        self.value(flags)
+            .map(|value| value.parse().map_err(|_| format!("Invalid {} '{}'", name, value)))
+            .transpose()
+    }
+}
+
+/// Returns the message reported for a flag given without its value.
+fn missing(flag: &str) -> String {
+    match flag {
+        "-E" => "Pattern argument '-E' requires a pattern".to_string(),
+        "-f" => "Pattern argument '-f' requires a file".to_string(),
+        _ => format!("Flag '{}' requires a value", flag),
+    }
+}
+
+/// Returns the text printed for `--help` or `--version` if either is given.
+fn info_message(args: &Args) -> Option<String> {
+    if args.has_flag(&["--help"]) {
+        Some(HELP.to_string())
+    } else if args.has_flag(&["--version"]) {
+        Some(format!("grep (codecrafters-grep) {}\n", env!("CARGO_PKG_VERSION")))
     } else {
-        let files: Vec<String> = env::args().skip(3).collect();
-        grep_files(&pattern, &files, files.len() > 1);
+        None
+    }
+}
+
+/// Reports an error writing the output and returns the exit code for it. A closed
+/// output is no error, its reader does not want any more lines, e.g. `head`, so grep
+/// exits quietly like after the last selected line.
+fn write_error_exit_code(error: &io::Error) -> i32 {
+    if error.kind() == io::ErrorKind::BrokenPipe {
+        return 0;
+    }
+
+    eprintln!("grep: write error: {}", error);
+    2
+}
+
+// Usage: echo <input_text> | your_program.sh -E <pattern>
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args = Args::parse(&args).unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        process::exit(2);
+    });
+
+    if let Some(message) = info_message(&args) {
+        let exit_code = io::stdout()
+            .write_all(message.as_bytes())
+            .map_or_else(|error| write_error_exit_code(&error), |()| 0);
+        process::exit(exit_code);
+    }
+
+    // The patterns are either given directly or read from a file, one per line.
+    // An empty pattern is valid and matches every line, but it must be given.
+    let (pattern, is_pattern_file) = match (args.value(&["-E"]), args.value(&["-f"])) {
+        (Some(pattern), None) => (pattern, false),
+        (None, Some(file)) => (file, true),
+        (Some(_), Some(_)) => {
+            eprintln!("Pattern arguments '-E' and '-f' cannot be combined");
+            process::exit(2);
+        }
+        (None, None) => {
+            eprintln!("Pattern argument '-E' is required");
+            process::exit(2);
+        }
+    };
+    let operands = &args.operands;
+
+    let has_flag = |flag: &str| args.has_flag(&[flag]);
+    let number = |flags: &[&str], name: &str| {
+        args.number(flags, name).unwrap_or_else(|message| {
+            eprintln!("{}", message);
+            process::exit(2);
+        })
+    };
+
+    let max_count = number(&["-m", "--max-count"], "max count");
+    let only_group = number(&["--only"], "capture group");
+
+    let context = number(&["-C"], "context length");
+    let after_context = number(&["-A"], "context length").or(context).unwrap_or(0);
+    let before_context = number(&["-B"], "context length").or(context).unwrap_or(0);
+
+    let max_line_length = number(&["--max-line-length"], "max line length");
+    let max_filesize = number(&["--max-filesize"], "max file size");
+    let threads = number(&["--threads"], "thread count").unwrap_or(1);
+    let group_separator = args.value(&["--group-separator"]).map(String::from);
+
+    let is_basic = match args.value(&["--regexp-type"]) {
+        Some("basic") => true,
+        Some("extended") => false,
+        Some(other) => {
+            eprintln!("Invalid regexp type '{}', expected 'basic' or 'extended'", other);
+            process::exit(2);
+        }
+        None => has_flag("-G") || has_flag("--basic-regexp"),
+    };
+
+    let is_fixed = has_flag("-F") || has_flag("--fixed-strings");
+    let is_perl = has_flag("-P") || has_flag("--perl-regexp");
+    if is_perl && (is_fixed || is_basic) {
+        eprintln!("Conflicting matchers specified");
+        process::exit(2);
+    }
+
+    if is_pattern_file && !is_fixed {
+        eprintln!("Pattern argument '-f' requires -F");
+        process::exit(2);
+    }
+
+    let regex = if is_pattern_file {
+        let patterns = read_patterns(pattern).unwrap_or_else(|error| {
+            eprintln!("grep: {}: {}", pattern, file_error_message(&error));
+            process::exit(2);
+        });
+        let patterns: Vec<&str> = patterns.iter().map(String::as_str).collect();
+
+        Ok(Regex::compile_fixed_set(&patterns))
+    } else if is_fixed {
+        Ok(Regex::compile_fixed(pattern))
+    } else if is_basic {
+        Regex::compile_basic_with_limits(pattern, Limits::default())
+    } else {
+        // Extended syntax is the richest one supported, so Perl-compatible patterns
+        // are compiled in it too. Their other constructs are reported instead of misparsed.
+        Regex::compile_with_limits(pattern, Limits::default())
+    };
+
+    let mut regex = regex.unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(2);
+    });
+
+    if has_flag("-i") || has_flag("--ignore-case") {
+        regex = regex.ignore_case();
+    }
+
+    // Undocumented, selects the engine to compare the engines on the same input.
+    let engine = match args.value(&["--engine"]) {
+        Some("backtrack") | None => Engine::Backtrack,
+        Some("nfa") => Engine::Nfa,
+        Some(other) => {
+            eprintln!("Invalid engine '{}', expected 'backtrack' or 'nfa'", other);
+            process::exit(2);
+        }
+    };
+
+    let regex = regex.with_engine(engine).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(2);
+    });
+
+    if let Some(group) = only_group.filter(|&group| group > regex.captures_len()) {
+        eprintln!(
+            "Capture group {} does not exist, the highest group of the pattern is {}",
+            group,
+            regex.captures_len()
+        );
+        process::exit(2);
+    }
+
+    let mut options = Options {
+        quiet: has_flag("-q") || has_flag("--quiet") || has_flag("--silent"),
+        files_with_matches: has_flag("-l"),
+        files_without_match: has_flag("-L"),
+        invert_match: has_flag("-v"),
+        count: has_flag("-c"),
+        count_matches: has_flag("--count-matches"),
+        total: has_flag("--total"),
+        max_count,
+        only_matching: has_flag("-o"),
+        only_group,
+        json: has_flag("--json"),
+        line_number: has_flag("-n") || has_flag("--line-number"),
+        byte_offset: has_flag("-b"),
+        initial_tab: has_flag("-T") || has_flag("--initial-tab"),
+        null_data: has_flag("-z") || has_flag("--null-data"),
+        line_regexp: has_flag("-x") || has_flag("--line-regexp"),
+        word_regexp: has_flag("-w") || has_flag("--word-regexp"),
+        after_context,
+        before_context,
+        group_separator,
+        no_group_separator: has_flag("--no-group-separator"),
+        suppress_errors: has_flag("-s") || has_flag("--no-messages"),
+        max_line_length,
+        decompress: has_flag("-Z") || has_flag("--decompress"),
+        threads,
+        ..Default::default()
+    };
+
+    if let Err(message) = options.validate() {
+        eprintln!("{}", message);
+        process::exit(2);
+    }
+
+    let searched = if operands.is_empty() {
+        let stdin = [STDIN_FILE.to_string()];
+        grep_files(&regex, &stdin, &options, &mut io::stdout(), &mut io::stderr())
+    } else if has_flag("-r") {
+        let max_size = max_filesize.map(|max| max as u64);
+        let files = find_files(operands, max_size);
+
+        options.prefix = operands.len() > 1 || operands.iter().any(|o| Path::new(o).is_dir());
+        grep_files(&regex, &files, &options, &mut io::stdout(), &mut io::stderr())
+    } else {
+        options.prefix = operands.len() > 1;
+        grep_files(&regex, operands, &options, &mut io::stdout(), &mut io::stderr())
+    };
+
+    // The output is flushed before exiting, which may fail like any other write.
+    let exit_code = searched
+        .and_then(|exit_code| io::stdout().flush().map(|()| exit_code))
+        .unwrap_or_else(|error| write_error_exit_code(&error));
+
+    process::exit(exit_code);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// Creates the given files in a new temporary directory,
+    /// returning the directory and the paths of the created files.
+    fn create_files(files: &[(&str, &str)]) -> (TempDir, Vec<String>) {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = files
+            .iter()
+            .map(|(name, content)| {
+                let path = dir.path().join(name);
+                fs::write(&path, content).unwrap();
+                path.display().to_string()
+            })
+            .collect();
+
+        (dir, paths)
+    }
+
+    fn run_grep_files(pattern: &str, files: &[String], options: &Options) -> (i32, String) {
+        run_grep_files_regex(&Regex::compile(pattern), files, options)
+    }
+
+    fn run_grep_files_regex(regex: &Regex, files: &[String], options: &Options) -> (i32, String) {
+        let (exit_code, output, _) = run_grep_files_with_errors(regex, files, options);
+        (exit_code, output)
+    }
+
+    /// Like `run_grep_files_regex`, additionally returning what was written to stderr.
+    fn run_grep_files_with_errors(
+        regex: &Regex,
+        files: &[String],
+        options: &Options,
+    ) -> (i32, String, String) {
+        let mut out = vec![];
+        let mut err = vec![];
+        let exit_code = grep_files(regex, files, options, &mut out, &mut err).unwrap();
+
+        (
+            exit_code,
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_grep_files_single_file() {
+        let (_dir, files) = create_files(&[("fruits.txt", "apple\nbanana\ncherry\n")]);
+
+        let (exit_code, output) = run_grep_files("an", &files, &Options::default());
+
+        assert_eq!(0, exit_code);
+        assert_eq!("banana\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_multiple_files_golden_output() {
+        let (_dir, files) = create_files(&[
+            ("a.txt", "x\nno\n"),
+            ("b.txt", "yes x\nx"),
+            ("c.txt", "none\n"),
+        ]);
+        let options = Options {
+            prefix: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("x", &files, &options);
+
+        // Every line is terminated, including the last line of a file without a newline.
+        let expected = format!("{0}:x\n{1}:yes x\n{1}:x\n", files[0], files[1]);
+        assert_eq!(0, exit_code);
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_grep_files_empty_pattern() {
+        let (_dir, files) = create_files(&[("fruits.txt", "apple\n\ncherry\n")]);
+
+        let (exit_code, output) = run_grep_files("", &files, &Options::default());
+
+        assert_eq!(0, exit_code);
+        assert_eq!("apple\n\ncherry\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_no_match() {
+        let (_dir, files) = create_files(&[("fruits.txt", "apple\nbanana\ncherry\n")]);
+
+        let (exit_code, output) = run_grep_files("kiwi", &files, &Options::default());
+
+        assert_eq!(1, exit_code);
+        assert_eq!("", output);
+    }
+
+    #[test]
+    fn test_grep_files_files_with_matches() {
+        let (_dir, files) = create_files(&[
+            ("fruits.txt", "apple\nbanana\n"),
+            ("vegetables.txt", "carrot\npea\n"),
+        ]);
+        let options = Options {
+            files_with_matches: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("apple", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!(format!("{}\n", files[0]), output);
+    }
+
+    #[test]
+    fn test_grep_files_files_without_match() {
+        let (_dir, files) = create_files(&[
+            ("fruits.txt", "apple\nbanana\n"),
+            ("vegetables.txt", "carrot\npea\n"),
+        ]);
+        let options = Options {
+            files_without_match: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("apple", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!(format!("{}\n", files[1]), output);
+    }
+
+    #[test]
+    fn test_grep_files_files_without_match_all_files_match() {
+        let (_dir, files) = create_files(&[
+            ("fruits.txt", "apple\nbanana\n"),
+            ("more_fruits.txt", "pineapple\n"),
+        ]);
+        let options = Options {
+            files_without_match: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("apple", &files, &options);
+
+        assert_eq!(1, exit_code);
+        assert_eq!("", output);
+    }
+
+    #[test]
+    fn test_grep_files_invert_match() {
+        let (_dir, files) = create_files(&[("fruits.txt", "apple\nbanana\ncherry\n")]);
+        let options = Options {
+            invert_match: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("an", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("apple\ncherry\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_count() {
+        let (_dir, files) = create_files(&[("fruits.txt", "apple\nbanana\nmango\ncherry\n")]);
+        let options = Options {
+            count: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("an", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("2\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_count_no_match() {
+        let (_dir, files) = create_files(&[("fruits.txt", "apple\nbanana\n")]);
+        let options = Options {
+            count: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("kiwi", &files, &options);
+
+        assert_eq!(1, exit_code);
+        assert_eq!("0\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_count_invert_match() {
+        let (_dir, files) = create_files(&[("fruits.txt", "apple\nbanana\nmango\ncherry\nkiwi\n")]);
+        let options = Options {
+            count: true,
+            invert_match: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("an", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("3\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_count_max_count() {
+        let (_dir, files) = create_files(&[("fruits.txt", "banana\nmango\norange\ncherry\n")]);
+        let options = Options {
+            count: true,
+            max_count: Some(2),
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("an", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("2\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_count_multiple_files() {
+        let (_dir, files) = create_files(&[
+            ("fruits.txt", "apple\nbanana\nmango\n"),
+            ("vegetables.txt", "carrot\npea\n"),
+        ]);
+        let options = Options {
+            prefix: true,
+            count: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("an", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!(format!("{}:2\n{}:0\n", files[0], files[1]), output);
+    }
+
+    #[test]
+    fn test_grep_files_quiet_count() {
+        let (_dir, files) = create_files(&[
+            ("fruits.txt", "apple\nbanana\nmango\n"),
+            ("vegetables.txt", "carrot\npea\n"),
+        ]);
+        let options = Options {
+            prefix: true,
+            quiet: true,
+            count: true,
+            total: true,
+            ..Default::default()
+        };
+
+        assert_eq!((0, String::new()), run_grep_files("an", &files, &options));
+        assert_eq!((1, String::new()), run_grep_files("kiwi", &files, &options));
+    }
+
+    #[test]
+    fn test_grep_files_quiet_prints_nothing() {
+        let (_dir, files) = create_files(&[("fruits.txt", "apple\nbanana\nmango\n")]);
+        let options = Options {
+            quiet: true,
+            before_context: 1,
+            only_matching: true,
+            ..Default::default()
+        };
+
+        assert_eq!((0, String::new()), run_grep_files("mango", &files, &options));
+        assert_eq!((1, String::new()), run_grep_files("kiwi", &files, &options));
+    }
+
+    #[test]
+    fn test_grep_files_quiet_after_error() {
+        let (dir, files) = create_files(&[("fruits.txt", "apple\n")]);
+        let missing = dir.path().join("missing.txt").display().to_string();
+        let operands = vec![missing.clone(), files[0].clone()];
+        let options = Options {
+            quiet: true,
+            ..Default::default()
+        };
+
+        let regex = Regex::compile("apple");
+        let (exit_code, output, errors) = run_grep_files_with_errors(&regex, &operands, &options);
+
+        // A match found after an unreadable file still succeeds, like in GNU grep.
+        assert_eq!(0, exit_code);
+        assert_eq!("", output);
+        assert_eq!(format!("grep: {}: No such file or directory\n", missing), errors);
+    }
+
+    #[test]
+    fn test_grep_files_invalid_utf8() {
+        let (_dir, files) = create_files(&[("fruits.txt", "")]);
+        fs::write(&files[0], b"apple\n\xff\napple\n").unwrap();
+        let options = Options {
+            count: true,
+            ..Default::default()
+        };
+
+        let regex = Regex::compile("apple");
+        let (exit_code, output, errors) = run_grep_files_with_errors(&regex, &files, &options);
+
+        // The lines after the invalid one are still searched.
+        assert_eq!(0, exit_code);
+        assert_eq!("2\n", output);
+        assert_eq!("", errors);
+    }
+
+    #[test]
+    fn test_grep_files_invalid_utf8_lines_printed_as_bytes() {
+        let (_dir, files) = create_files(&[("menu.txt", "")]);
+        fs::write(&files[0], b"caf\xe9 au lait\ncaf\xc3\xa9\ntea\n").unwrap();
+
+        // Each invalid byte is matched as the char with its value.
+        let mut out = vec![];
+        let regex = Regex::compile("caf.");
+        let exit_code = grep_files(&regex, &files, &Options::default(), &mut out, &mut vec![]);
+
+        assert_eq!(0, exit_code.unwrap());
+        assert_eq!(b"caf\xe9 au lait\ncaf\xc3\xa9\n".to_vec(), out);
+
+        let mut out = vec![];
+        let regex = Regex::compile("é$");
+        grep_files(&regex, &files, &Options::default(), &mut out, &mut vec![]).unwrap();
+
+        assert_eq!(b"caf\xc3\xa9\n".to_vec(), out);
+    }
+
+    #[test]
+    fn test_grep_files_read_error() {
+        let (_dir, files) = create_files(&[("pets.gz", "")]);
+        fs::write(&files[0], [&GZIP_MAGIC[..], b"not compressed"].concat()).unwrap();
+        let options = Options {
+            count: true,
+            decompress: true,
+            ..Default::default()
+        };
+
+        let regex = Regex::compile("cat");
+        let (exit_code, output, errors) = run_grep_files_with_errors(&regex, &files, &options);
+
+        // A file failing to be read is reported like one failing to be opened.
+        assert_eq!(2, exit_code);
+        assert_eq!("0\n", output);
+        assert!(errors.starts_with(&format!("grep: {}: ", files[0])), "{}", errors);
+    }
+
+    /// Output whose reader went away, so every write fails.
+    struct ClosedOutput;
+
+    impl Write for ClosedOutput {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_grep_files_write_error() {
+        let (_dir, files) = create_files(&[("fruits.txt", "apple
+banana
+")]);
+        let options = Options::default();
+
+        let regex = Regex::compile("an");
+        let error = grep_files(&regex, &files, &options, &mut ClosedOutput, &mut vec![]);
+        assert_eq!(io::ErrorKind::BrokenPipe, error.unwrap_err().kind());
+
+        // Nothing is written without a selected line.
+        let regex = Regex::compile("kiwi");
+        let exit_code = grep_files(&regex, &files, &options, &mut ClosedOutput, &mut vec![]);
+        assert_eq!(1, exit_code.unwrap());
+    }
+
+    #[test]
+    fn test_grep_files_count_total() {
+        let (_dir, files) = create_files(&[
+            ("fruits.txt", "apple\nbanana\nmango\n"),
+            ("vegetables.txt", "carrot\npea\n"),
+            ("nuts.txt", "pecan\ncashew\n"),
+        ]);
+        let options = Options {
+            prefix: true,
+            count: true,
+            total: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("an", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!(
+            format!("{}:2\n{}:0\n{}:1\ntotal:3\n", files[0], files[1], files[2]),
+            output
+        );
+    }
+
+    #[test]
+    fn test_grep_files_count_matches_total() {
+        let (_dir, files) = create_files(&[
+            ("fruits.txt", "banana\n"),
+            ("nuts.txt", "pecan\n"),
+        ]);
+        let options = Options {
+            count_matches: true,
+            total: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("an", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("2\n1\ntotal:3\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_total_without_count() {
+        let (_dir, files) = create_files(&[("fruits.txt", "banana\n")]);
+        let options = Options {
+            total: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("an", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("banana\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_count_matches() {
+        let (_dir, files) = create_files(&[("fruits.txt", "banana\nmango\ncherry\n")]);
+        let options = Options {
+            count_matches: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("(an|a$)", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("4\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_count_matches_single_line() {
+        let (_dir, files) = create_files(&[
+            ("fruits.txt", "banana bandana\n"),
+            ("vegetables.txt", "pea\n"),
+        ]);
+        let options = Options {
+            prefix: true,
+            count_matches: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("an\\w", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!(format!("{}:3\n{}:0\n", files[0], files[1]), output);
+    }
+
+    #[test]
+    fn test_grep_files_max_count() {
+        let (_dir, files) = create_files(&[("fruits.txt", "banana\nmango\norange\n")]);
+        let options = Options {
+            max_count: Some(2),
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("an", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("banana\nmango\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_max_count_per_file_recursive() {
+        let (dir, _) = create_files(&[
+            ("fruits.txt", "banana\nmango\norange\n"),
+            ("vegetables.txt", "bean\npecan\nlentil\nsoybean\n"),
+        ]);
+        let files = find_files(&[dir.path().display().to_string()], None);
+        let options = Options {
+            prefix: true,
+            max_count: Some(1),
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("an", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!(format!("{}:banana\n{}:bean\n", files[0], files[1]), output);
+    }
+
+    #[test]
+    fn test_grep_files_max_count_zero() {
+        let (_dir, files) = create_files(&[("fruits.txt", "banana\n")]);
+        let options = Options {
+            max_count: Some(0),
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("an", &files, &options);
+
+        assert_eq!(1, exit_code);
+        assert_eq!("", output);
+    }
+
+    #[test]
+    fn test_grep_files_fixed_strings() {
+        let (_dir, files) = create_files(&[("patterns.txt", "axb\na.b\na+b\n")]);
+
+        let (exit_code, output) =
+            run_grep_files_regex(&Regex::compile_fixed("a.b"), &files, &Options::default());
+
+        assert_eq!(0, exit_code);
+        assert_eq!("a.b\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_fixed_strings_only_matching() {
+        let (_dir, files) = create_files(&[("patterns.txt", "xa.by\naxb\na.b a.b\n")]);
+        let options = Options {
+            only_matching: true,
+            byte_offset: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) =
+            run_grep_files_regex(&Regex::compile_fixed("a.b"), &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("1:a.b\n0:a.b\n4:a.b\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_fixed_string_set() {
+        // Numbered items are prefixes of each other, like "item1" of "item10".
+        let mut words: Vec<String> = (0..99).map(|i| format!("item{}", i)).collect();
+        words.push("a.b".to_string());
+        let (_dir, files) = create_files(&[
+            ("words.txt", &(words.join("\n") + "\n")),
+            ("input.txt", "found item42 here\nnothing\nitem100\naxb\nsee a.b\n"),
+        ]);
+
+        let patterns = read_patterns(&files[0]).unwrap();
+        assert_eq!(100, patterns.len());
+
+        let patterns: Vec<&str> = patterns.iter().map(String::as_str).collect();
+        let regex = Regex::compile_fixed_set(&patterns);
+        let input = &files[1..];
+
+        let (exit_code, output) = run_grep_files_regex(&regex, input, &Options::default());
+        assert_eq!(0, exit_code);
+        assert_eq!("found item42 here\nitem100\nsee a.b\n", output);
+
+        let options = Options {
+            only_matching: true,
+            ..Default::default()
+        };
+        let (exit_code, output) = run_grep_files_regex(&regex, input, &options);
+        assert_eq!(0, exit_code);
+        assert_eq!("item42\nitem10\na.b\n", output);
+    }
+
+    #[test]
+    fn test_read_patterns() {
+        let (_dir, files) = create_files(&[("words.txt", "cat\r\n\ndog")]);
+
+        assert_eq!(vec!["cat", "", "dog"], read_patterns(&files[0]).unwrap());
+        assert!(read_patterns("does-not-exist.txt").is_err());
+    }
+
+    #[test]
+    fn test_grep_files_ignore_case() {
+        let (_dir, files) = create_files(&[("animals.txt", "Dog\nCAT\nbird\n")]);
+        let regex = Regex::compile("^[a-d][a-t]+$").ignore_case();
+
+        let (exit_code, output) = run_grep_files_regex(&regex, &files, &Options::default());
+
+        assert_eq!(0, exit_code);
+        assert_eq!("Dog\nCAT\nbird\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_json() {
+        let (_dir, files) = create_files(&[("sizes.txt", "none\nw=10px h=\"20px\"\n")]);
+        let options = Options {
+            json: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("\\d+px", &files, &options);
+        let objects: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(0, exit_code);
+        assert_eq!(2, objects.len());
+        assert_eq!(
+            serde_json::json!({
+                "path": files[0],
+                "line_number": 2,
+                "line": "w=10px h=\"20px\"",
+                "start": 2,
+                "end": 6,
+            }),
+            objects[0]
+        );
+        assert_eq!(10, objects[1]["start"]);
+        assert_eq!(14, objects[1]["end"]);
+    }
+
+    #[test]
+    fn test_grep_files_json_group_and_whole_line() {
+        let (_dir, files) = create_files(&[("sizes.txt", "w=10px\n")]);
+        let mut options = Options {
+            json: true,
+            only_group: Some(1),
+            ..Default::default()
+        };
+
+        let (_, output) = run_grep_files("(\\d+)px", &files, &options);
+        let object: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!((2, 4), (object["start"].as_u64().unwrap(), object["end"].as_u64().unwrap()));
+
+        options.line_regexp = true;
+        let (_, output) = run_grep_files("w=(\\d+)px", &files, &options);
+        let object: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!((0, 6), (object["start"].as_u64().unwrap(), object["end"].as_u64().unwrap()));
+    }
+
+    #[test]
+    fn test_options_validate_invert_match_json() {
+        let options = Options {
+            invert_match: true,
+            json: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Err("Options '-v' and '--json' cannot be combined".to_string()),
+            options.validate()
+        );
+    }
+
+    #[test]
+    fn test_grep_files_only_matching() {
+        let (_dir, files) = create_files(&[("sizes.txt", "w=10px h=20px\nnone\nd=3px\n")]);
+        let options = Options {
+            only_matching: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("\\d+px", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("10px\n20px\n3px\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_line_number() {
+        let (_dir, files) = create_files(&[("pets.txt", "cat
+dog
+cats
+")]);
+        let options = Options {
+            line_number: true,
+            before_context: 1,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("cats", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("2-dog\n3:cats\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_only_matching_line_number_prefix() {
+        let (_dir, files) = create_files(&[
+            ("a.txt", "w=10px h=20px\nnone\n"),
+            ("b.txt", "none\nd=3px\n"),
+        ]);
+        let options = Options {
+            prefix: true,
+            only_matching: true,
+            line_number: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("\\d+px", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!(
+            format!("{0}:1:10px\n{0}:1:20px\n{1}:2:3px\n", files[0], files[1]),
+            output
+        );
+    }
+
+    #[test]
+    fn test_grep_files_only_matching_line_number_byte_offset() {
+        let (_dir, files) = create_files(&[("sizes.txt", "none\nw=10px h=20px\n")]);
+        let options = Options {
+            only_matching: true,
+            line_number: true,
+            byte_offset: true,
+            initial_tab: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("\\d+px", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("2:2:\t10px\n2:9:\t20px\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_only_matching_empty_matches() {
+        // `a*` matches empty before every `b`, which must neither hang nor print blank lines.
+        let (_dir, files) = create_files(&[("runs.txt", "baaab
+bab
+bbb
+")]);
+        let options = Options {
+            only_matching: true,
+            byte_offset: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("a*", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("1:aaa\n1:a\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_byte_offset() {
+        // The first line takes up 6 bytes including its newline, the second 4.
+        let (_dir, files) = create_files(&[("animals.txt", "horse\ncat\ncats\n")]);
+        let options = Options {
+            byte_offset: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("cat", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("6:cat\n10:cats\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_byte_offset_crlf_and_multibyte() {
+        let (_dir, files) = create_files(&[("prices.txt", "€\r\nx 42\r\n")]);
+        let options = Options {
+            byte_offset: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("\\d", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("5:x 42\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_byte_offset_context() {
+        let (_dir, files) = create_files(&[("numbers.txt", "1\n22\nx3\n4\n")]);
+        let options = Options {
+            byte_offset: true,
+            before_context: 1,
+            after_context: 1,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("x", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("2-22\n5:x3\n8-4\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_only_matching_byte_offset() {
+        let (_dir, files) = create_files(&[("sizes.txt", "w=10px h=20px\n")]);
+        let options = Options {
+            only_matching: true,
+            byte_offset: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("\\d+px", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("2:10px\n9:20px\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_initial_tab() {
+        let (_dir, files) = create_files(&[("a.txt", "cat\n"), ("b.txt", "dog\ncats\n")]);
+        let options = Options {
+            prefix: true,
+            byte_offset: true,
+            initial_tab: true,
+            before_context: 1,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("cat", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!(
+            format!("{0}:0:\tcat\n--\n{1}-0-\tdog\n{1}:4:\tcats\n", files[0], files[1]),
+            output
+        );
+    }
+
+    #[test]
+    fn test_grep_files_initial_tab_without_prefix() {
+        let (_dir, files) = create_files(&[("a.txt", "cat\n")]);
+        let options = Options {
+            initial_tab: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("cat", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("cat\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_only_matching_byte_offset_multibyte() {
+        let (_dir, files) = create_files(&[("prices.txt", "€€ 42 ü 7\n")]);
+        let options = Options {
+            only_matching: true,
+            byte_offset: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("\\d+", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("7:42\n13:7\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_only_matching_group() {
+        let (_dir, files) = create_files(&[("sizes.txt", "w=10px h=20px\nnone\n")]);
+        let options = Options {
+            only_matching: true,
+            only_group: Some(1),
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("(\\d+)px", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("10\n20\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_only_matching_group_word_regexp() {
+        let (_dir, files) = create_files(&[("sizes.txt", "w=10px h=20pxs d=30px\n")]);
+        let options = Options {
+            only_matching: true,
+            only_group: Some(1),
+            word_regexp: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("(\\d+)px", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("10\n30\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_only_matching_group_byte_offset() {
+        let (_dir, files) = create_files(&[("sizes.txt", "w=10px h=em\n")]);
+        let options = Options {
+            only_matching: true,
+            only_group: Some(2),
+            byte_offset: true,
+            ..Default::default()
+        };
+
+        // The second group only participates in the match of "em".
+        let (exit_code, output) = run_grep_files("=((\\d+)px|em)", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("2:10\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_line_regexp() {
+        let (_dir, files) = create_files(&[("animals.txt", "dog\nhotdog\ndogs\ndo\n")]);
+        let options = Options {
+            line_regexp: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("(hot)?dog", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("dog\nhotdog\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_line_regexp_alternation() {
+        let (_dir, files) = create_files(&[("letters.txt", "a\nab\nabc\n")]);
+        let options = Options {
+            line_regexp: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("(a|ab)", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("a\nab\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_only_matching_line_regexp_byte_offset() {
+        // Like every match printed by `-o`, the whole line is prefixed with its offset
+        // within the line.
+        let (_dir, files) = create_files(&[("animals.txt", "hotdog\ndog\n")]);
+        let options = Options {
+            only_matching: true,
+            line_regexp: true,
+            byte_offset: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("dog", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("0:dog\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_line_regexp_invert_match() {
+        let (_dir, files) = create_files(&[("animals.txt", "dog\nhotdog\n")]);
+        let options = Options {
+            line_regexp: true,
+            invert_match: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("dog", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("hotdog\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_word_regexp() {
+        let (_dir, files) = create_files(&[(
+            "animals.txt",
+            "cat\ncat nap\nmy cat\na cat here\ncats\nscat\nconcatenate\n",
+        )]);
+        let options = Options {
+            word_regexp: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("cat", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("cat\ncat nap\nmy cat\na cat here\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_word_regexp_later_match() {
+        let (_dir, files) = create_files(&[("animals.txt", "cats and a cat\n")]);
+        let options = Options {
+            word_regexp: true,
+            only_matching: true,
+            byte_offset: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("cat", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("11:cat\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_context() {
+        let (_dir, files) = create_files(&[("numbers.txt", "1\n2\nx3\n4\n5\n6\n7\nx8\n9\n")]);
+        let options = Options {
+            after_context: 1,
+            before_context: 1,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("x", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("2\nx3\n4\n--\n7\nx8\n9\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_context_adjacent_groups() {
+        let (_dir, files) = create_files(&[("numbers.txt", "x1\n2\nx3\n4\n")]);
+        let options = Options {
+            after_context: 1,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("x", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("x1\n2\nx3\n4\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_context_prefix() {
+        let (_dir, files) = create_files(&[("a.txt", "1\nx2\n"), ("b.txt", "x3\n")]);
+        let options = Options {
+            prefix: true,
+            before_context: 1,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("x", &files, &options);
+
+        assert_eq!(0, exit_code);
+        let expected = format!("{0}-1\n{0}:x2\n--\n{1}:x3\n", files[0], files[1]);
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_grep_files_custom_group_separator() {
+        let (_dir, files) = create_files(&[("numbers.txt", "x1\n2\n3\n4\nx5\n")]);
+        let options = Options {
+            after_context: 1,
+            group_separator: Some("==".to_string()),
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("x", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("x1\n2\n==\nx5\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_no_group_separator() {
+        let (_dir, files) = create_files(&[("numbers.txt", "x1\n2\n3\n4\nx5\n")]);
+        let options = Options {
+            after_context: 1,
+            group_separator: Some("==".to_string()),
+            no_group_separator: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("x", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("x1\n2\nx5\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_context_max_count() {
+        let (_dir, files) = create_files(&[("numbers.txt", "x1\n2\nx3\n4\n")]);
+        let options = Options {
+            after_context: 2,
+            max_count: Some(1),
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("x", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("x1\n2\nx3\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_skips_directory() {
+        let (dir, files) = create_files(&[("fruits.txt", "apple\nbanana\n")]);
+        let operands = vec![dir.path().display().to_string(), files[0].clone()];
+        let options = Options {
+            prefix: true,
+            ..Default::default()
+        };
+
+        let regex = Regex::compile("apple");
+        let (exit_code, output, errors) = run_grep_files_with_errors(&regex, &operands, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!(format!("{}:apple\n", files[0]), output);
+        assert_eq!(format!("grep: {}: Is a directory\n", operands[0]), errors);
+    }
+
+    #[test]
+    fn test_grep_files_missing_file() {
+        let (dir, files) = create_files(&[("fruits.txt", "apple\nbanana\n")]);
+        let missing = dir.path().join("missing.txt").display().to_string();
+        let operands = vec![missing.clone(), files[0].clone()];
+        let options = Options {
+            prefix: true,
+            ..Default::default()
+        };
+
+        let regex = Regex::compile("apple");
+        let (exit_code, output, errors) = run_grep_files_with_errors(&regex, &operands, &options);
+
+        // The readable file is still searched, but the exit code reports the error.
+        assert_eq!(2, exit_code);
+        assert_eq!(format!("{}:apple\n", files[0]), output);
+        assert_eq!(format!("grep: {}: No such file or directory\n", missing), errors);
+    }
+
+    #[test]
+    fn test_grep_files_max_line_length() {
+        let long_line = format!("apple {}", "x".repeat(100_000));
+        let content = format!("apple\n{}\napples\n", long_line);
+        let (_dir, files) = create_files(&[("fruits.txt", &content)]);
+        let options = Options {
+            byte_offset: true,
+            max_line_length: Some(10),
+            ..Default::default()
+        };
+
+        let regex = Regex::compile("apple");
+        let (exit_code, output, errors) = run_grep_files_with_errors(&regex, &files, &options);
+
+        // The skipped line still counts for the offsets of the following lines.
+        assert_eq!(0, exit_code);
+        assert_eq!(format!("0:apple\n{}:apples\n", 6 + long_line.len() + 1), output);
+        assert_eq!(format!("grep: {}:2: line longer than 10 bytes skipped\n", files[0]), errors);
+    }
+
+    #[test]
+    fn test_grep_files_max_line_length_exact() {
+        let (_dir, files) = create_files(&[("fruits.txt", "apple\r\nkiwi")]);
+        let options = Options {
+            max_line_length: Some(6),
+            ..Default::default()
+        };
+
+        let regex = Regex::compile("(apple|kiwi)");
+        let (exit_code, output, errors) = run_grep_files_with_errors(&regex, &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("apple\nkiwi\n", output);
+        assert_eq!("", errors);
+    }
+
+    #[test]
+    fn test_grep_files_suppress_errors() {
+        let (dir, files) = create_files(&[("fruits.txt", "apple\nbanana\n")]);
+        let missing = dir.path().join("missing.txt").display().to_string();
+        let operands = vec![dir.path().display().to_string(), missing, files[0].clone()];
+        let options = Options {
+            suppress_errors: true,
+            ..Default::default()
+        };
+
+        let regex = Regex::compile("apple");
+        let (exit_code, output, errors) = run_grep_files_with_errors(&regex, &operands, &options);
+
+        assert_eq!(2, exit_code);
+        assert_eq!("apple\n", output);
+        assert_eq!("", errors);
+    }
+
+    #[test]
+    fn test_find_files_directory() {
+        let (dir, files) = create_files(&[("fruits.txt", "apple\n"), ("vegetables.txt", "pea\n")]);
+        fs::create_dir(dir.path().join("nested")).unwrap();
+        fs::write(dir.path().join("nested").join("nuts.txt"), "pecan\n").unwrap();
+
+        let found = find_files(&[dir.path().display().to_string()], None);
+
+        let nested = dir.path().join("nested").join("nuts.txt").display().to_string();
+        assert_eq!(vec![files[0].clone(), nested, files[1].clone()], found);
+    }
+
+    #[test]
+    fn test_find_files_single_file() {
+        let (_dir, files) = create_files(&[("fruits.txt", "apple\n"), ("vegetables.txt", "pea\n")]);
+
+        assert_eq!(vec![files[1].clone()], find_files(&files[1..], None));
+    }
+
+    #[test]
+    fn test_find_files_max_size() {
+        let (dir, files) = create_files(&[
+            ("small.txt", "match\n"),
+            ("exact.txt", "match 123\n"),
+            ("large.bin", "match 1234567890\n"),
+        ]);
+        fs::create_dir(dir.path().join("nested")).unwrap();
+        fs::write(dir.path().join("nested").join("large.txt"), "match 1234567890\n").unwrap();
+
+        let found = find_files(&[dir.path().display().to_string()], Some(10));
+
+        assert_eq!(vec![files[1].clone(), files[0].clone()], found);
+        assert_eq!(4, find_files(&[dir.path().display().to_string()], None).len());
+    }
+
+    /// Returns the content compressed with gzip.
+    fn gzip(content: &str) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(content.as_bytes()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_grep_files_decompress() {
+        let (dir, mut files) = create_files(&[("plain.txt", "cat\ndog\n")]);
+        let compressed = dir.path().join("pets.txt.gz");
+        fs::write(&compressed, gzip("bird\ncats\n")).unwrap();
+        files.push(compressed.display().to_string());
+
+        let options = Options {
+            prefix: true,
+            line_number: true,
+            decompress: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("cat", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!(format!("{}:1:cat\n{}:2:cats\n", files[0], files[1]), output);
+    }
+
+    #[test]
+    fn test_grep_files_decompress_concatenated_members() {
+        let (dir, _) = create_files(&[]);
+        let compressed = dir.path().join("pets.gz");
+        fs::write(&compressed, [gzip("cat\n"), gzip("dog\ncats\n")].concat()).unwrap();
+        let files = vec![compressed.display().to_string()];
+
+        let options = Options {
+            count: true,
+            decompress: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("cat", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("2\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_without_decompress() {
+        let (dir, _) = create_files(&[]);
+        let compressed = dir.path().join("pets.gz");
+        fs::write(&compressed, gzip("cat\n")).unwrap();
+        let files = vec![compressed.display().to_string()];
+
+        let (exit_code, output) = run_grep_files("cat", &files, &Options::default());
+
+        assert_eq!(1, exit_code);
+        assert_eq!("", output);
+    }
+
+    #[test]
+    fn test_grep_files_threads() {
+        let (dir, _) = create_files(&[
+            ("a.txt", "cat\ndog\ncats\nbird\n"),
+            ("b.txt", "dog\n"),
+            ("c.txt", "bird\ncat\n"),
+        ]);
+        fs::create_dir(dir.path().join("nested")).unwrap();
+        for index in 0..20 {
+            let content = format!("line {0}\ncat {0}\nline\n", index);
+            fs::write(dir.path().join("nested").join(format!("{}.txt", index)), content).unwrap();
+        }
+
+        let mut files = find_files(&[dir.path().display().to_string()], None);
+        files.insert(3, dir.path().join("missing.txt").display().to_string());
+
+        let regex = Regex::compile("cat");
+        let option_sets = [
+            Options::default(),
+            Options {
+                line_number: true,
+                before_context: 1,
+                ..Default::default()
+            },
+            Options {
+                count: true,
+                total: true,
+                ..Default::default()
+            },
+            Options {
+                files_without_match: true,
+                ..Default::default()
+            },
+            Options {
+                only_matching: true,
+                byte_offset: true,
+                ..Default::default()
+            },
+            Options {
+                quiet: true,
+                ..Default::default()
+            },
+        ];
+
+        for options in option_sets {
+            let mut options = Options {
+                prefix: true,
+                ..options
+            };
+            let sequential = run_grep_files_with_errors(&regex, &files, &options);
+
+            for threads in [2, 4, 64] {
+                options.threads = threads;
+                let parallel = run_grep_files_with_errors(&regex, &files, &options);
+
+                assert_eq!(sequential, parallel);
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_files_stdin() {
+        let (_dir, files) = create_files(&[("fruits.txt", "apple\n")]);
+        let operands = strings(&["-", &files[0]]);
+
+        assert_eq!(operands, find_files(&operands, None));
+    }
+
+    #[test]
+    fn test_find_files_sorted() {
+        let (dir, _) = create_files(&[
+            ("c.txt", "match c\n"),
+            ("a.txt", "match a\n"),
+            ("b.txt", "match b\n"),
+        ]);
+        fs::create_dir(dir.path().join("b")).unwrap();
+        fs::write(dir.path().join("b").join("z.txt"), "match z\n").unwrap();
+        fs::write(dir.path().join("b").join("y.txt"), "match y\n").unwrap();
+
+        let files = find_files(&[dir.path().display().to_string()], None);
+        let options = Options {
+            only_matching: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("match \\w", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("match a\nmatch y\nmatch z\nmatch b\nmatch c\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_crlf_line_endings() {
+        let (_dir, files) = create_files(&[("animals.txt", "dog\r\ncat\r\nhotdog\r\n")]);
+
+        let (exit_code, output) = run_grep_files("^\\w+g$", &files, &Options::default());
+
+        assert_eq!(0, exit_code);
+        assert_eq!("dog\nhotdog\n", output);
+    }
+
+    #[test]
+    fn test_grep_files_null_data() {
+        let (_dir, files) = create_files(&[("animals.txt", "dog\0cat\nbird\0hotdog")]);
+        let options = Options {
+            null_data: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("(dog|bird)$", &files, &options);
+
+        assert_eq!(0, exit_code);
+        assert_eq!("dog\0cat\nbird\0hotdog\0", output);
+    }
+
+    #[test]
+    fn test_grep_files_null_data_no_match() {
+        let (_dir, files) = create_files(&[("animals.txt", "dog\0cat\0")]);
+        let options = Options {
+            null_data: true,
+            invert_match: true,
+            ..Default::default()
+        };
+
+        let (exit_code, output) = run_grep_files("^\\w+$", &files, &options);
+
+        assert_eq!(1, exit_code);
+        assert_eq!("", output);
+    }
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    fn strings(strings: &[&str]) -> Vec<String> {
+        strings.iter().map(|string| string.to_string()).collect()
+    }
+
+    #[test]
+    fn test_args_parse() {
+        let args = parse(&["-i", "-E", "a+", "file.txt", "dir"]).unwrap();
+
+        assert_eq!(strings(&["-i"]), args.switches);
+        assert_eq!(Some("a+"), args.value(&["-E"]));
+        assert_eq!(strings(&["file.txt", "dir"]), args.operands);
+        assert!(args.has_flag(&["-i", "--ignore-case"]));
+        assert!(!args.has_flag(&["-v"]));
+    }
+
+    #[test]
+    fn test_args_parse_orderings() {
+        let expected = parse(&["-r", "-i", "-E", "x", "a.txt", "b.txt"]).unwrap();
+
+        for args in [
+            &["-E", "x", "-r", "a.txt", "-i", "b.txt"][..],
+            &["a.txt", "-E", "x", "b.txt", "-ri"],
+            &["-ir", "a.txt", "b.txt", "-E", "x"],
+        ] {
+            let parsed = parse(args).unwrap();
+            assert_eq!(Some("x"), parsed.value(&["-E"]), "{:?}", args);
+            assert_eq!(expected.operands, parsed.operands, "{:?}", args);
+            assert!(parsed.has_flag(&["-r"]) && parsed.has_flag(&["-i"]), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_args_parse_bundled_short_flags() {
+        let args = parse(&["-rci", "-m5", "-A", "2", "-vE", "-x", "f"]).unwrap();
+
+        assert_eq!(strings(&["-r", "-c", "-i", "-v"]), args.switches);
+        assert_eq!(Ok(Some(5)), args.number(&["-m"], "max count"));
+        assert_eq!(Ok(Some(2)), args.number(&["-A"], "context length"));
+
+        // A value flag takes the next argument, even if it looks like a flag.
+        assert_eq!(Some("-x"), args.value(&["-E"]));
+        assert_eq!(strings(&["f"]), args.operands);
+    }
+
+    #[test]
+    fn test_args_parse_long_flags() {
+        let args = parse(&["--max-count=3", "--group-separator", "==", "--count-matches"]).unwrap();
+
+        assert_eq!(Some("3"), args.value(&["-m", "--max-count"]));
+        assert_eq!(Some("=="), args.value(&["--group-separator"]));
+        assert!(args.has_flag(&["--count-matches"]));
+        assert_eq!(Some("a=b"), parse(&["--only=a=b"]).unwrap().value(&["--only"]));
+    }
+
+    #[test]
+    fn test_args_parse_last_value_wins() {
+        let args = parse(&["-m", "1", "--max-count=2", "-m3"]).unwrap();
+
+        assert_eq!(Some("3"), args.value(&["-m", "--max-count"]));
+    }
+
+    #[test]
+    fn test_args_parse_operands_only() {
+        let args = parse(&["-E", "x", "--", "-i", "--help", "-"]).unwrap();
+
+        assert!(args.switches.is_empty());
+        assert_eq!(strings(&["-i", "--help", "-"]), args.operands);
+    }
+
+    #[test]
+    fn test_args_parse_errors() {
+        assert_eq!(Err("Unknown flag '-y'".to_string()), parse(&["-iy"]));
+        assert_eq!(Err("Unknown flag '--bogus'".to_string()), parse(&["--bogus=1"]));
+        assert_eq!(
+            Err("Flag '--total' does not take a value".to_string()),
+            parse(&["--total=1"])
+        );
+        assert_eq!(Err("Flag '-m' requires a value".to_string()), parse(&["-E", "x", "-m"]));
+        assert_eq!(
+            Err("Pattern argument '-E' requires a pattern".to_string()),
+            parse(&["-iE"])
+        );
+        assert_eq!(
+            Err("Invalid max count 'x'".to_string()),
+            parse(&["-mx"]).unwrap().number(&["-m"], "max count")
+        );
+    }
+
+    #[test]
+    fn test_info_message_help() {
+        let help = info_message(&parse(&["--help"]).unwrap()).unwrap();
+
+        assert!(help.starts_with("Usage: grep [OPTION]... -E PATTERN [FILE]...\n"));
+        for expected in ["-i, --ignore-case", "-A NUM", "[^a-z]", "{n,m}", "(?<name>", "\\1"] {
+            assert!(help.contains(expected), "help must mention {}", expected);
+        }
+
+        let args = parse(&["-i", "-E", "a", "--help"]).unwrap();
+        assert_eq!(Some(help), info_message(&args));
+    }
+
+    #[test]
+    fn test_info_message_help_lists_all_flags() {
+        let help = info_message(&parse(&["--help"]).unwrap()).unwrap();
+
+        // The engine flag is only meant for comparing the engines.
+        for flag in VALUE_FLAGS.iter().chain(SWITCHES).filter(|flag| **flag != "--engine") {
+            let is_listed = help
+                .split(|c: char| c.is_whitespace() || c == ',' || c == '=')
+                .any(|word| word == *flag);
+            assert!(is_listed, "help must list {}", flag);
+        }
+    }
+
+    #[test]
+    fn test_info_message_version() {
+        assert_eq!(
+            Some(format!("grep (codecrafters-grep) {}\n", env!("CARGO_PKG_VERSION"))),
+            info_message(&parse(&["--version"]).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_info_message_pattern() {
+        // The value of `-E` is the pattern, even if it looks like a flag.
+        assert_eq!(None, info_message(&parse(&["-E", "--help"]).unwrap()));
+        assert_eq!(None, info_message(&parse(&["-i", "-E", "a"]).unwrap()));
+        assert_eq!(None, info_message(&parse(&[]).unwrap()));
+    }
+
+    #[test]
+    fn test_options_validate() {
+        assert_eq!(Ok(()), Options::default().validate());
+
+        let options = Options {
+            only_matching: true,
+            ..Default::default()
+        };
+        assert_eq!(Ok(()), options.validate());
+    }
+
+    #[test]
+    fn test_options_validate_invert_match_only_matching() {
+        let options = Options {
+            invert_match: true,
+            only_matching: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Err("Options '-v' and '-o' cannot be combined".to_string()),
+            options.validate()
+        );
+    }
+
+    #[test]
+    fn test_trim_line_ending() {
+        for (line, expected) in [("dog\n", "dog"), ("dog\r\n", "dog"), ("dog", "dog"), ("\r", "")] {
+            let mut line = line.as_bytes().to_vec();
+            trim_line_ending(&mut line);
+
+            assert_eq!(expected.as_bytes(), line);
+        }
     }
 }
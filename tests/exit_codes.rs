@@ -0,0 +1,115 @@
+//! Runs the binary to check its exit codes: 0 if a line is selected, 1 if none is
+//! and 2 if an error occurred, like GNU grep. Errors are reported on stderr only.
+
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+/// Runs grep with the arguments and the input on stdin, returning the exit code.
+fn exit_code(args: &[&str], input: &str) -> i32 {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_codecrafters-grep"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    // Grep may exit before reading its input, e.g. for a bad argument, which breaks the pipe.
+    let _ = child.stdin.take().unwrap().write_all(input.as_bytes());
+    child.wait().unwrap().code().unwrap()
+}
+
+#[test]
+fn test_exit_code_stdin() {
+    assert_eq!(0, exit_code(&["-E", "\\d+"], "apple 12\n"));
+    assert_eq!(1, exit_code(&["-E", "\\d+"], "apple\n"));
+}
+
+#[test]
+fn test_exit_code_long_repetition() {
+    let line = format!("{}\n", "ab".repeat(60_000));
+
+    assert_eq!(0, exit_code(&["-E", "^(ab)+$"], &line));
+    assert_eq!(1, exit_code(&["-E", "^(ab)+c"], &line));
+}
+
+#[test]
+fn test_exit_code_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("fruits.txt");
+    fs::write(&file, "apple\nbanana\n").unwrap();
+    let file = file.to_str().unwrap();
+    let missing = dir.path().join("missing.txt");
+    let missing = missing.to_str().unwrap();
+
+    assert_eq!(0, exit_code(&["-E", "an", file], ""));
+    assert_eq!(1, exit_code(&["-E", "cherry", file], ""));
+    assert_eq!(2, exit_code(&["-E", "an", missing], ""));
+    assert_eq!(2, exit_code(&["-E", "an", file, missing], ""));
+    assert_eq!(2, exit_code(&["-s", "-E", "an", missing], ""));
+}
+
+#[test]
+fn test_exit_code_bad_pattern() {
+    assert_eq!(2, exit_code(&["-E", "(a"], "a\n"));
+    assert_eq!(2, exit_code(&["-E", "[a"], "a\n"));
+    assert_eq!(2, exit_code(&["-P", "a(?=b)"], "ab\n"));
+    assert_eq!(2, exit_code(&["--engine", "nfa", "-E", "(a)\\1"], "aa\n"));
+}
+
+#[test]
+fn test_exit_code_bad_arguments() {
+    assert_eq!(2, exit_code(&[], "a\n"));
+    assert_eq!(2, exit_code(&["--unknown", "-E", "a"], "a\n"));
+    assert_eq!(2, exit_code(&["-m", "x", "-E", "a"], "a\n"));
+    assert_eq!(2, exit_code(&["-v", "-o", "-E", "a"], "a\n"));
+    assert_eq!(2, exit_code(&["-o", "--only=5", "-E", "(\\d+)px"], "10px\n"));
+}
+
+#[test]
+fn test_exit_code_info() {
+    assert_eq!(0, exit_code(&["--help"], ""));
+    assert_eq!(0, exit_code(&["--version"], ""));
+}
+
+#[test]
+fn test_exit_code_closed_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("numbers.txt");
+    let numbers: String = (1..300_000).map(|n| format!("{}\n", n)).collect();
+    fs::write(&file, numbers).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_codecrafters-grep"))
+        .args(["-E", "1", file.to_str().unwrap()])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // Like `head -1`, the output is closed after the first line.
+    let mut first_line = String::new();
+    BufReader::new(child.stdout.take().unwrap())
+        .read_line(&mut first_line)
+        .unwrap();
+    assert_eq!("1\n", first_line);
+
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(Some(0), output.status.code());
+    assert!(output.stderr.is_empty(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn test_errors_written_to_stderr() {
+    for args in [&["-E", "(a"][..], &["-m", "x", "-E", "a"], &["-v", "-o", "-E", "a"], &["a"]] {
+        let output = Command::new(env!("CARGO_BIN_EXE_codecrafters-grep"))
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+            .unwrap();
+
+        assert_eq!(Some(2), output.status.code(), "{:?}", args);
+        assert!(output.stdout.is_empty(), "{:?}", args);
+        assert!(!output.stderr.is_empty(), "{:?}", args);
+    }
+}
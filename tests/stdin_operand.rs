@@ -0,0 +1,98 @@
+//! Runs the binary to check that `-` reads standard input, both as a data operand
+//! and as the pattern file of `-f`, and that standard input is searched like a file
+//! without any data operand.
+
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Runs grep with the arguments and the input on stdin, returning the exit code and
+/// the output.
+fn run_grep(args: &[&str], input: &str) -> (i32, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_codecrafters-grep"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    (output.status.code().unwrap(), String::from_utf8(output.stdout).unwrap())
+}
+
+#[test]
+fn test_stdin_data_operand() {
+    let (exit_code, output) = run_grep(&["-E", "an", "-"], "apple\nbanana\nmango\n");
+
+    assert_eq!(0, exit_code);
+    assert_eq!("banana\nmango\n", output);
+}
+
+#[test]
+fn test_stdin_data_operand_with_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("fruits.txt");
+    fs::write(&file, "banana\ncherry\n").unwrap();
+    let file = file.to_str().unwrap();
+
+    let (exit_code, output) = run_grep(&["-n", "-E", "an", file, "-"], "apple\nmango\n");
+
+    assert_eq!(0, exit_code);
+    assert_eq!(format!("{}:1:banana\n(standard input):2:mango\n", file), output);
+}
+
+#[test]
+fn test_stdin_pattern_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("fruits.txt");
+    fs::write(&file, "apple\nbanana\ncherry\n").unwrap();
+    let file = file.to_str().unwrap();
+
+    let (exit_code, output) = run_grep(&["-F", "-f", "-", file], "rr\napp\n");
+
+    assert_eq!(0, exit_code);
+    assert_eq!("apple\ncherry\n", output);
+
+    let (exit_code, output) = run_grep(&["-F", "-f", "-", file], "kiwi\n");
+
+    assert_eq!(1, exit_code);
+    assert_eq!("", output);
+}
+
+#[test]
+fn test_stdin_data_operand_between_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = dir.path().join("first.txt");
+    let second = dir.path().join("second.txt");
+    fs::write(&first, "apple\nbanana\n").unwrap();
+    fs::write(&second, "cherry\nmango\n").unwrap();
+    let (first, second) = (first.to_str().unwrap(), second.to_str().unwrap());
+
+    let (exit_code, output) = run_grep(&["-E", "an", first, "-", second], "plum\norange\n");
+
+    assert_eq!(0, exit_code);
+    assert_eq!(
+        format!("{}:banana\n(standard input):orange\n{}:mango\n", first, second),
+        output
+    );
+
+    let (exit_code, output) = run_grep(&["-c", "-E", "an", first, "-", second], "plum\n");
+
+    assert_eq!(0, exit_code);
+    assert_eq!(format!("{}:1\n(standard input):0\n{}:1\n", first, second), output);
+}
+
+#[test]
+fn test_stdin_without_operands() {
+    let input = "apple\nbanana\nmango\n";
+
+    assert_eq!((0, "banana\nmango\n".to_string()), run_grep(&["-E", "an"], input));
+    assert_eq!((0, "apple\n".to_string()), run_grep(&["-v", "-E", "an"], input));
+    assert_eq!((1, String::new()), run_grep(&["-v", "-E", "a"], input));
+    assert_eq!((0, "2\n".to_string()), run_grep(&["-c", "-E", "an"], input));
+    assert_eq!((1, "0\n".to_string()), run_grep(&["-c", "-E", "kiwi"], input));
+    assert_eq!((0, "2:an\n2:an\n3:an\n".to_string()), run_grep(&["-n", "-o", "-E", "an"], input));
+}